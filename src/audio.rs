@@ -0,0 +1,337 @@
+//! Sound effects and the palettes they're synthesized in. The terminal
+//! plays them on the audio thread in `speaker`, the browser through web
+//! audio in `web`.
+
+use fundsp::prelude32 as dsp;
+
+use crate::game::{BIRD_HITBOX_HH, GameEvent, UiEvent};
+
+// ── Sounds ──────────────────────────────────────────────────────────────────
+pub(crate) const SAMPLE_RATE: u32 = 44_100;
+const DEATH_DURATION: f32 = 0.5;
+// The score chime climbs one semitone per point in a run, topping out an octave up.
+const SCORE_LADDER_MAX: u32 = 12;
+// The whoosh as a pipe goes by is loudest grazing a cap, and this loud through
+// the middle of the gap.
+const WHOOSH_QUIET: f32 = 0.2;
+
+// Sounds requested by the game or the UI, and the palette to make them with
+// from then on.
+pub enum Sound {
+    Game(GameEvent),
+    Ui(UiEvent),
+    Palette(SoundPalette),
+}
+
+impl Sound {
+    // The samples of a one-shot sound, mono at SAMPLE_RATE, for whichever
+    // backend plays them. None for events that make no sound of their own.
+    pub(crate) fn samples(&self, palette: &SoundPalette) -> Option<Vec<f32>> {
+        let (rate, p) = (SAMPLE_RATE, palette);
+        let samples = match *self {
+            Sound::Game(GameEvent::Flap) => generate_flap_samples(rate, p),
+            Sound::Game(GameEvent::Score(score)) => {
+                let step = score.saturating_sub(1).min(SCORE_LADDER_MAX);
+                generate_score_samples(rate, p, step)
+            }
+            Sound::Game(GameEvent::Whoosh(volume)) => generate_whoosh_samples(rate, p, volume),
+            Sound::Game(GameEvent::Milestone) => generate_fanfare_samples(rate, p),
+            Sound::Game(GameEvent::Death) => generate_death_samples(rate, p, DEATH_DURATION),
+            Sound::Game(GameEvent::Bonk) => generate_bonk_samples(rate, p),
+            Sound::Game(_) | Sound::Palette(_) => return None,
+            Sound::Ui(UiEvent::Move) => generate_ui_move_samples(rate, p),
+            Sound::Ui(UiEvent::Select) => generate_ui_select_samples(rate, p),
+            Sound::Ui(UiEvent::Back) => generate_ui_back_samples(rate, p),
+        };
+        Some(palette.level(samples))
+    }
+}
+
+// ── Palettes ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Wave {
+    Sine,
+    Triangle,
+    Square,
+    Saw,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Noise {
+    White,
+    Pink,
+    Brown,
+}
+
+// How a theme's sound effects are made: `wave` replaces the waveform of every
+// tone (None keeps each sound's own), `pitch` scales every frequency, `noise`
+// colors the whooshes and the rush hour hi-hat, and `volume` scales it all.
+// The default is the stock sound.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SoundPalette {
+    pub(crate) wave: Option<Wave>,
+    pub(crate) pitch: f32,
+    pub(crate) noise: Noise,
+    pub(crate) volume: f32,
+}
+
+impl Default for SoundPalette {
+    fn default() -> Self {
+        SoundPalette {
+            wave: None,
+            pitch: 1.0,
+            noise: Noise::White,
+            volume: 1.0,
+        }
+    }
+}
+
+impl SoundPalette {
+    // An oscillator playing the frequency on its input, pitched, in the
+    // palette's waveform or else `stock`.
+    fn osc(&self, stock: Wave) -> dsp::An<dsp::Unit<dsp::U1, dsp::U1>> {
+        let pitch = dsp::mul(self.pitch);
+        dsp::unit(match self.wave.unwrap_or(stock) {
+            Wave::Sine => Box::new(pitch >> dsp::sine()),
+            Wave::Triangle => Box::new(pitch >> dsp::triangle()),
+            Wave::Square => Box::new(pitch >> dsp::square()),
+            Wave::Saw => Box::new(pitch >> dsp::saw()),
+        })
+    }
+
+    // A tone at a fixed frequency.
+    fn tone(
+        &self,
+        stock: Wave,
+        freq: f32,
+    ) -> dsp::An<impl dsp::AudioNode<Inputs = dsp::U0, Outputs = dsp::U1>> {
+        dsp::dc(freq) >> self.osc(stock)
+    }
+
+    fn noise(&self) -> dsp::An<dsp::Unit<dsp::U0, dsp::U1>> {
+        dsp::unit(match self.noise {
+            Noise::White => Box::new(dsp::noise()),
+            Noise::Pink => Box::new(dsp::pink()),
+            Noise::Brown => Box::new(dsp::brown()),
+        })
+    }
+
+    fn level(&self, mut samples: Vec<f32>) -> Vec<f32> {
+        if self.volume != 1.0 {
+            samples.iter_mut().for_each(|s| *s *= self.volume);
+        }
+        samples
+    }
+}
+
+// How loud a pipe going by sounds for a bird at `by`, from how close it is to
+// the nearer edge of the opening, given as world-space top and bottom.
+pub(crate) fn whoosh_volume(by: f64, (top, bot): (f64, f64)) -> f32 {
+    let room = ((bot - top) / 2.0 - BIRD_HITBOX_HH).max(1.0);
+    let off = ((by - (top + bot) / 2.0).abs() / room).min(1.0) as f32;
+    WHOOSH_QUIET + (1.0 - WHOOSH_QUIET) * off * off
+}
+
+fn generate_death_samples(sample_rate: u32, palette: &SoundPalette, duration: f32) -> Vec<f32> {
+    let mut node = (dsp::lfo(|t: f32| dsp::lerp(400.0, 80.0, (t / 0.4).min(1.0)))
+        >> palette.osc(Wave::Saw))
+        * dsp::lfo(|t: f32| dsp::lerp(0.15, 0.0, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+// A soft, short drop in pitch, for kid mode's bounce instead of a crash.
+fn generate_bonk_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    let duration = 0.15;
+    let mut node = (dsp::lfo(|t: f32| dsp::xerp(330.0, 180.0, (t / 0.1).min(1.0)))
+        >> palette.osc(Wave::Sine))
+        * dsp::lfo(|t: f32| dsp::xerp(0.12, 0.001, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+fn generate_flap_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    let duration = 0.12;
+    let mut node = (dsp::lfo(|t: f32| {
+        if t < 0.08 {
+            dsp::xerp(400.0, 800.0, (t / 0.08).min(1.0))
+        } else {
+            800.0
+        }
+    }) >> palette.osc(Wave::Sine))
+        * dsp::lfo(|t: f32| dsp::xerp(0.15, 0.001, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+fn generate_score_samples(sample_rate: u32, palette: &SoundPalette, step: u32) -> Vec<f32> {
+    const NOTES: [f32; 2] = [520.0, 680.0];
+    let pitch = 2f32.powf(step as f32 / 12.0);
+    let note_gap = 0.1f32;
+    let note_len = 0.15f32;
+    let total_duration = note_gap * (NOTES.len() as f32 - 1.0) + note_len;
+    let total_samples = (sample_rate as f32 * total_duration) as usize;
+    let mut samples = vec![0.0f32; total_samples];
+
+    for (idx, freq) in NOTES.iter().enumerate() {
+        let start = (note_gap * idx as f32 * sample_rate as f32) as usize;
+        let mut node = palette.tone(Wave::Sine, *freq * pitch)
+            * dsp::lfo(|t: f32| dsp::xerp(0.12, 0.001, (t / note_len).min(1.0)));
+        let tone = render_mono(&mut node, sample_rate, note_len);
+        for (i, s) in tone.into_iter().enumerate() {
+            let target = start + i;
+            if target < total_samples {
+                samples[target] += s;
+            }
+        }
+    }
+
+    samples
+}
+
+// A rising major arpeggio that holds its top note.
+fn generate_fanfare_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    const NOTES: [(f32, f32); 4] = [(523.0, 0.12), (659.0, 0.12), (784.0, 0.12), (1047.0, 0.4)];
+    let mut samples = Vec::new();
+    for (freq, len) in NOTES {
+        let mut node = (palette.tone(Wave::Sine, freq)
+            + palette.tone(Wave::Sine, freq * 2.0) * 0.3)
+            * dsp::lfo(move |t: f32| dsp::xerp(0.1, 0.002, (t / len).min(1.0)));
+        samples.extend(render_mono(&mut node, sample_rate, len));
+    }
+    samples
+}
+
+// One bar of the rush hour layer, twice the tempo of anything else in the
+// game: a driving eighth-note bass under a hi-hat.
+pub(crate) fn generate_rush_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    const BASS: [f32; 8] = [110.0, 110.0, 220.0, 110.0, 131.0, 131.0, 262.0, 147.0];
+    const EIGHTH: f32 = 1.0 / 6.0;
+    let mut samples = Vec::new();
+    for freq in BASS {
+        let bass = (palette.tone(Wave::Saw, freq) >> dsp::lowpass_hz(900.0, 1.0))
+            * dsp::lfo(|t: f32| dsp::xerp(0.05, 0.002, (t / EIGHTH).min(1.0)));
+        let hat = (palette.noise() >> dsp::highpass_hz(6000.0, 0.7))
+            * dsp::lfo(|t: f32| dsp::xerp(0.03, 0.0005, (t / 0.05).min(1.0)));
+        let mut node = bass + hat;
+        samples.extend(render_mono(&mut node, sample_rate, EIGHTH));
+    }
+    palette.level(samples)
+}
+
+fn generate_whoosh_samples(sample_rate: u32, palette: &SoundPalette, volume: f32) -> Vec<f32> {
+    let duration = 0.08;
+    let mut node = (palette.noise() >> dsp::bandpass_hz(1200.0, 0.5) >> dsp::mul(0.1 * volume))
+        * dsp::lfo(|t: f32| dsp::xerp(0.3, 0.001, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+fn generate_ui_move_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    let duration = 0.04;
+    let mut node = palette.tone(Wave::Sine, 960.0)
+        * dsp::lfo(|t: f32| dsp::xerp(0.06, 0.001, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+fn generate_ui_select_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    let duration = 0.1;
+    let mut node = (dsp::lfo(|t: f32| if t < 0.04 { 660.0 } else { 990.0 })
+        >> palette.osc(Wave::Sine))
+        * dsp::lfo(|t: f32| dsp::xerp(0.08, 0.001, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+fn generate_ui_back_samples(sample_rate: u32, palette: &SoundPalette) -> Vec<f32> {
+    let duration = 0.1;
+    let mut node = (dsp::lfo(|t: f32| if t < 0.04 { 740.0 } else { 494.0 })
+        >> palette.osc(Wave::Sine))
+        * dsp::lfo(|t: f32| dsp::xerp(0.08, 0.001, (t / duration).min(1.0)));
+    render_mono(&mut node, sample_rate, duration)
+}
+
+fn render_mono(node: &mut dyn dsp::AudioUnit, sample_rate: u32, duration: f32) -> Vec<f32> {
+    node.set_sample_rate(sample_rate as f64);
+    node.reset();
+
+    let sample_count = (sample_rate as f32 * duration) as usize;
+    let mut samples = Vec::with_capacity(sample_count);
+    for _ in 0..sample_count {
+        samples.push(node.get_mono());
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speaker::AUDIO_STALL;
+
+    #[test]
+    fn whoosh_is_loudest_grazing_a_cap() {
+        let gap = (20.0, 40.0);
+        let middle = whoosh_volume(30.0, gap);
+        let near_top = whoosh_volume(24.0, gap);
+        let at_top = whoosh_volume(20.0, gap);
+        assert!((middle - WHOOSH_QUIET).abs() < 1e-6);
+        assert!(middle < near_top && near_top < at_top);
+        assert!((at_top - 1.0).abs() < 1e-6);
+        assert!((whoosh_volume(36.0, gap) - near_top).abs() < 1e-6);
+    }
+
+    #[test]
+    fn only_one_shot_sounds_have_samples() {
+        let p = SoundPalette::default();
+        assert!(Sound::Game(GameEvent::Flap).samples(&p).is_some());
+        assert!(Sound::Ui(UiEvent::Back).samples(&p).is_some());
+        assert!(Sound::Game(GameEvent::RushHour(true)).samples(&p).is_none());
+        assert!(Sound::Game(GameEvent::PipeSpawned).samples(&p).is_none());
+        assert!(Sound::Palette(p).samples(&p).is_none());
+    }
+
+    #[test]
+    fn sounds_stay_in_range_in_every_palette() {
+        let chippy = SoundPalette {
+            wave: Some(Wave::Square),
+            pitch: 2.0,
+            ..SoundPalette::default()
+        };
+        let soft = SoundPalette {
+            wave: Some(Wave::Triangle),
+            pitch: 0.5,
+            noise: Noise::Brown,
+            volume: 0.5,
+        };
+        for p in [SoundPalette::default(), chippy, soft] {
+            let sounds = [
+                generate_flap_samples(SAMPLE_RATE, &p),
+                generate_bonk_samples(SAMPLE_RATE, &p),
+                generate_score_samples(SAMPLE_RATE, &p, 0),
+                generate_score_samples(SAMPLE_RATE, &p, 40),
+                generate_whoosh_samples(SAMPLE_RATE, &p, 1.0),
+                generate_ui_select_samples(SAMPLE_RATE, &p),
+                generate_death_samples(SAMPLE_RATE, &p, DEATH_DURATION),
+            ];
+            for samples in sounds {
+                assert!(!samples.is_empty());
+                assert!(samples.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+                assert!(samples.len() as f32 / (SAMPLE_RATE as f32) < AUDIO_STALL.as_secs_f32());
+            }
+        }
+    }
+
+    #[test]
+    fn palettes_change_the_sound() {
+        let stock = SoundPalette::default();
+        let square = SoundPalette {
+            wave: Some(Wave::Square),
+            ..stock
+        };
+        let quiet = SoundPalette {
+            volume: 0.5,
+            ..stock
+        };
+        let flap = Sound::Game(GameEvent::Flap);
+        let loud = flap.samples(&stock).unwrap();
+        assert!(flap.samples(&square).unwrap() != loud);
+        let halved: Vec<f32> = loud.iter().map(|s| s * 0.5).collect();
+        assert!(flap.samples(&quiet).unwrap() == halved);
+    }
+}
@@ -0,0 +1,65 @@
+//! The event bus: hands what happens in a game to every subsystem that
+//! reacts to it, so a new one subscribes instead of being threaded through
+//! the event loop.
+
+use crate::game::{Game, GameEvent};
+
+// ── Event bus ───────────────────────────────────────────────────────────────
+
+type Subscriber<'a> = Box<dyn FnMut(&Game, &GameEvent) + 'a>;
+
+// Subscribers see each event in the order they subscribed, along with the
+// game as it stands after the step or flap that emitted it.
+#[derive(Default)]
+pub struct EventBus<'a> {
+    subscribers: Vec<Subscriber<'a>>,
+}
+
+impl<'a> EventBus<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&Game, &GameEvent) + 'a) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    pub fn publish(&mut self, game: &Game, events: &[GameEvent]) {
+        for event in events {
+            for subscriber in &mut self.subscribers {
+                subscriber(game, event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::State;
+
+    #[test]
+    fn subscribers_see_a_run_start_and_end() {
+        let mut seen = Vec::new();
+        let mut bus = EventBus::new();
+        bus.subscribe(|_, event| seen.push(*event));
+        let mut game = Game::headless(7);
+        while !game.is_over() {
+            let events = game.step(game.state == State::Ready);
+            bus.publish(&game, &events);
+        }
+        drop(bus);
+        let started = |e: &GameEvent| {
+            matches!(
+                e,
+                GameEvent::StateChanged {
+                    from: State::Ready,
+                    to: State::Playing
+                }
+            )
+        };
+        assert!(seen.iter().any(started));
+        assert!(seen.iter().any(|e| matches!(e, GameEvent::PipeSpawned)));
+        assert!(seen.iter().any(|e| matches!(e, GameEvent::Death)));
+    }
+}
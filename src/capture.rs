@@ -0,0 +1,515 @@
+//! Screenshots, GIF recordings and the demo reel, plus the metadata that lets
+//! a picture be replayed.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::FlappyError;
+use crate::game::{Game, SEED_CODES, SIM_FPS, State, decode_seed, encode_seed};
+use crate::pixels::{PixelBuf, Rgb};
+use crate::theme::config_dir;
+
+// ── Screenshots ─────────────────────────────────────────────────────────────
+
+// Where a shared picture came from: enough to play the same run again with
+// FLAPPY_SEED and the same mutators. Exporters take this rather than a Game,
+// and write it in whatever form their format has for text.
+struct MediaMeta {
+    version: &'static str,
+    seed: String,
+    score: u32,
+    mode: String,
+}
+
+impl MediaMeta {
+    // Keyword and text pairs, using PNG's registered keywords where one fits.
+    fn entries(&self) -> [(&'static str, String); 5] {
+        [
+            ("Software", format!("flappy-tui {}", self.version)),
+            ("Seed", self.seed.clone()),
+            ("Score", self.score.to_string()),
+            ("Mode", self.mode.clone()),
+            (
+                "Comment",
+                format!(
+                    "Play this run with FLAPPY_SEED={} in mode {}",
+                    self.seed, self.mode
+                ),
+            ),
+        ]
+    }
+}
+
+// Screenshots are scaled up to about 640 pixels wide, but at most 4x.
+const SCREENSHOT_WIDTH: usize = 640;
+
+pub fn save_screenshot(game: &Game) -> Result<PathBuf, FlappyError> {
+    const WHAT: &str = "a screenshot";
+    let dir = config_dir()
+        .ok_or_else(|| FlappyError::no_asset_dir(WHAT))?
+        .join("screenshots");
+    fs::create_dir_all(&dir).map_err(FlappyError::asset(WHAT, dir.clone()))?;
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = dir.join(format!("flappy-{secs}.png"));
+    let mut buf = PixelBuf::new(game.pw, game.ph);
+    game.draw(&mut buf);
+    let scale = (SCREENSHOT_WIDTH / buf.w.max(1)).clamp(1, 4);
+    fs::write(&path, encode_png(&buf, scale, &game.media_meta()))
+        .map_err(FlappyError::asset(WHAT, path.clone()))?;
+    Ok(path)
+}
+
+// An RGB PNG of the buffer, each pixel drawn as a `scale` square, with the
+// metadata as tEXt chunks.
+fn encode_png(buf: &PixelBuf, scale: usize, meta: &MediaMeta) -> Vec<u8> {
+    let (w, h) = (buf.w * scale, buf.h * scale);
+    let mut raw = Vec::with_capacity((w * 3 + 1) * h);
+    for y in 0..h {
+        raw.push(0);
+        for x in 0..w {
+            let Rgb(r, g, b) = buf.px[y / scale * buf.w + x / scale];
+            raw.extend([r, g, b]);
+        }
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut chunk = |kind: &[u8; 4], data: &[u8]| {
+        png.extend((data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend(kind);
+        png.extend(data);
+        let crc = crc32(&png[start..]);
+        png.extend(crc.to_be_bytes());
+    };
+    let mut header = Vec::new();
+    header.extend((w as u32).to_be_bytes());
+    header.extend((h as u32).to_be_bytes());
+    // 8 bits per channel, RGB, no interlacing.
+    header.extend([8, 2, 0, 0, 0]);
+    chunk(b"IHDR", &header);
+    for (keyword, text) in meta.entries() {
+        // tEXt is Latin-1; anything else would need iTXt.
+        let text: String = text.chars().filter(char::is_ascii).collect();
+        chunk(
+            b"tEXt",
+            &[keyword.as_bytes(), &[0], text.as_bytes()].concat(),
+        );
+    }
+    chunk(b"IDAT", &zlib_compress(&raw, w * 3 + 1));
+    chunk(b"IEND", &[]);
+    png
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                crc >> 1 ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// Deflate lengths and distances: (base, extra bits) for each code.
+#[rustfmt::skip]
+const DEFLATE_LENGTHS: [(u16, u8); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 1), (13, 1),
+    (15, 1), (17, 1), (19, 2), (23, 2), (27, 2), (31, 2), (35, 3), (43, 3), (51, 3), (59, 3),
+    (67, 4), (83, 4), (99, 4), (115, 4), (131, 5), (163, 5), (195, 5), (227, 5), (258, 0),
+];
+#[rustfmt::skip]
+const DEFLATE_DISTANCES: [(u16, u8); 30] = [
+    (1, 0), (2, 0), (3, 0), (4, 0), (5, 1), (7, 1), (9, 2), (13, 2), (17, 3), (25, 3),
+    (33, 4), (49, 4), (65, 5), (97, 5), (129, 6), (193, 6), (257, 7), (385, 7), (513, 8),
+    (769, 8), (1025, 9), (1537, 9), (2049, 10), (3073, 10), (4097, 11), (6145, 11),
+    (8193, 12), (12289, 12), (16385, 13), (24577, 13),
+];
+
+// Bits go out least significant first, as deflate packs them.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are the one thing stored most significant bit first.
+    fn put_code(&mut self, code: u32, len: u32) {
+        self.put(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn literal(&mut self, value: u16) {
+        let v = value as u32;
+        match value {
+            0..=143 => self.put_code(0x30 + v, 8),
+            144..=255 => self.put_code(0x190 + v - 144, 9),
+            256..=279 => self.put_code(v - 256, 7),
+            _ => self.put_code(0xc0 + v - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+// A zlib stream in one fixed-Huffman deflate block. Pictures drawn in blocks
+// of flat color mostly repeat the pixel to the left or the row above, so
+// those are the only matches looked for.
+fn zlib_compress(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    out.put(1, 1);
+    out.put(1, 2);
+    let mut i = 0;
+    while i < data.len() {
+        let (len, dist) = [3, stride]
+            .into_iter()
+            .filter(|&d| d <= i && d <= 32768)
+            .map(|d| {
+                let len = (0..258.min(data.len() - i))
+                    .take_while(|&k| data[i + k] == data[i + k - d])
+                    .count();
+                (len, d)
+            })
+            .max()
+            .unwrap_or((0, 0));
+        if len < 3 {
+            out.literal(data[i] as u16);
+            i += 1;
+            continue;
+        }
+        let code = DEFLATE_LENGTHS
+            .iter()
+            .rposition(|&(base, _)| base as usize <= len)
+            .unwrap_or(0);
+        let (base, extra) = DEFLATE_LENGTHS[code];
+        out.literal(257 + code as u16);
+        out.put((len - base as usize) as u32, extra as u32);
+        let code = DEFLATE_DISTANCES
+            .iter()
+            .rposition(|&(base, _)| base as usize <= dist)
+            .unwrap_or(0);
+        let (base, extra) = DEFLATE_DISTANCES[code];
+        out.put_code(code as u32, 5);
+        out.put((dist - base as usize) as u32, extra as u32);
+        i += len;
+    }
+    out.literal(256);
+
+    // zlib header (deflate, 32K window, no dictionary) and Adler-32 trailer.
+    let mut zlib = vec![0x78, 0x01];
+    zlib.extend(out.finish());
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    zlib.extend((b << 16 | a).to_be_bytes());
+    zlib
+}
+
+// An animated GIF, built up a frame at a time, that loops forever. Each pixel
+// is drawn as a `scale` square, and the metadata goes in a comment at the end,
+// once the run it describes is over.
+struct GifEncoder {
+    out: Vec<u8>,
+    w: usize,
+    h: usize,
+    scale: usize,
+}
+
+impl GifEncoder {
+    fn new(w: usize, h: usize, scale: usize) -> Self {
+        let mut out = b"GIF89a".to_vec();
+        out.extend(((w * scale) as u16).to_le_bytes());
+        out.extend(((h * scale) as u16).to_le_bytes());
+        // No global color table: every frame brings its own.
+        out.extend([0, 0, 0]);
+        out.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+        GifEncoder { out, w, h, scale }
+    }
+
+    // Adds the buffer as a frame shown for `delay` hundredths of a second.
+    // Colors are exact while a frame has at most 256 of them; past that, the
+    // rarer ones become the nearest of the 256 most used.
+    fn frame(&mut self, buf: &PixelBuf, delay: u16) {
+        let mut counts = std::collections::HashMap::new();
+        for &c in &buf.px {
+            *counts.entry(c).or_insert(0usize) += 1;
+        }
+        let mut palette: Vec<Rgb> = counts.keys().copied().collect();
+        palette.sort_by_key(|c| std::cmp::Reverse(counts[c]));
+        palette.truncate(256);
+        let dist = |Rgb(r, g, b): Rgb, Rgb(pr, pg, pb): Rgb| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, pr) + d(g, pg) + d(b, pb)
+        };
+        let index: std::collections::HashMap<Rgb, u8> = counts
+            .keys()
+            .map(|&c| {
+                let i = (0..palette.len()).min_by_key(|&i| dist(c, palette[i]));
+                (c, i.unwrap_or(0) as u8)
+            })
+            .collect();
+        let pixels: Vec<u8> = buf.px.iter().map(|c| index[c]).collect();
+        let bits = palette.len().max(2).next_power_of_two().trailing_zeros();
+
+        // Graphic control: the delay, and no disposal as each frame covers
+        // the whole picture.
+        self.out.extend([0x21, 0xf9, 4, 0]);
+        self.out.extend(delay.to_le_bytes());
+        self.out.extend([0, 0]);
+        // Image descriptor with a local color table.
+        self.out.push(0x2c);
+        self.out.extend([0, 0, 0, 0]);
+        self.out
+            .extend(((self.w * self.scale) as u16).to_le_bytes());
+        self.out
+            .extend(((self.h * self.scale) as u16).to_le_bytes());
+        self.out.push(0x80 | (bits - 1) as u8);
+        for i in 0..1 << bits {
+            let Rgb(r, g, b) = palette.get(i).copied().unwrap_or(Rgb(0, 0, 0));
+            self.out.extend([r, g, b]);
+        }
+        let min_size = bits.max(2);
+        let (w, scale) = (self.w, self.scale);
+        let scaled = (0..self.h * scale)
+            .flat_map(|y| (0..w * scale).map(move |x| (y / scale) * w + x / scale))
+            .map(|i| pixels[i]);
+        self.out.push(min_size as u8);
+        push_sub_blocks(&mut self.out, &lzw_compress(scaled, min_size));
+    }
+
+    fn finish(mut self, meta: &MediaMeta) -> Vec<u8> {
+        let text: String = meta
+            .entries()
+            .iter()
+            .map(|(keyword, text)| format!("{keyword}: {text}\n"))
+            .filter(|line| line.is_ascii())
+            .collect();
+        self.out.extend([0x21, 0xfe]);
+        push_sub_blocks(&mut self.out, text.as_bytes());
+        self.out.push(0x3b);
+        self.out
+    }
+}
+
+// GIF data goes in blocks of up to 255 bytes, each led by its length, and
+// ends with an empty one.
+fn push_sub_blocks(out: &mut Vec<u8>, data: &[u8]) {
+    for block in data.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend(block);
+    }
+    out.push(0);
+}
+
+// GIF's variable-width LZW, starting over once all 4096 codes are taken.
+// Table entries are tagged with the table's generation, so starting over
+// doesn't have to clear them.
+fn lzw_compress(mut pixels: impl Iterator<Item = u8>, min_size: u32) -> Vec<u8> {
+    let (clear, end) = (1u32 << min_size, (1u32 << min_size) + 1);
+    let mut table = vec![0u32; 4096 * 256];
+    let mut generation = 1;
+    let mut out = BitWriter::default();
+    let mut size = min_size + 1;
+    let mut next = end + 1;
+    out.put(clear, size);
+    let Some(first) = pixels.next() else {
+        out.put(end, size);
+        return out.finish();
+    };
+    let mut code = first as u32;
+    for px in pixels {
+        let slot = code as usize * 256 + px as usize;
+        if table[slot] >> 12 == generation {
+            code = table[slot] & 0xfff;
+            continue;
+        }
+        out.put(code, size);
+        if next == 4096 {
+            out.put(clear, size);
+            generation += 1;
+            size = min_size + 1;
+            next = end + 1;
+        } else {
+            if next >= 1 << size {
+                size += 1;
+            }
+            table[slot] = generation << 12 | next;
+            next += 1;
+        }
+        code = px as u32;
+    }
+    out.put(code, size);
+    out.put(end, size);
+    out.finish()
+}
+
+// ── Demo ────────────────────────────────────────────────────────────────────
+
+// `flappy-tui render-demo` flies the autopilot through a seeded run off
+// screen, at this size in pixels, and saves it as a GIF.
+pub(crate) const DEMO_SIZE: (usize, usize) = (160, 90);
+const DEMO_SECONDS: u32 = 10;
+// Every other step is kept, as GIFs much faster than this play unevenly.
+const DEMO_FPS: u32 = 15;
+// A run that ends is restarted after this long on the game-over panel.
+const DEMO_DEAD_FRAMES: u32 = 2 * SIM_FPS;
+
+const DEMO_USAGE: &str = "Usage: flappy-tui render-demo --out FILE.gif [--seed SEED] [--seconds N]";
+
+pub fn render_demo(args: &[String]) -> Result<(), FlappyError> {
+    let flag = |name: &str| {
+        args.windows(2)
+            .find(|pair| pair[0] == name)
+            .map(|pair| pair[1].as_str())
+    };
+    let Some(out) = flag("--out") else {
+        return Err(FlappyError::Usage(DEMO_USAGE.to_string()));
+    };
+    let seed = match flag("--seed").map(decode_seed) {
+        Some(Some(seed)) => seed,
+        Some(None) => {
+            let seed = flag("--seed").unwrap_or("");
+            return Err(FlappyError::Usage(format!(
+                "Not a seed: {seed}.\n{DEMO_USAGE}"
+            )));
+        }
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() % SEED_CODES),
+    };
+    let seconds = match flag("--seconds").map(str::parse::<u32>) {
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            return Err(FlappyError::Usage(format!(
+                "--seconds takes a whole number above 0.\n{DEMO_USAGE}"
+            )));
+        }
+        None => DEMO_SECONDS,
+    };
+
+    let (w, h) = DEMO_SIZE;
+    let mut game = Game::headless(seed);
+    let mut buf = PixelBuf::new(w, h);
+    let scale = (SCREENSHOT_WIDTH / w).clamp(1, 4);
+    let mut gif = GifEncoder::new(w, h, scale);
+    let every = SIM_FPS / DEMO_FPS;
+    let frames = seconds * DEMO_FPS;
+    for i in 0..frames {
+        for _ in 0..every {
+            if game.state == State::Dead && game.dead_timer >= DEMO_DEAD_FRAMES {
+                game.restart();
+            }
+            game.autopilot();
+            game.update();
+        }
+        game.draw(&mut buf);
+        // Hundredths of a second don't divide evenly into frames, so the
+        // delays alternate to keep the pace right overall.
+        let delay = (i + 1) * 100 / DEMO_FPS - i * 100 / DEMO_FPS;
+        gif.frame(&buf, delay as u16);
+    }
+    fs::write(out, gif.finish(&game.media_meta()))
+        .map_err(FlappyError::asset("the demo", out.into()))?;
+    eprintln!("Saved {seconds}s of seed {} to {out}.", encode_seed(seed));
+    Ok(())
+}
+
+impl Game {
+    fn media_meta(&self) -> MediaMeta {
+        let code = self.mutators.code();
+        MediaMeta {
+            version: env!("CARGO_PKG_VERSION"),
+            seed: encode_seed(self.run_seed),
+            score: self.score,
+            mode: if code.is_empty() {
+                "CLASSIC".into()
+            } else {
+                code
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta() -> MediaMeta {
+        MediaMeta {
+            version: "0.0.0",
+            seed: encode_seed(1234),
+            score: 7,
+            mode: "CLASSIC".into(),
+        }
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn png_has_header_size_and_metadata() {
+        let png = encode_png(&PixelBuf::new(5, 3), 2, &meta());
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 10, 0, 0, 0, 6]);
+        let text = String::from_utf8_lossy(&png);
+        assert!(text.contains(&format!("Seed\0{}", encode_seed(1234))));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+
+    #[test]
+    fn gif_frames_end_with_a_comment_and_trailer() {
+        let mut buf = PixelBuf::new(4, 4);
+        buf.px[5] = Rgb(255, 0, 0);
+        let mut gif = GifEncoder::new(4, 4, 3);
+        gif.frame(&buf, 5);
+        gif.frame(&buf, 5);
+        let bytes = gif.finish(&meta());
+        assert!(bytes.starts_with(b"GIF89a\x0c\x00\x0c\x00"));
+        assert!(String::from_utf8_lossy(&bytes).contains("Score: 7"));
+        assert_eq!(bytes.last(), Some(&0x3b));
+    }
+
+    #[test]
+    fn sub_blocks_split_at_255_bytes() {
+        let mut out = Vec::new();
+        push_sub_blocks(&mut out, &[1; 300]);
+        assert_eq!(out.len(), 1 + 255 + 1 + 45 + 1);
+        assert_eq!((out[0], out[256], out[302]), (255, 45, 0));
+    }
+
+    #[test]
+    fn bad_demo_flags_are_errors() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let usage = |list: &[&str]| matches!(render_demo(&args(list)), Err(FlappyError::Usage(_)));
+        assert!(usage(&[]));
+        assert!(usage(&["--out", "x.gif", "--seed", "not a seed!"]));
+        assert!(usage(&["--out", "x.gif", "--seconds", "0"]));
+    }
+}
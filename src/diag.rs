@@ -0,0 +1,215 @@
+//! Bug-report traces, the panic hook and `--log-file` logging.
+
+use crossterm::{
+    cursor,
+    event::{DisableMouseCapture, KeyEvent},
+    execute, terminal,
+};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::stdout;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::FlappyError;
+use crate::game::{Game, GameEvent, Physics, SIM_FPS, State, encode_seed};
+use crate::theme::config_dir;
+
+// ── Diagnostics ─────────────────────────────────────────────────────────────
+
+// Steps kept for bug reports: about a minute of play.
+const TRACE_STEPS: usize = SIM_FPS as usize * 60;
+
+// One simulation step: the keys read and game events since the step before,
+// and the state hash after it.
+struct TraceStep {
+    frame: u64,
+    keys: Vec<String>,
+    events: Vec<String>,
+    hash: u64,
+}
+
+// A rolling record of recent play, written out on request or on a panic so
+// odd physics can be replayed with the same seed and settings.
+#[derive(Default)]
+pub struct Trace {
+    steps: VecDeque<TraceStep>,
+    keys: Vec<String>,
+    events: Vec<String>,
+    // The settings a run was played with, refreshed as runs start.
+    header: String,
+    last_state: Option<State>,
+    pub saved: Vec<PathBuf>,
+}
+
+impl Trace {
+    pub fn key(&mut self, key: &KeyEvent) {
+        self.keys.push(format!("{:?}/{:?}", key.code, key.kind));
+    }
+
+    pub fn event(&mut self, event: &GameEvent) {
+        self.events.push(format!("{event:?}"));
+    }
+
+    pub fn step(&mut self, game: &Game) {
+        if self.last_state != Some(game.state) {
+            self.last_state = Some(game.state);
+            self.header = game.trace_header();
+        }
+        if self.steps.len() == TRACE_STEPS {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(TraceStep {
+            frame: game.frame,
+            keys: std::mem::take(&mut self.keys),
+            events: std::mem::take(&mut self.events),
+            hash: game.snapshot().hash(),
+        });
+    }
+
+    pub fn save(&mut self) -> Result<PathBuf, FlappyError> {
+        const WHAT: &str = "a trace";
+        let dir = config_dir()
+            .ok_or_else(|| FlappyError::no_asset_dir(WHAT))?
+            .join("traces");
+        fs::create_dir_all(&dir).map_err(FlappyError::asset(WHAT, dir.clone()))?;
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = dir.join(format!("trace-{secs}.txt"));
+        let mut text = self.header.clone();
+        for step in &self.steps {
+            text += &format!(
+                "{} {:016x} keys[{}] events[{}]\n",
+                step.frame,
+                step.hash,
+                step.keys.join(" "),
+                step.events.join(" ")
+            );
+        }
+        fs::write(&path, text).map_err(FlappyError::asset(WHAT, path.clone()))?;
+        self.saved.push(path.clone());
+        Ok(path)
+    }
+}
+
+impl Game {
+    fn trace_header(&self) -> String {
+        let physics = match self.world.physics {
+            Physics::Float => "float",
+            Physics::Fixed => "fixed",
+        };
+        format!(
+            "flappy-tui {}\nseed {}{}\nphysics {}\nmutators {}\n{}\
+             pixels {}x{} zoom {}\ngravity {} flap {} speed {} coyote {}\ngap {} spacing {}\n\
+             frame hash keys events\n",
+            env!("CARGO_PKG_VERSION"),
+            encode_seed(self.run_seed),
+            if self.forced_seed.is_some() {
+                " (FLAPPY_SEED)"
+            } else {
+                ""
+            },
+            physics,
+            Some(self.mutators.code())
+                .filter(|code| !code.is_empty())
+                .unwrap_or("none".into()),
+            self.adaptive
+                .as_ref()
+                .map_or(String::new(), |a| format!("adaptive {:.1}\n", a.level)),
+            self.pw,
+            self.ph,
+            self.zoom,
+            self.gravity,
+            self.flap_vel,
+            self.pipe_speed,
+            self.tuning.coyote,
+            self.config.pipe_gap,
+            self.config.pipe_spacing,
+        )
+    }
+}
+
+// Gives the terminal back as best it can after the game stopped short,
+// should it still be in raw mode.
+pub fn restore_terminal() {
+    if !terminal::is_raw_mode_enabled().unwrap_or(true) {
+        return;
+    }
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(
+        stdout(),
+        DisableMouseCapture,
+        terminal::LeaveAlternateScreen,
+        cursor::Show,
+        terminal::EnableLineWrap
+    );
+}
+
+// Saves the trace when the game panics, after giving the terminal back.
+pub fn install_panic_hook(trace: Arc<Mutex<Trace>>) {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default(info);
+        tracing::error!(%info, "panic");
+        if let Ok(mut trace) = trace.try_lock()
+            && let Ok(path) = trace.save()
+        {
+            eprintln!("Saved a trace of the last minute to {}", path.display());
+        }
+    }));
+}
+
+// ── Logging ─────────────────────────────────────────────────────────────────
+
+// Sends the game's logs to `path`, appending. Lines are formatted and written
+// on a thread of their own, so logging never waits on the disk; the returned
+// guard flushes what's left when dropped.
+pub fn init_logging(
+    path: &Path,
+) -> Result<tracing_appender::non_blocking::WorkerGuard, FlappyError> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(FlappyError::asset("the log", path.into()))?;
+    let (writer, guard) = tracing_appender::non_blocking(file);
+    tracing_subscriber::fmt()
+        .with_writer(writer)
+        .with_ansi(false)
+        .init();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "started");
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_keep_the_last_minute() {
+        let mut game = Game::headless(11);
+        let mut trace = Trace::default();
+        for _ in 0..TRACE_STEPS + 10 {
+            game.step(false);
+            trace.step(&game);
+        }
+        assert_eq!(trace.steps.len(), TRACE_STEPS);
+        assert_eq!(trace.steps.back().map(|s| s.frame), Some(game.frame));
+    }
+
+    #[test]
+    fn steps_collect_the_keys_and_events_since_the_last() {
+        let mut game = Game::headless(11);
+        let mut trace = Trace::default();
+        trace.event(&GameEvent::Flap);
+        trace.step(&game);
+        game.step(false);
+        trace.step(&game);
+        assert_eq!(trace.steps[0].events, ["Flap"]);
+        assert!(trace.steps[1].events.is_empty());
+        assert!(trace.header.contains(&encode_seed(game.run_seed)));
+        assert!(trace.header.contains("physics fixed"));
+    }
+}
@@ -0,0 +1,977 @@
+//! Drawing the world: terrain, pipes, the bird and the layers a frame is
+//! built from.
+
+use crate::game::{
+    BIRD_HITBOX_HH, CAT_W, CHECKPOINT_EVERY, Camera, DecorationKind, FAN_H, FOG_FALLOFF,
+    FOG_RADIUS, GLIDE_GRAVITY, Game, HazardKind, ICICLES_H, Medal, Mutator, PIPE_CAP_CHAMFER,
+    PIPE_HUE_MAX, PIPE_SPEED, PIPE_W, PISTON_W, Pipe, SIM_FPS, SKY_H, SPIKES_H, State,
+    VANISH_CAP_ALPHA, VANISH_RAMP, VU,
+};
+use crate::pixels::{Flip, PixelBuf, Rgb, Sprite};
+use crate::render::{
+    BIRD_Y, CAVE_SKY, DIRT, DIRT_DARK, FOG, GRASS, GRASS_LIGHT, HILL_FAR, HILL_NEAR, ROCK, ROCK_HI,
+    SHADOW, SIGN, SIGN_DARK, WHITE, fog_pass,
+};
+use crate::theme::{ARC_FRAMES, BirdSkin, PipePattern, PipeStyle};
+
+// ── Terrain ─────────────────────────────────────────────────────────────────
+
+// Colors for hills and ground; bat mode swaps the meadow for a cave ceiling.
+struct Terrain {
+    hill_far: Rgb,
+    hill_near: Rgb,
+    // Biome tweak on top of the theme's hill shape.
+    hill_amp: f64,
+    hill_freq: f64,
+    edge: Rgb,
+    edge_light: Rgb,
+    fill: Rgb,
+    fill_dark: Rgb,
+}
+const MEADOW: Terrain = Terrain {
+    hill_far: HILL_FAR,
+    hill_near: HILL_NEAR,
+    hill_amp: 1.0,
+    hill_freq: 1.0,
+    edge: GRASS,
+    edge_light: GRASS_LIGHT,
+    fill: DIRT,
+    fill_dark: DIRT_DARK,
+};
+const CAVE: Terrain = Terrain {
+    hill_far: Rgb(62, 56, 70),
+    hill_near: Rgb(84, 76, 88),
+    hill_amp: 0.8,
+    hill_freq: 2.0,
+    edge: ROCK,
+    edge_light: ROCK_HI,
+    fill: Rgb(96, 88, 96),
+    fill_dark: Rgb(74, 68, 78),
+};
+impl Game {
+    pub fn draw(&self, buf: &mut PixelBuf) {
+        if self.blanked {
+            let c = buf.transparent.unwrap_or(Rgb(0, 0, 0));
+            buf.fill_rect(0, 0, buf.w as i32, buf.h as i32, c);
+            return;
+        }
+        if let Some(view) = self.replay_view() {
+            view.draw(buf);
+            self.draw_replay_mark(buf);
+            self.draw_muted_icon(buf);
+            return;
+        }
+        let world_flip = Flip {
+            x: self.mutators.has(Mutator::MirrorX),
+            y: self.mutators.has(Mutator::MirrorY) != self.mutators.has(Mutator::Bat),
+        };
+        let world_cam = self.camera();
+        for layer in self.layers() {
+            let (flip, cam) = if layer.screen_space() {
+                (Flip::default(), &self.cam)
+            } else {
+                (world_flip, &world_cam)
+            };
+            buf.flip = flip;
+            layer.draw(self, buf, cam);
+        }
+        buf.flip = Flip::default();
+        self.draw_muted_icon(buf);
+    }
+
+    // The layers for the current scene, back to front. Mutators and scenes
+    // edit the default stack rather than branching inside the draw code.
+    fn layers(&self) -> Vec<&'static dyn Layer> {
+        let mut layers = DEFAULT_LAYERS.to_vec();
+        if self.screensaver {
+            remove_layer(&mut layers, "hud");
+            remove_layer(&mut layers, "milestone");
+        }
+        if self.low_power {
+            replace_layer(&mut layers, "sky", &BackdropLayer);
+            remove_layer(&mut layers, "hills");
+            remove_layer(&mut layers, "decorations");
+        }
+        if self.mutators.has(Mutator::Bat) {
+            remove_layer(&mut layers, "decorations");
+        }
+        if self.mutators.has(Mutator::InvisiblePipes) {
+            replace_layer(&mut layers, "pipes", &PipeOutlinesLayer);
+        }
+        if self.mutators.has(Mutator::Fog) {
+            insert_layer_after(&mut layers, "bird", &FogLayer);
+        }
+        if self.mutators.has(Mutator::Glide) {
+            insert_layer_after(&mut layers, "hud", &StaminaLayer);
+        }
+        if self.mutators.has(Mutator::Energy) {
+            insert_layer_after(&mut layers, "bird", &EnergyLayer);
+        }
+        if self.progress_bar && !self.screensaver {
+            insert_layer_after(&mut layers, "hud", &BestProgressLayer);
+        }
+        if self.touch {
+            insert_layer_after(&mut layers, "hud", &TouchOverlay);
+        }
+        if self.hint && !self.screensaver {
+            insert_layer_after(&mut layers, "bird", &TrajectoryHintLayer);
+        }
+        self.scene().draw(self, &mut layers);
+        layers
+    }
+
+    fn terrain(&self) -> &'static Terrain {
+        if self.mutators.has(Mutator::Bat) {
+            &CAVE
+        } else {
+            &MEADOW
+        }
+    }
+
+    pub(crate) fn draw_sky(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let top = cam.rows().start;
+        let sky_h_px = (cam.y(SKY_H) - top).max(0) as usize;
+        let clear = self.transparent_sky.map(|c| [c, c]);
+        let sky: &[Rgb] = if self.mutators.has(Mutator::Bat) {
+            &CAVE_SKY
+        } else if let Some(clear) = &clear {
+            clear
+        } else {
+            &self.sky
+        };
+        let bands = sky.len() - 1;
+        let row_colors: Vec<Rgb> = (0..sky_h_px)
+            .map(|y| {
+                let t = y * bands * 256 / sky_h_px.max(1);
+                let band = (t / 256).min(bands - 1);
+                Rgb::lerp_linear(sky[band], sky[band + 1], (t - band * 256) as u16)
+            })
+            .collect();
+        let rows = top..top + sky_h_px as i32;
+        buf.fill_rows(rows, cam.cols(), true, |_, y| {
+            row_colors.get((y - top) as usize).copied()
+        });
+    }
+
+    fn draw_hills(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let terrain = self.terrain();
+        let base = cam.y(SKY_H);
+        let sy = cam.scale;
+        let amp = self.hills.amplitude * terrain.hill_amp * VU * sy;
+        let freq = self.hills.frequency * terrain.hill_freq;
+        // Low power keeps the hills still, so the backdrop can be reused.
+        let parallax = if self.low_power { 0.0 } else { 1.0 };
+        let layers = [
+            (
+                self.hill_seed,
+                0.2 * parallax,
+                0.04,
+                12.0,
+                4.0,
+                terrain.hill_far,
+            ),
+            (
+                !self.hill_seed,
+                0.4 * parallax,
+                0.06,
+                8.0,
+                2.0,
+                terrain.hill_near,
+            ),
+        ];
+        // Column tops for each layer; the noise is per column, so it's
+        // worked out once here rather than for every pixel.
+        let cols = cam.cols();
+        let tops: Vec<[i32; 2]> = cols
+            .clone()
+            .map(|x| {
+                let wx = cam.world_x(x);
+                layers.map(|(seed, parallax, scale, height, lift, _)| {
+                    let fx = (wx + self.ground_x * parallax) * scale * freq;
+                    let h = hill_noise(seed, fx) * height * amp;
+                    base - h as i32 - (lift * VU * sy) as i32
+                })
+            })
+            .collect();
+        let highest = tops.iter().flatten().copied().min().unwrap_or(base);
+        buf.fill_rows(highest..base, cols.clone(), true, |x, y| {
+            let [far, near] = tops[(x - cols.start) as usize];
+            if y >= near {
+                Some(layers[1].5)
+            } else if y >= far {
+                Some(layers[0].5)
+            } else {
+                None
+            }
+        });
+    }
+
+    // Sky and hills in one go, redrawn only when something they depend on
+    // changes. Used in low power, where the hills don't scroll.
+    fn draw_backdrop(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let mut key = Vec::new();
+        let sky: &[Rgb] = if self.mutators.has(Mutator::Bat) {
+            &CAVE_SKY
+        } else {
+            &self.sky
+        };
+        for c in sky {
+            key.extend([c.0, c.1, c.2]);
+        }
+        key.extend([
+            self.mutators.has(Mutator::Bat) as u8,
+            buf.flip.x as u8,
+            buf.flip.y as u8,
+        ]);
+        key.extend(self.hill_seed.to_le_bytes());
+        for v in [self.hills.amplitude, self.hills.frequency, cam.scale, cam.y] {
+            key.extend(v.to_le_bytes());
+        }
+        let (x0, y0, x1, y1) = cam.view;
+        for v in [x0, y0, x1, y1, cam.shake.0, cam.shake.1] {
+            key.extend(v.to_le_bytes());
+        }
+        buf.cached(key, |buf| {
+            self.draw_sky(buf, cam);
+            self.draw_hills(buf, cam);
+        });
+    }
+
+    fn draw_decorations(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let base = cam.y(SKY_H);
+        let u = (VU * sy).max(1.0) as i32;
+        for d in self.world.decorations.iter() {
+            let x = cam.x(d.x - self.ground_x);
+            match d.kind {
+                DecorationKind::Flower(petals) => {
+                    buf.fill_rect(x + u, base - 2 * u, 1.max(u / 2), 2 * u, GRASS);
+                    buf.fill_rect(x, base - 3 * u, u, u, petals);
+                    buf.fill_rect(x + 2 * u, base - 3 * u, u, u, petals);
+                    buf.fill_rect(x + u, base - 4 * u, u, u, petals);
+                    buf.fill_rect(x + u, base - 3 * u, u, u, BIRD_Y);
+                }
+                DecorationKind::Rock => {
+                    buf.fill_rect(x + u, base - 2 * u, 3 * u, u, ROCK);
+                    buf.fill_rect(x, base - u, 5 * u, u, ROCK);
+                    buf.fill_rect(x + u, base - 2 * u, u, u, ROCK_HI);
+                }
+                DecorationKind::Sign => {
+                    buf.fill_rect(x + 2 * u, base - 3 * u, u, 3 * u, SIGN_DARK);
+                    buf.fill_rect(x, base - 5 * u, 5 * u, 2 * u, SIGN);
+                    buf.fill_rect(x + u, base - 4 * u, 3 * u, 1.max(u / 2), SIGN_DARK);
+                }
+            }
+        }
+    }
+
+    fn draw_ground(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let terrain = self.terrain();
+        let gy = cam.y(SKY_H);
+        let gx = self.ground_x * cam.scale;
+        buf.fill_rows(gy..cam.rows().end, cam.cols(), false, |x, y| {
+            // Grass strip, then dirt
+            let c = if y == gy {
+                let alt = ((x as f64 + gx) as i32 / 3) % 2 == 0;
+                if alt {
+                    terrain.edge
+                } else {
+                    terrain.edge_light
+                }
+            } else if y == gy + 1 {
+                terrain.edge
+            } else {
+                let stripe = ((x as f64 + gx * 0.8) as i32 + (y - gy) * 2) % 12 < 6;
+                if stripe {
+                    terrain.fill
+                } else {
+                    terrain.fill_dark
+                }
+            };
+            Some(c)
+        });
+        self.draw_hazards(buf, cam);
+    }
+
+    // Spikes and prowling cats standing on the ground, and icicles and fans
+    // hanging from the top of the sky.
+    fn draw_hazards(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let base = cam.y(SKY_H);
+        let top = cam.y(0.0);
+        for h in self.world.hazards.iter() {
+            let (x0, _, x1, _) = h.extent(self.frame, self.world.physics);
+            let (px0, px1) = (cam.x(x0), cam.x(x1));
+            match h.kind {
+                HazardKind::Spikes => {
+                    let sh = (SPIKES_H * cam.scale).max(2.0) as i32;
+                    // As many whole teeth about as wide as they're tall as fit.
+                    let tooth = (px1 - px0) / ((px1 - px0) / sh.max(3)).max(1);
+                    for x in px0..px1 {
+                        let across = (x - px0) % tooth * 2 + 1;
+                        let rise = sh * (tooth - (across - tooth).abs()) / tooth;
+                        for y in base - rise..base {
+                            let c = if y == base - rise { ROCK_HI } else { ROCK };
+                            buf.set(x, y, c);
+                        }
+                    }
+                }
+                HazardKind::Cat { .. } => {
+                    let step = (self.frame / 6 % 2) as usize;
+                    let palette = [('K', CAT), ('E', BIRD_Y)];
+                    let sprite = Sprite::from_art(&CAT_ART[step], &palette);
+                    let k = (px1 - px0) as f64 / CAT_W;
+                    let y = base - (CAT_ART[step].len() as f64 * k).round() as i32;
+                    let left = h.heading_left(self.frame, self.world.physics);
+                    buf.blit(&sprite, px0, y, k, left);
+                }
+                HazardKind::Icicles { .. } => {
+                    let ih = (ICICLES_H * cam.scale).max(2.0) as i32;
+                    let tooth = ((ih / 2).max(3) | 1).min((px1 - px0).max(1));
+                    for x in px0..px1 {
+                        // Each icicle hangs a little longer or shorter than
+                        // its neighbours.
+                        let i = (x - px0) / tooth;
+                        let len = ih * (5 + (i * 7 + 3) % 5) / 9;
+                        let across = ((x - px0) % tooth * 2 + 1 - tooth).abs();
+                        let drop = len * (tooth - across) / tooth;
+                        for y in top..top + drop.max(1) {
+                            let c = if across == 0 || y == top { WHITE } else { ICE };
+                            buf.set(x, y, c);
+                        }
+                    }
+                }
+                HazardKind::Fan => {
+                    let hub = cam.y(FAN_H - 3.0);
+                    let mid = (px0 + px1) / 2;
+                    for y in top..hub {
+                        buf.set(mid, y, FAN_ROD);
+                    }
+                    // Seen side-on, each pair of blades sweeps out and back.
+                    let half = (px1 - px0) as f64 / 2.0;
+                    let t = self.frame as f64 * 0.5;
+                    let bh = (2.0 * cam.scale).max(1.0) as i32;
+                    for (blade, shade) in [(t, FAN_BLADE), (t + 1.6, FAN_BLADE_DARK)] {
+                        let reach = (half * blade.cos()).abs().round() as i32;
+                        for y in hub..hub + bh {
+                            for x in mid - reach..=mid + reach {
+                                buf.set(x, y, shade);
+                            }
+                        }
+                    }
+                    let lamp = cam.y(FAN_H);
+                    for y in hub + bh..lamp {
+                        for x in mid - bh..=mid + bh {
+                            buf.set(x, y, BIRD_Y);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_pipes(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let style = self.pipe_style;
+        let cap_extra = (style.cap_extra * sy).max(1.0) as i32;
+        let cap_h = (style.cap_h * sy).max(2.0) as i32;
+        let pw = (PIPE_W * sy) as i32;
+        let sky_h_px = cam.y(SKY_H);
+        let hue = self.pipe_hue();
+        let cap_dark = style.cap_dark.hue_rotate(hue);
+
+        let cap_w = pw + cap_extra * 2;
+        let chamfer = ((PIPE_CAP_CHAMFER * sy).round() as i32)
+            .clamp(1, 2)
+            .min((cap_h - 1) / 2);
+
+        for pipe in self.world.pipes.iter() {
+            let x_pos = cam.fx(pipe.x);
+            let px = x_pos.floor() as i32;
+            let gap_top = cam.y(pipe.gap_center - self.gap() / 2.0);
+            let gap_bot = cam.y(pipe.gap_center + self.gap() / 2.0);
+            let (body_a, cap_a) = self.pipe_alpha(pipe);
+            // Spans are drawn one column wider than their width, with the first
+            // and last columns weighted by how much of them the pipe covers.
+            let frac = x_pos - px as f64;
+            let cover = |x: i32, w: i32, alpha: u16| {
+                let c = if x == 0 {
+                    1.0 - frac
+                } else if x == w {
+                    frac
+                } else {
+                    1.0
+                };
+                (alpha as f64 * c).round() as u16
+            };
+
+            for x in 0..=pw {
+                let a = cover(x, pw, body_a);
+                let col = x.min(pw - 1);
+                // Top pipe body
+                for y in 0..gap_top - cap_h {
+                    buf.blend(px + x, y, pipe_body_color(style, col, y, pw, sy, hue), a);
+                }
+                // Bottom pipe body
+                for y in (gap_bot + cap_h)..sky_h_px {
+                    buf.blend(px + x, y, pipe_body_color(style, col, y, pw, sy, hue), a);
+                }
+            }
+
+            // Columns in from the cap's nearer side, counting from whichever
+            // edge column the pipe mostly covers.
+            let edge = (frac >= 0.5) as i32;
+            for i in 0..=cap_w {
+                let a = cover(i, cap_w, cap_a);
+                let x = px + i - cap_extra;
+                let shade = pipe_shade(style, i.min(cap_w - 1), cap_w, hue);
+                let dx = (i - edge).min(cap_w - 1 - i + edge);
+                // Caps, with a dark rim on their top and bottom rows and the
+                // corners cut off.
+                for top in [gap_top - cap_h, gap_bot] {
+                    for y in top..top + cap_h {
+                        let dy = (y - top).min(top + cap_h - 1 - y);
+                        if dx + dy < chamfer {
+                            continue;
+                        }
+                        let rim = y == top || y == top + cap_h - 1;
+                        buf.blend(x, y, if rim { cap_dark } else { shade }, a);
+                    }
+                }
+            }
+        }
+    }
+
+    // Pipes turn from green toward orange and red as they speed up, reaching
+    // PIPE_HUE_MAX at twice the stock speed.
+    fn pipe_hue(&self) -> f64 {
+        let t = (self.speed() / PIPE_SPEED - 1.0).clamp(0.0, 1.0);
+        PIPE_HUE_MAX * t
+    }
+
+    // Vanishing pipes fade out over VANISH_RAMP once they come within a third
+    // of the screen of the bird, leaving only a ghost of the caps.
+    fn pipe_alpha(&self, pipe: &Pipe) -> (u16, u16) {
+        if !self.mutators.has(Mutator::VanishingPipes) {
+            return (256, 256);
+        }
+        let start = self.world_w / 3.0;
+        let d = pipe.x - self.bird_x;
+        let t = ((d - (start - VANISH_RAMP)) / VANISH_RAMP).clamp(0.0, 1.0);
+        let fade = |min: f64| (min + (256.0 - min) * t) as u16;
+        (fade(0.0), fade(VANISH_CAP_ALPHA))
+    }
+
+    fn draw_pipe_outlines(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let pw = (PIPE_W * sy) as i32;
+        let sky_h_px = cam.y(SKY_H);
+        let c = self.pipe_style.cap_dark.hue_rotate(self.pipe_hue());
+
+        for pipe in self.world.pipes.iter() {
+            let px = cam.x(pipe.x);
+            let gap_top = cam.y(pipe.gap_center - self.gap() / 2.0);
+            let gap_bot = cam.y(pipe.gap_center + self.gap() / 2.0);
+            buf.fill_rect(px, 0, 1, gap_top, c);
+            buf.fill_rect(px + pw - 1, 0, 1, gap_top, c);
+            buf.fill_rect(px, gap_top - 1, pw, 1, c);
+            buf.fill_rect(px, gap_bot, 1, sky_h_px - gap_bot, c);
+            buf.fill_rect(px + pw - 1, gap_bot, 1, sky_h_px - gap_bot, c);
+            buf.fill_rect(px, gap_bot, pw, 1, c);
+        }
+    }
+
+    fn draw_fog(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let bx = cam.fx(self.bird_x);
+        let by = cam.fy(self.bird_y);
+        fog_pass(buf, bx, by, FOG_RADIUS * sy, FOG_FALLOFF * sy, FOG);
+    }
+
+    fn draw_bird(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let cx = cam.x(self.bird_x);
+        let cy = cam.fy(self.bird_y);
+        let s = VU * sy;
+
+        let tilt = (self.bird_vy / (3.0 * s)).clamp(-1.0, 1.0) as i32;
+        let wing_y_off = if self.state == State::Dying || self.state == State::Dead {
+            1
+        } else if self.is_gliding() || self.frame % 8 < 4 {
+            // Wings stay spread while gliding.
+            -1
+        } else {
+            1
+        };
+        if self.bonk_frames % 6 >= 3 {
+            return;
+        }
+        draw_bird_sprite(buf, cx, cy, s, tilt, wing_y_off, &self.skin);
+    }
+
+    // A flag on the bottom cap of every CHECKPOINT_EVERY-th pipe, colored by the
+    // medal it's worth.
+    fn draw_checkpoints(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let u = (VU * sy).max(1.0) as i32;
+        let pw = (PIPE_W * sy) as i32;
+        for pipe in self
+            .world
+            .pipes
+            .iter()
+            .filter(|p| p.number % CHECKPOINT_EVERY == 0)
+        {
+            let color = Medal::for_score(pipe.number).map_or(WHITE, Medal::color);
+            let pole_x = cam.x(pipe.x) + pw / 2;
+            let base = cam.y(pipe.gap_center + self.gap() / 2.0);
+            buf.line(pole_x, base - 1, pole_x, base - 7 * u, SHADOW);
+            let (fx, fy) = ((pole_x + 1) as f64, (base - 7 * u) as f64);
+            let u = u as f64;
+            buf.fill_triangle(
+                (fx, fy),
+                (fx + 5.0 * u, fy + 1.5 * u),
+                (fx, fy + 3.0 * u),
+                color,
+            );
+        }
+    }
+
+    fn draw_pistons(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let sy = cam.scale;
+        let w = (PISTON_W * sy) as i32;
+        let rod_w = (w / 3).max(1);
+        let head_h = (5.0 * VU * sy).max(2.0) as i32;
+        let sky_h_px = cam.y(SKY_H);
+        for p in self.world.pistons.iter() {
+            let px = cam.x(p.x);
+            let (top, bot) = p.opening(self.gap(), self.frame, self.world.physics);
+            let (top, bot) = (cam.y(top), cam.y(bot));
+            buf.fill_rect(px + (w - rod_w) / 2, 0, rod_w, top - head_h, ROCK);
+            buf.fill_rect(
+                px + (w - rod_w) / 2,
+                bot + head_h,
+                rod_w,
+                sky_h_px - bot - head_h,
+                ROCK,
+            );
+            for (y0, y1) in [(top - head_h, top), (bot, bot + head_h)] {
+                for y in y0..y1 {
+                    for x in 0..w {
+                        // Hazard stripes on the crushing faces.
+                        let c = if y == y0 || y == y1 - 1 {
+                            SHADOW
+                        } else if (x + y).rem_euclid(6) < 3 {
+                            BIRD_Y
+                        } else {
+                            SHADOW
+                        };
+                        buf.set(px + x, y, c);
+                    }
+                }
+            }
+        }
+    }
+
+    // Energy as a small bar over the bird, flashing red when a flap can't
+    // be afforded.
+    fn draw_energy(&self, buf: &mut PixelBuf, cam: &Camera) {
+        if self.state != State::Playing {
+            return;
+        }
+        let u = (VU * cam.scale).max(1.0) as i32;
+        let w = 8 * u;
+        let x = cam.x(self.bird_x) - w / 2;
+        let y = cam.y(self.bird_y - BIRD_HITBOX_HH) - 3 * u;
+        buf.fill_rect(x - 1, y - 1, w + 2, u + 2, SHADOW);
+        let empty = self.energy < self.energy_rules.cost;
+        let color = if empty && self.frame % 8 < 4 {
+            Rgb(220, 70, 60)
+        } else if empty {
+            Rgb(90, 40, 40)
+        } else {
+            Rgb(250, 205, 50)
+        };
+        buf.fill_rect(x, y, w, u, Rgb(60, 60, 70));
+        let fill = (self.energy * w as f64).round() as i32;
+        buf.fill_rect(x, y, if empty { w } else { fill }, u, color);
+    }
+
+    // Where a flap right now would carry the bird.
+    fn draw_flap_arc(&self, buf: &mut PixelBuf, cam: &Camera) {
+        let vy = self.flap_vel * self.gravity_dir();
+        self.draw_arc(buf, cam, vy, ARC_FRAMES, 220);
+    }
+
+    // The trajectory hint assist: where the bird is headed over the next
+    // second if nothing is pressed.
+    fn draw_trajectory_hint(&self, buf: &mut PixelBuf, cam: &Camera) {
+        if self.state == State::Playing {
+            self.draw_arc(buf, cam, self.bird_vy, SIM_FPS, 90);
+        }
+    }
+
+    // The path from the bird at vertical speed `vy`, dotted every other frame
+    // until it reaches the ground or the top of the sky.
+    fn draw_arc(&self, buf: &mut PixelBuf, cam: &Camera, mut vy: f64, frames: u32, alpha: u16) {
+        let dir = self.gravity_dir();
+        let gravity = if self.is_gliding() {
+            self.gravity * GLIDE_GRAVITY
+        } else {
+            self.gravity
+        };
+        let (mut x, mut y) = (self.bird_x, self.bird_y);
+        for frame in 1..=frames {
+            vy += gravity * dir;
+            y += vy;
+            x += self.speed();
+            if !(BIRD_HITBOX_HH..=SKY_H - BIRD_HITBOX_HH).contains(&y) {
+                break;
+            }
+            if frame % 2 == 0 {
+                buf.blend(cam.x(x), cam.y(y), WHITE, alpha);
+            }
+        }
+    }
+}
+const CAT: Rgb = Rgb(40, 36, 44);
+const ICE: Rgb = Rgb(190, 225, 245);
+const FAN_ROD: Rgb = Rgb(90, 90, 96);
+const FAN_BLADE: Rgb = Rgb(150, 110, 70);
+const FAN_BLADE_DARK: Rgb = Rgb(110, 78, 48);
+// A cat walking right, one world unit a pixel, in two steps.
+#[rustfmt::skip]
+const CAT_ART: [[&str; 10]; 2] = [
+    [
+        "........K..K",
+        "........KKKK",
+        ".K......KEKE",
+        "K.......KKKK",
+        "K..KKKKKKKK.",
+        ".KKKKKKKKKK.",
+        "..KKKKKKKKK.",
+        "..KKKKKKKK..",
+        "..K.K...K.K.",
+        "..K.K...K.K.",
+    ],
+    [
+        "........K..K",
+        "........KKKK",
+        ".K......KEKE",
+        "K.......KKKK",
+        "K..KKKKKKKK.",
+        ".KKKKKKKKKK.",
+        "..KKKKKKKKK.",
+        "..KKKKKKKK..",
+        "...K.K...K.K",
+        "..K.K...K.K.",
+    ],
+];
+
+// Bird art at scale 2, split into parts so the wing can flap and the beak, wing
+// and tail can tilt with the vertical speed.
+#[rustfmt::skip]
+const BIRD_BODY_ART: [&str; 8] = [
+    "..BBBBBBBBB..",
+    ".BhhhhhhhhhB.",
+    "BBBBBBBBBEEBB",
+    "BBBBBBBBBEpBB",
+    "BBBBBBBBBBBBB",
+    "BBBBBBBBBBBBB",
+    ".BBBBBBBBBBB.",
+    "..BBBBBBBBB..",
+];
+const BIRD_SHAPES_MIN_SCALE: f64 = 4.0;
+const BIRD_WING_ART: [&str; 3] = ["wwww", "wwww", "wwww"];
+const BIRD_TAIL_ART: [&str; 2] = ["www", "www"];
+const BIRD_BEAK_ART: [&str; 3] = ["KK...", "KKKKK", "kk..."];
+pub(crate) fn draw_bird_sprite(
+    buf: &mut PixelBuf,
+    cx: i32,
+    cy: f64,
+    s: f64,
+    tilt: i32,
+    wing_y_off: i32,
+    skin: &BirdSkin,
+) {
+    let palette = [
+        ('B', skin.body),
+        ('h', skin.body_hi),
+        ('w', skin.wing),
+        ('E', skin.eye),
+        ('p', skin.pupil),
+        ('K', skin.beak_hi),
+        ('k', skin.beak),
+    ];
+    if s >= BIRD_SHAPES_MIN_SCALE {
+        draw_bird_shapes(buf, cx as f64, cy, s, tilt, wing_y_off, skin);
+        return;
+    }
+    let k = s / 2.0;
+    // Offsets are in art pixels; rounding away from zero keeps the flap
+    // visible even when the art is drawn at half size.
+    let at = |dx: i32, dy: i32| {
+        (
+            cx + (dx as f64 * k).round() as i32,
+            cy + (dy as f64 * k).round(),
+        )
+    };
+    let parts: [(&[&str], i32, i32); 4] = [
+        (&BIRD_TAIL_ART, -9, -1 + tilt),
+        (&BIRD_BODY_ART, -6, -4),
+        (&BIRD_WING_ART, -5, wing_y_off + tilt),
+        (&BIRD_BEAK_ART, 6, -1 + tilt),
+    ];
+    for (art, dx, dy) in parts {
+        let (x, y) = at(dx, dy);
+        buf.blit_subpixel(&Sprite::from_art(art, &palette), x, y, k);
+    }
+}
+// The same bird built from ellipses and triangles, for scales where the pixel
+// art would turn blocky.
+fn draw_bird_shapes(
+    buf: &mut PixelBuf,
+    cx: f64,
+    cy: f64,
+    s: f64,
+    tilt: i32,
+    wing_y_off: i32,
+    skin: &BirdSkin,
+) {
+    let t = tilt as f64 * s / 2.0;
+    let wing = wing_y_off as f64 * s / 2.0;
+    let tail_x = cx - 3.0 * s;
+    buf.fill_triangle(
+        (tail_x + s, cy + t),
+        (tail_x - 1.5 * s, cy - s + t),
+        (tail_x - 1.5 * s, cy + s + t),
+        skin.wing,
+    );
+    buf.fill_ellipse(cx, cy, 3.3 * s, 2.1 * s, skin.body);
+    buf.fill_ellipse(cx - 0.2 * s, cy - 1.2 * s, 2.3 * s, 0.6 * s, skin.body_hi);
+    buf.fill_ellipse(
+        cx - 1.3 * s,
+        cy + 0.4 * s + wing + t,
+        1.4 * s,
+        0.8 * s,
+        skin.wing,
+    );
+    buf.fill_circle(cx + 1.7 * s, cy - 0.8 * s, 0.75 * s, skin.eye);
+    buf.fill_circle(cx + 2.0 * s, cy - 0.6 * s, 0.35 * s, skin.pupil);
+    let bx = cx + 2.7 * s;
+    buf.fill_triangle(
+        (bx, cy - 0.7 * s + t),
+        (bx + 1.9 * s, cy + 0.15 * s + t),
+        (bx, cy + 0.15 * s + t),
+        skin.beak_hi,
+    );
+    buf.fill_triangle(
+        (bx, cy + 0.15 * s + t),
+        (bx + 1.4 * s, cy + 0.25 * s + t),
+        (bx, cy + 0.75 * s + t),
+        skin.beak,
+    );
+}
+// Two octaves of 1D value noise in about -1..1. Unlike the old sine sums it
+// never repeats, so wide terminals don't show the same hill twice.
+fn hill_noise(seed: u64, x: f64) -> f64 {
+    value_noise(seed, x) * 0.67 + value_noise(seed ^ 0xa5a5_a5a5, x * 2.1) * 0.33
+}
+fn value_noise(seed: u64, x: f64) -> f64 {
+    let lattice = |i: i64| {
+        let mut h = seed ^ (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^= h >> 31;
+        (h >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    };
+    let i = x.floor();
+    let t = x - i;
+    let t = t * t * (3.0 - 2.0 * t);
+    let (a, b) = (lattice(i as i64), lattice(i as i64 + 1));
+    a + (b - a) * t
+}
+// Shade of a pipe column, turned by `hue` degrees.
+fn pipe_shade(style: &PipeStyle, x: i32, total_w: i32, hue: f64) -> Rgb {
+    let stops = style.stops;
+    if total_w <= 1 {
+        return stops[1].1.hue_rotate(hue);
+    }
+    let t = (x as f64 / (total_w - 1) as f64 * 256.0) as u16;
+    for pair in stops.windows(2) {
+        let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+        if t < t1 {
+            let c = Rgb::lerp_linear(c0, c1, (t.saturating_sub(t0) * 256 / (t1 - t0)).min(256));
+            return c.hue_rotate(hue);
+        }
+    }
+    stops[stops.len() - 1].1.hue_rotate(hue)
+}
+fn pipe_body_color(style: &PipeStyle, x: i32, y: i32, total_w: i32, sy: f64, hue: f64) -> Rgb {
+    let c = pipe_shade(style, x, total_w, hue);
+    match style.pattern {
+        PipePattern::Plain => c,
+        PipePattern::Stripes { color, period } => {
+            let p = (period * sy).max(2.0) as i32;
+            if (x + y).rem_euclid(p) < p / 2 {
+                Rgb::lerp(c, color, 180)
+            } else {
+                c
+            }
+        }
+        PipePattern::Rings { color, period } => {
+            let p = (period * sy).max(3.0) as i32;
+            if y.rem_euclid(p) == 0 { color } else { c }
+        }
+    }
+}
+// ── Layers ──────────────────────────────────────────────────────────────────
+
+// One slice of the picture. World layers are drawn through the camera and the
+// mirror transform; screen-space layers (HUD, menus) are drawn as-is.
+pub(crate) trait Layer {
+    fn name(&self) -> &'static str;
+
+    fn screen_space(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, game: &Game, buf: &mut PixelBuf, cam: &Camera);
+}
+
+macro_rules! layer {
+    ($ty:ident, $name:literal, world: $method:ident) => {
+        layer!($ty, $name, false, |game, buf, cam| game.$method(buf, cam));
+    };
+    ($ty:ident, $name:literal, screen: $method:ident) => {
+        layer!($ty, $name, true, |game, buf, _cam| game.$method(buf));
+    };
+    ($ty:ident, $name:literal, $screen:literal, |$game:ident, $buf:ident, $cam:ident| $body:expr) => {
+        pub(crate) struct $ty;
+
+        impl Layer for $ty {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn screen_space(&self) -> bool {
+                $screen
+            }
+
+            fn draw(&self, $game: &Game, $buf: &mut PixelBuf, $cam: &Camera) {
+                $body
+            }
+        }
+    };
+}
+
+layer!(SkyLayer, "sky", world: draw_sky);
+layer!(HillsLayer, "hills", world: draw_hills);
+layer!(BackdropLayer, "sky", world: draw_backdrop);
+layer!(DecorationsLayer, "decorations", world: draw_decorations);
+layer!(PipesLayer, "pipes", world: draw_pipes);
+layer!(PipeOutlinesLayer, "pipes", world: draw_pipe_outlines);
+layer!(CheckpointsLayer, "checkpoints", world: draw_checkpoints);
+layer!(PistonsLayer, "pistons", world: draw_pistons);
+layer!(GroundLayer, "ground", world: draw_ground);
+layer!(BirdLayer, "bird", world: draw_bird);
+layer!(FogLayer, "fog", world: draw_fog);
+layer!(StaminaLayer, "stamina", screen: draw_stamina);
+layer!(EnergyLayer, "energy", world: draw_energy);
+layer!(HudLayer, "hud", screen: draw_score);
+layer!(BestProgressLayer, "progress", screen: draw_best_progress);
+layer!(MilestoneLayer, "milestone", screen: draw_milestone);
+layer!(MutatorsOverlay, "mutators", screen: draw_mutators);
+layer!(StatsOverlay, "stats", screen: draw_stats);
+layer!(FlapArcLayer, "flap arc", world: draw_flap_arc);
+layer!(TrajectoryHintLayer, "hint", world: draw_trajectory_hint);
+layer!(PlaygroundOverlay, "playground", screen: draw_playground);
+layer!(TouchOverlay, "touch", screen: draw_touch_buttons);
+layer!(TitleOverlay, "title", screen: draw_title);
+layer!(DozeOverlay, "doze", screen: draw_doze);
+layer!(GameOverOverlay, "game over", screen: draw_game_over);
+layer!(TooSmallScreen, "too small", screen: draw_too_small);
+layer!(EditorScreen, "editor", screen: draw_editor);
+
+const DEFAULT_LAYERS: [&dyn Layer; 10] = [
+    &SkyLayer,
+    &HillsLayer,
+    &DecorationsLayer,
+    &PipesLayer,
+    &CheckpointsLayer,
+    &PistonsLayer,
+    &GroundLayer,
+    &BirdLayer,
+    &HudLayer,
+    &MilestoneLayer,
+];
+
+pub(crate) fn remove_layer(layers: &mut Vec<&'static dyn Layer>, name: &str) {
+    layers.retain(|l| l.name() != name);
+}
+
+pub(crate) fn replace_layer(
+    layers: &mut [&'static dyn Layer],
+    name: &str,
+    with: &'static dyn Layer,
+) {
+    for l in layers.iter_mut().filter(|l| l.name() == name) {
+        *l = with;
+    }
+}
+
+pub(crate) fn insert_layer_after(
+    layers: &mut Vec<&'static dyn Layer>,
+    name: &str,
+    layer: &'static dyn Layer,
+) {
+    let at = layers
+        .iter()
+        .position(|l| l.name() == name)
+        .map_or(layers.len(), |i| i + 1);
+    layers.insert(at, layer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(layers: &[&'static dyn Layer]) -> Vec<&'static str> {
+        layers.iter().map(|l| l.name()).collect()
+    }
+
+    #[test]
+    fn noise_is_bounded_and_repeatable() {
+        for i in 0..2000 {
+            let x = i as f64 * 0.37;
+            let v = hill_noise(5, x);
+            assert!((-1.5..=1.5).contains(&v));
+            assert_eq!(v, hill_noise(5, x));
+        }
+        assert_ne!(hill_noise(5, 10.5), hill_noise(6, 10.5));
+    }
+
+    #[test]
+    fn layers_can_be_swapped_added_and_dropped() {
+        let mut layers = DEFAULT_LAYERS.to_vec();
+        replace_layer(&mut layers, "pipes", &PipeOutlinesLayer);
+        insert_layer_after(&mut layers, "bird", &FogLayer);
+        remove_layer(&mut layers, "hills");
+        let names = names(&layers);
+        assert!(!names.contains(&"hills"));
+        let bird = names.iter().position(|n| *n == "bird").unwrap();
+        assert_eq!(names[bird + 1], "fog");
+
+        let mut layers = DEFAULT_LAYERS.to_vec();
+        insert_layer_after(&mut layers, "missing", &FogLayer);
+        assert_eq!(layers.last().map(|l| l.name()), Some("fog"));
+    }
+
+    #[test]
+    fn every_frame_is_fully_drawn() {
+        let mut game = Game::headless(3);
+        let mut buf = PixelBuf::new(game.pw, game.ph);
+        buf.px.fill(Rgb(1, 2, 3));
+        game.step(true);
+        game.draw(&mut buf);
+        assert!(!buf.px.contains(&Rgb(1, 2, 3)));
+    }
+}
@@ -0,0 +1,158 @@
+//! `FlappyError`: what can go wrong around the game, in the terminal, the
+//! audio device, the files it reads and writes or its command line, with the
+//! context to say what to do about it.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// ── Errors ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub enum FlappyError {
+    // The terminal couldn't be set up, read or drawn to while doing `action`.
+    Terminal {
+        action: &'static str,
+        source: io::Error,
+    },
+    // No audio output could be opened.
+    #[cfg(not(target_arch = "wasm32"))]
+    Audio(rodio::StreamError),
+    // A settings or state file, like the run history or the autosave,
+    // couldn't be saved. `path` is None when there was nowhere to put it.
+    Config {
+        what: &'static str,
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    // A file asked for, like a screenshot, a trace, a demo, the log or a
+    // mirror, couldn't be written.
+    Asset {
+        what: &'static str,
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    // The command line asked for something that can't be done. The message
+    // says what was wrong and how to ask.
+    Usage(String),
+}
+
+impl FlappyError {
+    pub fn terminal(action: &'static str) -> impl FnOnce(io::Error) -> FlappyError {
+        move |source| FlappyError::Terminal { action, source }
+    }
+
+    pub fn config(what: &'static str, path: PathBuf) -> impl FnOnce(io::Error) -> FlappyError {
+        move |source| FlappyError::Config {
+            what,
+            path: Some(path),
+            source,
+        }
+    }
+
+    pub fn asset(what: &'static str, path: PathBuf) -> impl FnOnce(io::Error) -> FlappyError {
+        move |source| FlappyError::Asset {
+            what,
+            path: Some(path),
+            source,
+        }
+    }
+
+    // Nowhere to save `what`, as neither HOME nor the XDG directory is set.
+    pub(crate) fn no_config_dir(what: &'static str) -> FlappyError {
+        FlappyError::Config {
+            what,
+            path: None,
+            source: io::Error::new(io::ErrorKind::NotFound, "no config directory"),
+        }
+    }
+
+    pub(crate) fn no_asset_dir(what: &'static str) -> FlappyError {
+        FlappyError::Asset {
+            what,
+            path: None,
+            source: io::Error::new(io::ErrorKind::NotFound, "no config directory"),
+        }
+    }
+}
+
+impl fmt::Display for FlappyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlappyError::Terminal { action, source } => write!(f, "Can't {action}: {source}."),
+            #[cfg(not(target_arch = "wasm32"))]
+            FlappyError::Audio(e) => write!(
+                f,
+                "Can't open an audio output: {e}. Check that a sound device is connected \
+                 and not held by another program."
+            ),
+            FlappyError::Config {
+                what,
+                path: Some(path),
+                source,
+            } => write!(
+                f,
+                "Can't save {what} to {}: {source}. Check that its directory is writable.",
+                path.display()
+            ),
+            FlappyError::Asset {
+                what,
+                path: Some(path),
+                source,
+            } => write!(f, "Can't write {what} to {}: {source}.", path.display()),
+            FlappyError::Usage(message) => write!(f, "{message}"),
+            FlappyError::Config { what, source, .. } | FlappyError::Asset { what, source, .. } => {
+                write!(
+                    f,
+                    "Can't save {what}: {source}. Set HOME, or XDG_CONFIG_HOME and \
+                     XDG_STATE_HOME, to somewhere writable."
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlappyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FlappyError::Terminal { source, .. }
+            | FlappyError::Config { source, .. }
+            | FlappyError::Asset { source, .. } => Some(source),
+            #[cfg(not(target_arch = "wasm32"))]
+            FlappyError::Audio(e) => Some(e),
+            FlappyError::Usage(_) => None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<rodio::StreamError> for FlappyError {
+    fn from(e: rodio::StreamError) -> Self {
+        FlappyError::Audio(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn messages_say_what_failed_and_where() {
+        let denied = || io::Error::new(io::ErrorKind::PermissionDenied, "permission denied");
+        let e = FlappyError::config("the run history", PathBuf::from("/x/history"))(denied());
+        assert_eq!(
+            e.to_string(),
+            "Can't save the run history to /x/history: permission denied. \
+             Check that its directory is writable."
+        );
+        assert!(e.source().is_some());
+        let e = FlappyError::no_config_dir("the autosave");
+        assert!(
+            e.to_string()
+                .starts_with("Can't save the autosave: no config directory.")
+        );
+        let e = FlappyError::terminal("read the keyboard")(denied());
+        assert_eq!(e.to_string(), "Can't read the keyboard: permission denied.");
+    }
+}
@@ -0,0 +1,83 @@
+//! The input events the game handles: keys, mouse and resizes. In a terminal
+//! they're crossterm's own. crossterm doesn't build for the web, so there
+//! the browser's events are turned into look-alikes with the same names and
+//! the parts of them the game reads.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+
+#[cfg(target_arch = "wasm32")]
+pub use browser::*;
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Event {
+        FocusGained,
+        FocusLost,
+        Key(KeyEvent),
+        Mouse(MouseEvent),
+        Resize(u16, u16),
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct KeyEvent {
+        pub code: KeyCode,
+        pub kind: KeyEventKind,
+    }
+
+    impl From<KeyCode> for KeyEvent {
+        fn from(code: KeyCode) -> KeyEvent {
+            KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum KeyCode {
+        Backspace,
+        Enter,
+        Left,
+        Right,
+        Up,
+        Down,
+        Tab,
+        Char(char),
+        Esc,
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum KeyEventKind {
+        Press,
+        Repeat,
+        Release,
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct MouseEvent {
+        pub kind: MouseEventKind,
+        pub column: u16,
+        pub row: u16,
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum MouseEventKind {
+        Down(MouseButton),
+        Up(MouseButton),
+        Drag(MouseButton),
+        Moved,
+        ScrollDown,
+        ScrollUp,
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum MouseButton {
+        Left,
+        Right,
+        Middle,
+    }
+}
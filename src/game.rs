@@ -0,0 +1,3195 @@
+//! The simulation: physics, pipes, bosses, seeds, run history and snapshots.
+//! Nothing here touches the terminal.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+// std can't read the clock in a browser; web-time asks the page instead.
+#[cfg(not(feature = "web"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "web")]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{SoundPalette, whoosh_volume};
+use crate::capture::DEMO_SIZE;
+use crate::error::FlappyError;
+use crate::pixels::Rgb;
+use crate::render::{BIRD_BEAK, RollingNumber, SKY_BOT, SKY_TOP, WHITE};
+use crate::theme::{
+    BirdSkin, DEFAULT_SKIN, EnergyRules, GapRules, HillShape, PIPE_STYLES, PipeStyle, Tuning,
+    config_dir, state_dir,
+};
+use crate::ui::Screen;
+
+// ── World coordinate system ──────────────────────────────────────────────────
+
+pub(crate) const WORLD_H: f64 = 104.0;
+const GROUND_H: f64 = 17.0;
+pub(crate) const SKY_H: f64 = WORLD_H - GROUND_H;
+
+const GRAVITY: f64 = 0.433;
+const FLAP_VEL: f64 = -4.333;
+pub(crate) const PIPE_SPEED: f64 = 2.6;
+const PIPE_SPACING: f64 = PIPE_SPEED * 30.0;
+
+const PIPE_GAP: f64 = 32.0;
+pub(crate) const PIPE_W: f64 = 14.0;
+pub(crate) const PISTON_W: f64 = 20.0;
+pub(crate) const PIPE_CAP_H: f64 = 6.5;
+pub(crate) const PIPE_CAP_EXTRA: f64 = 4.33;
+// How far in from each corner pipe caps are cut away, drawn and collided alike.
+pub(crate) const PIPE_CAP_CHAMFER: f64 = 1.0;
+
+const BIRD_X_PCT: f64 = 0.22;
+const BIRD_HITBOX_HW: f64 = 4.33;
+pub(crate) const BIRD_HITBOX_HH: f64 = 3.25;
+const BIRD_BOB_AMP: f64 = 6.5;
+
+// Each gap is kept within reach of the last for a player tapping every
+// TAP_FRAMES, asking for REACH_EASY of that reach at first and all of it by
+// REACH_FULL_SCORE.
+const TAP_FRAMES: u32 = 5;
+const REACH_EASY: f64 = 0.6;
+const REACH_FULL_SCORE: u32 = 50;
+
+const DECORATION_MAX_W: f64 = 12.0;
+
+pub(crate) const FOG_RADIUS: f64 = 18.0;
+pub(crate) const FOG_FALLOFF: f64 = 20.0;
+pub(crate) const FOG_DENSITY: f64 = 235.0;
+pub(crate) const VANISH_RAMP: f64 = 12.0;
+pub(crate) const VANISH_CAP_ALPHA: f64 = 40.0;
+pub(crate) const PIPE_HUE_MAX: f64 = -100.0;
+// Glide stamina runs from 1 to 0: a full bar lasts 1.5 s and refills in 3 s.
+pub(crate) const GLIDE_GRAVITY: f64 = 0.5;
+const GLIDE_DRAIN: f64 = 1.0 / 45.0;
+const GLIDE_RECHARGE: f64 = 1.0 / 90.0;
+
+// Visual unit: converts original design base values to world units.
+// At 212x52, VU ≈ 2.167. For drawing: dimension_px = base * VU * sy = base * old_scale.
+pub(crate) const VU: f64 = WORLD_H / 48.0;
+
+const ZOOM_MIN: f64 = 0.5;
+const ZOOM_MAX: f64 = 2.0;
+
+// Frames into the death fall before a flap is buffered as a restart.
+pub(crate) const GAME_OVER_DELAY: u32 = 15;
+const SHAKE_FRAMES: u32 = 8;
+const SHAKE_PX: f64 = 3.0;
+pub(crate) const RESTART_BUFFER_DELAY: u32 = 6;
+
+// Frames after the bird's front edge clears a pipe during which its tail may
+// still clip the pipe without a crash.
+pub(crate) const COYOTE_FRAMES: f64 = 3.0;
+pub(crate) const COYOTE_MAX: f64 = 10.0;
+
+// A pipe passed with the bird's hitbox this close to an edge of its opening
+// is a near miss.
+const NEAR_MISS: f64 = VU;
+
+// Kid mode: gaps this much taller, and frames after a bonk before the bird
+// can bonk again, blinking meanwhile.
+const KID_GAP_SCALE: f64 = 1.6;
+const KID_BONK_FRAMES: u32 = 30;
+
+// The photo-finish replay: the last three seconds before landing, at half speed.
+const REPLAY_FRAMES: usize = 90;
+const REPLAY_SLOWDOWN: u32 = 2;
+
+// `update_dt` plays at most this many steps at once, so a long stall, like a
+// suspended laptop, is skipped rather than played out in a rush.
+const MAX_CATCH_UP: u32 = 8;
+
+// Presses closer together than this are terminal auto-repeat, not taps.
+pub(crate) const KEY_REPEAT_WINDOW: Duration = Duration::from_millis(70);
+// A held key stops counting as held once repeats stop for this long.
+pub(crate) const KEY_HOLD_TIMEOUT: Duration = Duration::from_millis(120);
+
+pub const FRAME_DUR: Duration = Duration::from_millis(33); // ~30 fps
+pub const SIM_FPS: u32 = 30;
+pub const MAX_FPS: u32 = 240;
+pub const LOW_POWER_FPS: u32 = 15;
+// A title screen left alone for DOZE_AFTER dozes: it dims, and the host draws
+// it DOZE_FPS times a second until a key wakes it.
+pub const DOZE_AFTER: Duration = Duration::from_secs(5 * 60);
+pub const DOZE_FPS: u32 = 2;
+
+pub const MIN_COLS: u16 = 40;
+pub(crate) const MIN_ROWS: u16 = 25;
+pub(crate) const MIN_ROWS_FULL_BLOCKS: u16 = 20;
+
+// ── Config ──────────────────────────────────────────────────────────────────
+
+// The numbers a game starts from, in world units and steps. `Default` is the
+// stock game; difficulty presets, flags and config files start from it and
+// change what they need. Themes and the playground scale these in turn.
+#[derive(Clone)]
+pub struct GameConfig {
+    pub gravity: f64,
+    pub flap_vel: f64,
+    pub pipe_gap: f64,
+    pub pipe_spacing: f64,
+    pub pipe_speed: f64,
+    // How long a step lasts. Physics is per step, so longer steps make a
+    // slower game rather than a choppier one.
+    pub frame_dur: Duration,
+    // The sky gradient, top to bottom, for themes that don't set one.
+    pub sky: Vec<Rgb>,
+    // A flap while the bird falls starts the next run at once, skipping the
+    // fall, the replay and the game-over panel.
+    pub instant_restart: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            gravity: GRAVITY,
+            flap_vel: FLAP_VEL,
+            pipe_gap: PIPE_GAP,
+            pipe_spacing: PIPE_SPACING,
+            pipe_speed: PIPE_SPEED,
+            frame_dur: FRAME_DUR,
+            sky: vec![SKY_TOP, SKY_BOT],
+            instant_restart: false,
+        }
+    }
+}
+
+// ── Deterministic physics ───────────────────────────────────────────────────
+
+// Fixed physics snaps every rate and spawn position to a 1/65536 grid, so each
+// step is an exact sum of grid values and a seed plays out the same on every
+// platform. Oscillators use a polynomial sine, since libm's varies.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Physics {
+    Float,
+    Fixed,
+}
+
+const FIXED_ONE: f64 = 65536.0;
+
+impl Physics {
+    // Seeded runs are the ones that get compared, so they default to fixed.
+    pub fn from_env(seeded: bool) -> Physics {
+        match std::env::var("FLAPPY_PHYSICS").as_deref() {
+            Ok("fixed") => Physics::Fixed,
+            Ok("float") => Physics::Float,
+            _ if seeded => Physics::Fixed,
+            _ => Physics::Float,
+        }
+    }
+
+    fn snap(self, v: f64) -> f64 {
+        match self {
+            Physics::Float => v,
+            Physics::Fixed => (v * FIXED_ONE).round() / FIXED_ONE,
+        }
+    }
+
+    fn sin(self, t: f64) -> f64 {
+        match self {
+            Physics::Float => t.sin(),
+            Physics::Fixed => poly_sin(t),
+        }
+    }
+}
+
+// Bhaskara I's approximation, within 0.002 of the real sine.
+fn poly_sin(t: f64) -> f64 {
+    use std::f64::consts::{PI, TAU};
+    let x = t.rem_euclid(TAU);
+    let (x, sign) = if x > PI { (x - PI, -1.0) } else { (x, 1.0) };
+    let p = x * (PI - x);
+    sign * 16.0 * p / (5.0 * PI * PI - 4.0 * p)
+}
+
+// ── Seed codes ──────────────────────────────────────────────────────────────
+
+// Seeds are shared as "brave-otter-42": an adjective, an animal and a number
+// below 100, from lists kept short, friendly and unambiguous. Seeds past
+// SEED_CODES have no code and are shared as plain numbers.
+const SEED_ADJECTIVES: [&str; 64] = [
+    "brave", "calm", "clever", "cozy", "eager", "fancy", "fluffy", "gentle", "happy", "jolly",
+    "kind", "lucky", "merry", "mighty", "nimble", "noble", "plucky", "proud", "quick", "quiet",
+    "rapid", "shiny", "silly", "sleek", "smart", "snappy", "sunny", "swift", "tidy", "witty",
+    "zesty", "bold", "bright", "bubbly", "cheery", "chirpy", "crisp", "dapper", "daring", "dizzy",
+    "breezy", "fuzzy", "glad", "golden", "grand", "hearty", "humble", "jazzy", "keen", "lively",
+    "loyal", "mellow", "misty", "peppy", "perky", "polite", "rosy", "snug", "spry", "stout",
+    "super", "sweet", "cosmic", "frosty",
+];
+
+const SEED_ANIMALS: [&str; 64] = [
+    "otter", "badger", "beaver", "bison", "crane", "dingo", "eagle", "falcon", "ferret", "finch",
+    "gecko", "heron", "hippo", "ibis", "koala", "lemur", "llama", "lynx", "magpie", "marten",
+    "moose", "newt", "ocelot", "oriole", "panda", "parrot", "puffin", "quail", "rabbit", "raven",
+    "robin", "salmon", "seal", "sloth", "snail", "squid", "stork", "swan", "tapir", "tiger",
+    "toucan", "trout", "turtle", "walrus", "wombat", "wren", "yak", "zebra", "alpaca", "bunny",
+    "camel", "dove", "duck", "egret", "fox", "goose", "hare", "jay", "kiwi", "lark", "mole", "owl",
+    "puma", "tern",
+];
+
+pub(crate) const SEED_CODES: u64 = 64 * 64 * 100;
+
+pub(crate) fn encode_seed(seed: u64) -> String {
+    if seed >= SEED_CODES {
+        return seed.to_string();
+    }
+    let words = (seed / 100) as usize;
+    format!(
+        "{}-{}-{}",
+        SEED_ADJECTIVES[words / 64],
+        SEED_ANIMALS[words % 64],
+        seed % 100
+    )
+}
+
+// Reads either form back, ignoring case.
+pub fn decode_seed(code: &str) -> Option<u64> {
+    let code = code.trim().to_ascii_lowercase();
+    if let Ok(seed) = code.parse() {
+        return Some(seed);
+    }
+    let mut parts = code.split('-');
+    let (adjective, animal, number) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let adjective = SEED_ADJECTIVES.iter().position(|w| *w == adjective)?;
+    let animal = SEED_ANIMALS.iter().position(|w| *w == animal)?;
+    let number: u64 = number.parse().ok().filter(|n| *n < 100)?;
+    Some((adjective * 64 + animal) as u64 * 100 + number)
+}
+
+// ── Run history ─────────────────────────────────────────────────────────────
+
+// Assists and difficulty settings that change how hard a run is.
+const ASSIST_HINT: u8 = 1;
+const ASSIST_KID: u8 = 2;
+const ASSIST_ADAPTIVE: u8 = 4;
+
+// The rules a run was played under. Scores are only ranked against runs of
+// the same category: the same mutators, the same assists, the same physics,
+// as a hash of the tuned values that is 0 for the stock ones, and the same
+// points a pipe, which events like double points weekends raise.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Category {
+    mutators: u16,
+    assists: u8,
+    physics: u64,
+    points: u32,
+}
+
+impl Default for Category {
+    fn default() -> Self {
+        Category {
+            mutators: 0,
+            assists: 0,
+            physics: 0,
+            points: 1,
+        }
+    }
+}
+
+impl Category {
+    // Names the category on the stats screen, by the mutator letters and the
+    // assists, which `short` cuts to their first letters.
+    pub(crate) fn label(self, short: bool) -> String {
+        let mut parts = vec![Mutators(self.mutators).code()];
+        let extras = [
+            (self.assists & ASSIST_HINT != 0, "ASSIST"),
+            (self.assists & ASSIST_KID != 0, "KID"),
+            (self.assists & ASSIST_ADAPTIVE != 0, "ADAPTIVE"),
+            (self.physics != 0, "TUNED"),
+        ];
+        for (_, name) in extras.iter().filter(|(on, _)| *on) {
+            parts.push(if short {
+                name[..1].to_string()
+            } else {
+                name.to_string()
+            });
+        }
+        if self.points != 1 {
+            parts.push(format!("x{}", self.points));
+        }
+        parts.retain(|part| !part.is_empty());
+        if parts.is_empty() {
+            "STANDARD".to_string()
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+// One finished run, as a line of `history` in the config directory: when it
+// ended (Unix seconds), its score, and its category as the mutators, the
+// assists, the physics hash and the points a pipe. Those last three are left
+// off when stock, and older lines have a 1 for the trajectory hint in place
+// of the assists.
+#[derive(Clone, Copy)]
+pub struct RunRecord {
+    when: u64,
+    pub(crate) score: u32,
+    pub(crate) category: Category,
+}
+
+impl RunRecord {
+    fn parse(line: &str) -> Option<RunRecord> {
+        let mut fields = line.split_whitespace().map(str::parse::<u64>);
+        Some(RunRecord {
+            when: fields.next()?.ok()?,
+            score: u32::try_from(fields.next()?.ok()?).ok()?,
+            category: Category {
+                mutators: u16::try_from(fields.next()?.ok()?).ok()?,
+                assists: u8::try_from(fields.next().unwrap_or(Ok(0)).ok()?).ok()?,
+                physics: fields.next().unwrap_or(Ok(0)).ok()?,
+                points: u32::try_from(fields.next().unwrap_or(Ok(1)).ok()?).ok()?,
+            },
+        })
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("history"))
+}
+
+pub fn load_history() -> Vec<RunRecord> {
+    history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| text.lines().filter_map(RunRecord::parse).collect())
+        .unwrap_or_default()
+}
+
+pub fn append_history(run: RunRecord) -> Result<(), FlappyError> {
+    let path = history_path().ok_or_else(|| FlappyError::no_config_dir("the run history"))?;
+    write_history(&path, run).map_err(FlappyError::config("the run history", path))
+}
+
+fn write_history(path: &Path, run: RunRecord) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let Category {
+        mutators,
+        assists,
+        physics,
+        points,
+    } = run.category;
+    write!(file, "{} {} {mutators}", run.when, run.score)?;
+    if assists != 0 || physics != 0 || points != 1 {
+        write!(file, " {assists}")?;
+    }
+    if physics != 0 || points != 1 {
+        write!(file, " {physics}")?;
+    }
+    if points != 1 {
+        write!(file, " {points}")?;
+    }
+    writeln!(file)
+}
+
+// ── Sessions ────────────────────────────────────────────────────────────────
+
+// While playing, the run is saved this often, in frames.
+pub const AUTOSAVE_FRAMES: u64 = 5 * SIM_FPS as u64;
+
+// `session.json` in the state directory holds the run in progress: the run's
+// seed, then its snapshot. It's removed when a run ends and when the game
+// quits, so it doubles as the dirty flag: finding one on startup means the
+// game died or lost its terminal mid-run.
+fn session_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("session.json"))
+}
+
+// A session written by a build whose `Snapshot` had other fields fails to
+// parse, and is ignored rather than misread.
+#[derive(Serialize, Deserialize)]
+struct SavedRun {
+    seed: u64,
+    run: Snapshot,
+}
+
+pub fn save_autosave(game: &Game) -> Result<(), FlappyError> {
+    let path = session_path().ok_or_else(|| FlappyError::no_config_dir("the autosave"))?;
+    write_autosave(&path, game).map_err(FlappyError::config("the autosave", path))
+}
+
+fn write_autosave(path: &Path, game: &Game) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let session = serde_json::to_string(&SavedRun {
+        seed: game.run_seed,
+        run: game.snapshot(),
+    })?;
+    // Written aside and renamed over, so dying mid-write keeps the last save.
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, format!("{session}\n"))?;
+    fs::rename(tmp, path)
+}
+
+// The saved run, if it was being played or was paused.
+pub fn load_autosave() -> Option<(u64, Snapshot)> {
+    let text = fs::read_to_string(session_path()?).ok()?;
+    let SavedRun { seed, run } = serde_json::from_str(&text).ok()?;
+    resumable(&run).then_some((seed, run))
+}
+
+// Whether a saved run is one to pick up again: mid-run, and holding only what
+// a game could have, as a hand-edited file may not.
+fn resumable(run: &Snapshot) -> bool {
+    let playing = matches!(run.state, State::Playing | State::Paused);
+    let past = |at: u64| at <= run.frame;
+    let sane = run
+        .milestone
+        .is_none_or(|(i, at)| i < MILESTONES.len() && past(at))
+        && run.zone_banner.is_none_or(|(_, at)| past(at));
+    playing && sane
+}
+
+pub fn clear_autosave() {
+    if let Some(path) = session_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// ── Adaptive difficulty ─────────────────────────────────────────────────────
+
+// Runs the death rate is taken over, and how far one run moves the level.
+const ADAPT_WINDOW: usize = 5;
+const ADAPT_STEP: f64 = 0.1;
+// Deaths per pipe reached above which the game eases off (dying before the
+// third pipe on average), and below which it tightens (past the fifteenth).
+const ADAPT_EASE_RATE: f64 = 0.25;
+const ADAPT_TIGHTEN_RATE: f64 = 1.0 / 16.0;
+
+// FLAPPY_ADAPTIVE rubber-bands gaps and spacing to how the player is doing.
+// The level runs from -1 (widest gaps, most room) through 0 (stock) to 1, and
+// is kept with the recent scores in `adaptive` in the config directory.
+#[derive(Clone, Default)]
+pub struct Adaptive {
+    pub(crate) level: f64,
+    recent: Vec<u32>,
+}
+
+impl Adaptive {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("adaptive"))
+    }
+
+    // The level, then the recent scores, oldest first.
+    pub fn load() -> Adaptive {
+        let text = Adaptive::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        let mut fields = text.split_whitespace();
+        let level = fields
+            .next()
+            .and_then(|f| f.parse::<f64>().ok())
+            .filter(|l| l.is_finite())
+            .map_or(0.0, |l| l.clamp(-1.0, 1.0));
+        let recent: Vec<u32> = fields.filter_map(|f| f.parse().ok()).collect();
+        let recent = recent[recent.len().saturating_sub(ADAPT_WINDOW)..].to_vec();
+        Adaptive { level, recent }
+    }
+
+    pub fn save(&self) -> Result<(), FlappyError> {
+        const WHAT: &str = "the difficulty level";
+        let path = Adaptive::path().ok_or_else(|| FlappyError::no_config_dir(WHAT))?;
+        let mut text = format!("{:.1}", self.level);
+        for score in &self.recent {
+            text += &format!(" {score}");
+        }
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, text + "\n"))
+            .map_err(FlappyError::config(WHAT, path))
+    }
+
+    fn record(&mut self, score: u32) {
+        self.recent.push(score);
+        if self.recent.len() > ADAPT_WINDOW {
+            self.recent.remove(0);
+        }
+        let deaths = self.recent.len() as f64;
+        let rate = deaths / (deaths + self.recent.iter().sum::<u32>() as f64);
+        if rate > ADAPT_EASE_RATE {
+            self.level -= ADAPT_STEP;
+        } else if rate < ADAPT_TIGHTEN_RATE {
+            self.level += ADAPT_STEP;
+        }
+        // Rounded so repeated steps don't drift off the tenths.
+        self.level = ((self.level * 10.0).round() / 10.0).clamp(-1.0, 1.0);
+    }
+
+    // Easing off goes further than tightening: at the bottom gaps are 30%
+    // taller and pipes 25% further apart, at the top 10% and 8% less.
+    fn gap_scale(&self) -> f64 {
+        if self.level < 0.0 {
+            1.0 - 0.3 * self.level
+        } else {
+            1.0 - 0.1 * self.level
+        }
+    }
+
+    fn spacing_scale(&self) -> f64 {
+        if self.level < 0.0 {
+            1.0 - 0.25 * self.level
+        } else {
+            1.0 - 0.08 * self.level
+        }
+    }
+
+    // Ground hazards die out toward the bottom and come half again as often
+    // at the top.
+    fn hazard_scale(&self) -> f64 {
+        if self.level < 0.0 {
+            1.0 + self.level
+        } else {
+            1.0 + 0.5 * self.level
+        }
+    }
+}
+
+// ── Camera ──────────────────────────────────────────────────────────────────
+
+// Maps world coordinates onto one viewport of the pixel buffer: `scale`
+// pixels per world unit, with world row `y` at the top of the view. `shake`
+// nudges the picture without moving the viewport, so fills still cover it.
+#[derive(Clone, Copy)]
+pub(crate) struct Camera {
+    pub(crate) scale: f64,
+    pub(crate) y: f64,
+    pub(crate) view: (i32, i32, i32, i32),
+    pub(crate) shake: (i32, i32),
+}
+
+impl Camera {
+    pub(crate) fn new(scale: f64, y: f64, w: usize, h: usize) -> Self {
+        Camera {
+            scale,
+            y,
+            view: (0, 0, w as i32, h as i32),
+            shake: (0, 0),
+        }
+    }
+
+    pub(crate) fn fx(&self, world_x: f64) -> f64 {
+        world_x * self.scale + (self.view.0 + self.shake.0) as f64
+    }
+
+    pub(crate) fn fy(&self, world_y: f64) -> f64 {
+        (world_y - self.y) * self.scale + (self.view.1 + self.shake.1) as f64
+    }
+
+    pub(crate) fn x(&self, world_x: f64) -> i32 {
+        self.fx(world_x).floor() as i32
+    }
+
+    pub(crate) fn y(&self, world_y: f64) -> i32 {
+        self.fy(world_y).floor() as i32
+    }
+
+    pub(crate) fn world_x(&self, px: i32) -> f64 {
+        (px - self.view.0 - self.shake.0) as f64 / self.scale
+    }
+
+    // Pixel columns and rows covered by the viewport.
+    pub(crate) fn cols(&self) -> Range<i32> {
+        self.view.0..self.view.0 + self.view.2
+    }
+
+    pub(crate) fn rows(&self) -> Range<i32> {
+        self.view.1..self.view.1 + self.view.3
+    }
+}
+
+// ── Entities ────────────────────────────────────────────────────────────────
+
+// Names one entity for the whole run. IDs only grow, so they also order
+// entities by spawn time.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+struct EntityId(u32);
+
+// What every scrolling entity has: a left edge and a width, in world units.
+pub(crate) trait Body {
+    fn x(&self) -> f64;
+    fn x_mut(&mut self) -> &mut f64;
+    fn width(&self) -> f64;
+}
+
+// All entities of one kind, in spawn order, so the newest is always last.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Pool<T> {
+    items: Vec<(EntityId, T)>,
+}
+
+impl<T> Pool<T> {
+    pub(crate) fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    fn push(&mut self, id: EntityId, item: T) {
+        self.items.push((id, item));
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().map(|(_, e)| e)
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.items.last().map(|(_, e)| e)
+    }
+
+    fn retain(&mut self, keep: impl Fn(&T) -> bool) {
+        self.items.retain(|(_, e)| keep(e));
+    }
+}
+
+// The systems shared by everything that scrolls.
+impl<T: Body> Pool<T> {
+    // Moves everything left, dropping whatever has left the screen.
+    fn scroll(&mut self, speed: f64) {
+        for (_, e) in &mut self.items {
+            *e.x_mut() -= speed;
+        }
+        self.retain(|e| e.x() + e.width() + 5.0 > 0.0);
+    }
+
+    // Whether the bird's hitbox overlaps an entity outside its opening, given
+    // as world-space top and bottom, or the caps framing the opening.
+    fn hits(
+        &self,
+        bx: f64,
+        by: f64,
+        grace: f64,
+        caps: Caps,
+        opening: impl Fn(&T) -> (f64, f64),
+    ) -> bool {
+        let bird = HitRect::around(bx, by, BIRD_HITBOX_HW, BIRD_HITBOX_HH);
+        self.iter().any(|e| {
+            let (top, bot) = opening(e);
+            let (x0, x1) = (e.x(), e.x() + e.width());
+            let mut rects = vec![
+                HitRect::new(x0, f64::NEG_INFINITY, x1, top - caps.h),
+                HitRect::new(x0, bot + caps.h, x1, f64::INFINITY),
+            ];
+            if caps.h > 0.0 {
+                let (x0, x1) = (x0 - caps.extra, x1 + caps.extra);
+                rects.push(HitRect::new(x0, top - caps.h, x1, top).chamfered(caps.chamfer));
+                rects.push(HitRect::new(x0, bot, x1, bot + caps.h).chamfered(caps.chamfer));
+            }
+            rects.iter().any(|r| r.hit_by(&bird, grace))
+        })
+    }
+
+    // Every entity's left edge, in order, for `set_xs` to put back.
+    fn xs(&self) -> impl Iterator<Item = f64> + '_ {
+        self.iter().map(Body::x)
+    }
+
+    fn set_xs(&mut self, xs: &mut impl Iterator<Item = f64>) {
+        for ((_, e), x) in self.items.iter_mut().zip(xs) {
+            *e.x_mut() = x;
+        }
+    }
+
+    // Moves each entity that also existed in `prev` back toward where it was
+    // there, leaving it `t` of the way from there to here.
+    fn interpolate(&mut self, prev: &Pool<T>, t: f64) {
+        for (id, e) in &mut self.items {
+            if let Some((_, p)) = prev.items.iter().find(|(pid, _)| pid == id) {
+                *e.x_mut() = p.x() + (e.x() - p.x()) * t;
+            }
+        }
+    }
+
+    // Entities whose leading edge went past `x` in the last scroll by `speed`.
+    fn reaching(&self, x: f64, speed: f64) -> impl Iterator<Item = &T> {
+        self.iter().filter(move |e| e.x() < x && e.x() + speed >= x)
+    }
+
+    // Entities spawned after `after` whose trailing edge is now behind `x`.
+    fn passed(&self, x: f64, after: Option<EntityId>) -> impl Iterator<Item = (EntityId, &T)> {
+        self.items
+            .iter()
+            .filter(move |(id, e)| after.is_none_or(|a| *id > a) && e.x() + e.width() < x)
+            .map(|(id, e)| (*id, e))
+    }
+}
+
+// The caps framing an entity's opening: `h` tall, overhanging the body by
+// `extra` on each side, corners cut by `chamfer`. Zero height means none.
+#[derive(Clone, Copy, Default)]
+struct Caps {
+    h: f64,
+    extra: f64,
+    chamfer: f64,
+}
+
+// A world-space box for collisions, with its corners cut by `chamfer`.
+#[derive(Clone, Copy)]
+struct HitRect {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    chamfer: f64,
+}
+
+impl HitRect {
+    fn new(x0: f64, y0: f64, x1: f64, y1: f64) -> Self {
+        HitRect {
+            x0,
+            y0,
+            x1,
+            y1,
+            chamfer: 0.0,
+        }
+    }
+
+    fn around(x: f64, y: f64, hw: f64, hh: f64) -> Self {
+        Self::new(x - hw, y - hh, x + hw, y + hh)
+    }
+
+    fn chamfered(self, chamfer: f64) -> Self {
+        HitRect { chamfer, ..self }
+    }
+
+    // Whether `other` overlaps this box. Once its front edge is past this
+    // box's trailing edge, overlaps are forgiven for `grace` more units.
+    fn hit_by(&self, other: &HitRect, grace: f64) -> bool {
+        if (0.0..grace).contains(&(other.x1 - self.x1)) {
+            return false;
+        }
+        let (left, right) = (other.x1 - self.x0, self.x1 - other.x0);
+        let (top, bot) = (other.y1 - self.y0, self.y1 - other.y0);
+        // Near a corner, `other` has to reach past the cut.
+        left > 0.0
+            && right > 0.0
+            && top > 0.0
+            && bot > 0.0
+            && left.min(right) + top.min(bot) >= self.chamfer
+    }
+}
+
+// Every entity in a run, one typed pool per kind.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct World {
+    // Physics mode for the run, which spawns and steps follow.
+    pub physics: Physics,
+    next_id: u32,
+    pub(crate) pipes: Pool<Pipe>,
+    pub(crate) pistons: Pool<Piston>,
+    pub(crate) decorations: Pool<Decoration>,
+    pub(crate) hazards: Pool<Hazard>,
+    // The last pipe the bird has flown through.
+    scored: Option<EntityId>,
+}
+
+impl World {
+    pub(crate) fn new(physics: Physics) -> Self {
+        Self {
+            physics,
+            next_id: 0,
+            pipes: Pool::new(),
+            pistons: Pool::new(),
+            decorations: Pool::new(),
+            hazards: Pool::new(),
+            scored: None,
+        }
+    }
+
+    fn next_id(&mut self) -> EntityId {
+        self.next_id += 1;
+        EntityId(self.next_id)
+    }
+
+    fn scroll(&mut self, speed: f64) {
+        self.pipes.scroll(speed);
+        self.pistons.scroll(speed);
+        self.hazards.scroll(speed);
+    }
+}
+
+// ── Game ────────────────────────────────────────────────────────────────────
+
+// Optional rule changes picked on the mutators screen before a run.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Mutator {
+    TinyGaps,
+    DoubleSpeed,
+    InvisiblePipes,
+    Fog,
+    MirroredControls,
+    VanishingPipes,
+    MirrorX,
+    MirrorY,
+    Bat,
+    Glide,
+    Energy,
+}
+
+pub(crate) const MUTATORS: [Mutator; 11] = [
+    Mutator::TinyGaps,
+    Mutator::DoubleSpeed,
+    Mutator::InvisiblePipes,
+    Mutator::VanishingPipes,
+    Mutator::Fog,
+    Mutator::MirroredControls,
+    Mutator::MirrorX,
+    Mutator::MirrorY,
+    Mutator::Bat,
+    Mutator::Glide,
+    Mutator::Energy,
+];
+
+impl Mutator {
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Mutator::TinyGaps => "GAPS",
+            Mutator::DoubleSpeed => "SPEED",
+            Mutator::InvisiblePipes => "GHOST",
+            Mutator::Fog => "FOG",
+            Mutator::MirroredControls => "MIRROR",
+            Mutator::VanishingPipes => "VANISH",
+            Mutator::MirrorX => "FLIP-X",
+            Mutator::MirrorY => "FLIP-Y",
+            Mutator::Bat => "BAT",
+            Mutator::Glide => "GLIDE",
+            Mutator::Energy => "ENERGY",
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Mutator::TinyGaps => 'T',
+            Mutator::DoubleSpeed => 'S',
+            Mutator::InvisiblePipes => 'G',
+            Mutator::Fog => 'F',
+            Mutator::MirroredControls => 'M',
+            Mutator::VanishingPipes => 'V',
+            Mutator::MirrorX => 'X',
+            Mutator::MirrorY => 'Y',
+            Mutator::Bat => 'B',
+            Mutator::Glide => 'L',
+            Mutator::Energy => 'E',
+        }
+    }
+}
+
+// Active mutators as a bit set, part of a run's `Category`. `code` names the
+// set on the results panel.
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) struct Mutators(u16);
+
+impl Mutators {
+    pub(crate) fn has(self, m: Mutator) -> bool {
+        self.0 & m.bit() != 0
+    }
+
+    pub(crate) fn toggle(&mut self, m: Mutator) {
+        self.0 ^= m.bit();
+    }
+
+    pub(crate) fn code(self) -> String {
+        MUTATORS
+            .iter()
+            .filter(|m| self.has(**m))
+            .map(|m| m.letter())
+            .collect()
+    }
+}
+
+// Decorations live in ground coordinates: screen x is `x - ground_x`.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Decoration {
+    pub(crate) x: f64,
+    pub(crate) kind: DecorationKind,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum DecorationKind {
+    Flower(Rgb),
+    Rock,
+    Sign,
+}
+
+const FLOWER_COLORS: [Rgb; 3] = [WHITE, BIRD_BEAK, Rgb(240, 130, 165)];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Pipe {
+    pub(crate) x: f64,
+    pub(crate) gap_center: f64,
+    // 1-based count of pipes this run; every CHECKPOINT_EVERY-th gets a flag.
+    pub(crate) number: u32,
+}
+
+impl Pipe {
+    fn opening(&self, gap: f64) -> (f64, f64) {
+        (self.gap_center - gap / 2.0, self.gap_center + gap / 2.0)
+    }
+}
+
+impl Body for Pipe {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn x_mut(&mut self) -> &mut f64 {
+        &mut self.x
+    }
+
+    fn width(&self) -> f64 {
+        PIPE_W
+    }
+}
+
+pub(crate) const CHECKPOINT_EVERY: u32 = 10;
+
+pub(crate) const MILESTONES: [(u32, &str); 4] = [
+    (10, "NICE! 10 POINTS"),
+    (25, "GREAT! 25 POINTS"),
+    (50, "AMAZING! 50 POINTS"),
+    (100, "LEGENDARY! 100 POINTS"),
+];
+pub(crate) const MILESTONE_FRAMES: u64 = 90;
+pub(crate) const ZONE_BANNER_FRAMES: u64 = 60;
+// How long the best-score bar flashes once a run passes the best.
+pub(crate) const BEST_FLASH_FRAMES: u64 = 45;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+// Medals are earned at these scores, and each one opens a named zone.
+const MEDALS: [(u32, Medal); 4] = [
+    (10, Medal::Bronze),
+    (20, Medal::Silver),
+    (30, Medal::Gold),
+    (40, Medal::Platinum),
+];
+
+impl Medal {
+    pub(crate) fn for_score(score: u32) -> Option<Medal> {
+        MEDALS
+            .iter()
+            .rev()
+            .find(|(at, _)| score >= *at)
+            .map(|(_, m)| *m)
+    }
+
+    pub(crate) fn color(self) -> Rgb {
+        match self {
+            Medal::Bronze => Rgb(205, 127, 50),
+            Medal::Silver => Rgb(200, 200, 210),
+            Medal::Gold => Rgb(250, 205, 50),
+            Medal::Platinum => Rgb(200, 240, 245),
+        }
+    }
+
+    pub(crate) fn zone_name(self) -> &'static str {
+        match self {
+            Medal::Bronze => "BRONZE ZONE",
+            Medal::Silver => "SILVER ZONE",
+            Medal::Gold => "GOLD ZONE",
+            Medal::Platinum => "PLATINUM ZONE",
+        }
+    }
+}
+
+// ── Boss encounters ─────────────────────────────────────────────────────────
+
+// Every BOSS_EVERY points the pipes stop for a crusher section: a row of pistons
+// that open and close out of phase, so the gaps have to be timed, not just aimed.
+const BOSS_EVERY: u32 = 25;
+const BOSS_FRAMES: u32 = 300;
+pub(crate) const BOSS_BANNER_FRAMES: u32 = 60;
+const PISTON_PERIOD: f64 = 70.0;
+const PISTON_MIN_OPEN: f64 = 0.3;
+const PISTON_MAX_OPEN: f64 = 1.15;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Piston {
+    pub(crate) x: f64,
+    center: f64,
+    phase: f64,
+}
+
+impl Piston {
+    // World-space top and bottom of the opening between the pair at `frame`.
+    pub(crate) fn opening(&self, gap: f64, frame: u64, physics: Physics) -> (f64, f64) {
+        let t = frame as f64 * std::f64::consts::TAU / PISTON_PERIOD + self.phase;
+        let wave = physics.sin(t);
+        let open = PISTON_MIN_OPEN + (PISTON_MAX_OPEN - PISTON_MIN_OPEN) * (0.5 + 0.5 * wave);
+        let half = gap * open / 2.0;
+        (self.center - half, self.center + half)
+    }
+}
+
+impl Body for Piston {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn x_mut(&mut self) -> &mut f64 {
+        &mut self.x
+    }
+
+    fn width(&self) -> f64 {
+        PISTON_W
+    }
+}
+
+// Spawns pistons into the world for BOSS_FRAMES; the pistons themselves scroll
+// and collide like any other entity, so they outlive the encounter.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Encounter {
+    pub(crate) frame: u32,
+    spawned: usize,
+}
+
+impl Encounter {
+    pub(crate) fn new() -> Self {
+        Self {
+            frame: 0,
+            spawned: 0,
+        }
+    }
+
+    // Advances the encounter; `clear` says the last regular pipe is far enough
+    // away for a piston to enter.
+    fn update(
+        &mut self,
+        world: &mut World,
+        world_w: f64,
+        spacing: f64,
+        gap: f64,
+        clear: bool,
+        rng: &mut impl Rng,
+    ) {
+        self.frame += 1;
+        let room = world.pistons.last().is_none_or(|p| p.x < world_w - spacing);
+        if self.frame < BOSS_FRAMES && clear && room {
+            let margin = gap * 0.7;
+            let center = margin + rng.next_f64() * (SKY_H - margin * 2.0);
+            // Neighbours are a third of a cycle apart, giving a rolling wave.
+            let phase = self.spawned as f64 * std::f64::consts::TAU / 3.0;
+            self.spawned += 1;
+            let id = world.next_id();
+            world.pistons.push(
+                id,
+                Piston {
+                    x: world.physics.snap(world_w + 2.0),
+                    center: world.physics.snap(center),
+                    phase,
+                },
+            );
+        }
+    }
+
+    fn is_over(&self, world: &World, world_w: f64, spacing: f64) -> bool {
+        self.frame >= BOSS_FRAMES && world.pistons.last().is_none_or(|p| p.x < world_w - spacing)
+    }
+}
+
+// ── Ground hazards ──────────────────────────────────────────────────────────
+
+// Past HAZARD_FIRST_SCORE, a pipe now and then comes with a hazard on the
+// ground before it: a row of spikes, or a cat prowling back and forth. Either
+// walls off the bottom of the sky for a stretch, so the bird has to keep
+// higher, and the pipe's gap is kept clear of it. The chance climbs to
+// HAZARD_CHANCE by HAZARD_FULL_SCORE, and moves with the adaptive level.
+const HAZARD_FIRST_SCORE: u32 = 5;
+const HAZARD_FULL_SCORE: u32 = 50;
+const HAZARD_CHANCE: f64 = 0.35;
+pub(crate) const SPIKES_W: f64 = 18.0;
+pub(crate) const SPIKES_H: f64 = 8.0;
+pub(crate) const CAT_W: f64 = 12.0;
+pub(crate) const CAT_H: f64 = 10.0;
+const CAT_RANGE: f64 = 20.0;
+const CAT_PERIOD: f64 = 90.0;
+
+// Ground hazards stand on the ground; the low ceilings' icicles and fans
+// hang from the top of the sky.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum HazardKind {
+    Spikes,
+    Cat { phase: f64 },
+    Icicles { w: f64 },
+    Fan,
+}
+
+impl HazardKind {
+    // How far into the sky it reaches, from the ground or the top.
+    fn height(self) -> f64 {
+        match self {
+            HazardKind::Spikes => SPIKES_H,
+            HazardKind::Cat { .. } => CAT_H,
+            HazardKind::Icicles { .. } => ICICLES_H,
+            HazardKind::Fan => FAN_H,
+        }
+    }
+
+    pub(crate) fn hangs(self) -> bool {
+        matches!(self, HazardKind::Icicles { .. } | HazardKind::Fan)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Hazard {
+    pub(crate) x: f64,
+    pub(crate) kind: HazardKind,
+}
+
+impl Hazard {
+    // A cat's walk at `frame`, as an angle.
+    fn stride(phase: f64, frame: u64) -> f64 {
+        frame as f64 * std::f64::consts::TAU / CAT_PERIOD + phase
+    }
+
+    // The world-space box the bird can hit at `frame`, as left, top, right
+    // and bottom.
+    pub(crate) fn extent(&self, frame: u64, physics: Physics) -> (f64, f64, f64, f64) {
+        let (x, w) = match self.kind {
+            HazardKind::Cat { phase } => {
+                let t = Hazard::stride(phase, frame);
+                (self.x + CAT_RANGE * (0.5 + 0.5 * physics.sin(t)), CAT_W)
+            }
+            _ => (self.x, self.width()),
+        };
+        let h = self.kind.height();
+        if self.kind.hangs() {
+            (x, f64::NEG_INFINITY, x + w, h)
+        } else {
+            (x, SKY_H - h, x + w, f64::INFINITY)
+        }
+    }
+
+    // Whether it's heading left, which only a cat ever does.
+    pub(crate) fn heading_left(&self, frame: u64, physics: Physics) -> bool {
+        match self.kind {
+            HazardKind::Cat { phase } => {
+                let t = Hazard::stride(phase, frame);
+                physics.sin(t + std::f64::consts::FRAC_PI_2) < 0.0
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Body for Hazard {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn x_mut(&mut self) -> &mut f64 {
+        &mut self.x
+    }
+
+    fn width(&self) -> f64 {
+        match self.kind {
+            HazardKind::Spikes => SPIKES_W,
+            HazardKind::Cat { .. } => CAT_RANGE + CAT_W,
+            HazardKind::Icicles { w } => w,
+            HazardKind::Fan => FAN_W,
+        }
+    }
+}
+
+// ── Low ceilings ────────────────────────────────────────────────────────────
+
+// A little after each rush hour the ceiling comes down: after
+// CEILING_WARN_FRAMES of warning, the next CEILING_PIPES pipes each have a
+// row of icicles or a ceiling fan hanging before them, so a bird hugging the
+// top edge has to drop. Their gaps are kept below whatever hangs there.
+const CEILING_FIRST: u32 = RUSH_FIRST + 6;
+const CEILING_PIPES: u32 = 4;
+pub(crate) const CEILING_WARN_FRAMES: u32 = 45;
+pub(crate) const ICICLES_H: f64 = 14.0;
+pub(crate) const FAN_W: f64 = 22.0;
+pub(crate) const FAN_H: f64 = 12.0;
+// Room left between hanging hazards and the pipe caps either side.
+const CEILING_PAD: f64 = 2.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct LowCeiling {
+    pub(crate) frame: u32,
+    fans: bool,
+    // Pipes still to come with something hanging before them.
+    pipes: u32,
+    // Whether the last pipe's gap was kept low too, so the bird leaves it
+    // already below whatever hangs next.
+    lowered: bool,
+}
+
+impl LowCeiling {
+    // What hangs over the section, once the warning is over.
+    fn kind(&self) -> Option<HazardKind> {
+        if self.frame < CEILING_WARN_FRAMES || self.pipes == 0 {
+            None
+        } else if self.fans {
+            Some(HazardKind::Fan)
+        } else {
+            Some(HazardKind::Icicles { w: 0.0 })
+        }
+    }
+}
+
+// ── Rush hour ───────────────────────────────────────────────────────────────
+
+// Halfway between crusher sections the pipes bunch up for a while: after
+// RUSH_WARN_FRAMES of warning, pipes spawn at RUSH_SPACING of their usual
+// spacing for RUSH_FRAMES, then go back to normal.
+const RUSH_FIRST: u32 = BOSS_EVERY / 2;
+const RUSH_FRAMES: u32 = 180;
+pub(crate) const RUSH_WARN_FRAMES: u32 = 45;
+const RUSH_SPACING: f64 = 0.6;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Rush {
+    pub(crate) frame: u32,
+}
+
+impl Rush {
+    // What the rush scales pipe spacing by right now.
+    fn spacing_scale(&self) -> f64 {
+        if self.frame < RUSH_WARN_FRAMES {
+            1.0
+        } else {
+            RUSH_SPACING
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.frame >= RUSH_WARN_FRAMES + RUSH_FRAMES
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum State {
+    Ready,
+    Playing,
+    Dying,
+    Dead,
+    TooSmall,
+    Editor,
+    Mutators,
+    Stats,
+    Paused,
+    Playground,
+}
+
+// Totals across every run since launch, for the summary printed on exit. The
+// best is only from standard runs, which nothing else is ranked against.
+#[derive(Clone, Copy, Default)]
+pub struct Session {
+    pub runs: u32,
+    best: u32,
+    pipes: u32,
+}
+
+impl Session {
+    pub fn summary(&self) -> String {
+        let plural = |n: u32| if n == 1 { "" } else { "s" };
+        format!(
+            "{} run{} played, best {}, {} pipe{} cleared",
+            self.runs,
+            plural(self.runs),
+            self.best,
+            self.pipes,
+            plural(self.pipes),
+        )
+    }
+}
+
+// What happened in a step or a flap, for the subsystems that react to play:
+// sounds, rumble, the trace and the stats recorder. Hosts hand them to an
+// `EventBus`.
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+    Flap,
+    Score(u32),
+    Milestone,
+    // A pipe going by the bird, at a volume from WHOOSH_QUIET to 1.
+    Whoosh(f32),
+    Death,
+    Bonk,
+    PipeSpawned,
+    // A pipe passed within NEAR_MISS of an edge of its opening.
+    NearMiss,
+    // A rush hour being announced, and then over.
+    RushHour(bool),
+    // A low ceiling being announced.
+    LowCeiling,
+    // The score passing the best there was to beat, with the new score.
+    NewBest(u32),
+    StateChanged { from: State, to: State },
+}
+
+// Sounds for screen navigation, kept apart from gameplay events.
+pub enum UiEvent {
+    Move,
+    Select,
+    Back,
+}
+
+#[derive(Clone)]
+pub struct Game {
+    pub(crate) pw: usize,
+    pub(crate) ph: usize,
+    pub(crate) cam: Camera,
+    pub(crate) zoom: f64,
+    pub(crate) world_w: f64,
+    pub(crate) bird_x: f64,
+    pub(crate) bird_y: f64,
+    pub(crate) bird_vy: f64,
+    // Glide mode: whether flap is held, and the stamina left for gliding.
+    pub glide_held: bool,
+    pub(crate) stamina: f64,
+    // Energy mode: the bar that flaps draw from.
+    pub(crate) energy: f64,
+    pub(crate) energy_rules: EnergyRules,
+    pub(crate) gap_rules: GapRules,
+    // What the theme's sound effects sound like, for the audio thread.
+    pub sound_palette: SoundPalette,
+    pub world: World,
+    pipes_spawned: u32,
+    // Zone entered at the last checkpoint and the frame it was reached.
+    pub(crate) zone_banner: Option<(Medal, u64)>,
+    // When the audio device went away, if it hasn't come back.
+    pub(crate) muted_at: Option<u64>,
+    // A newer release to mention on the title screen.
+    pub(crate) update: Option<String>,
+    // Seconds of play `update_dt` has been given but not yet stepped through.
+    time_debt: f64,
+    // Index into MILESTONES of the banner on screen, and when it started.
+    pub(crate) milestone: Option<(usize, u64)>,
+    pub(crate) encounter: Option<Encounter>,
+    pub(crate) next_boss: u32,
+    pub(crate) rush: Option<Rush>,
+    pub(crate) next_rush: u32,
+    pub(crate) low_ceiling: Option<LowCeiling>,
+    pub(crate) next_ceiling: u32,
+    // Recent play for the death replay, and the replay when one is showing.
+    history: VecDeque<Rc<Snapshot>>,
+    pub(crate) replay: Option<Replay>,
+    pub(crate) ground_x: f64,
+    pub(crate) score: u32,
+    pub(crate) score_roll: RollingNumber,
+    pub(crate) best: u32,
+    // The best before this run, which the game-over panel rolls on from.
+    pub(crate) best_before: u32,
+    bests: HashMap<Category, u32>,
+    pub session: Session,
+    // Where the mouse is, in pixels, once it has moved with capture on.
+    pub(crate) pointer: Option<(i32, i32)>,
+    // Touch layout: taps anywhere flap, corner buttons pause and restart,
+    // and the title and menus get bigger targets.
+    pub touch: bool,
+    // An autosaved run left by a crash, offered on the title screen: its
+    // seed and where it was.
+    pub resume: Option<(u64, Snapshot)>,
+    // Kid mode: huge gaps, slow pipes, bright colors, and crashes bounce the
+    // bird back instead of ending the run. `bonk_frames` counts down after
+    // each bounce.
+    pub kid: bool,
+    pub(crate) bonk_frames: u32,
+    // Adaptive difficulty, when FLAPPY_ADAPTIVE is on.
+    pub adaptive: Option<Adaptive>,
+    // The trajectory hint assist. Assisted runs are marked as such and never
+    // set a best.
+    pub hint: bool,
+    // The bar along the top that fills toward the best score, and when this
+    // run passed the best, for its flash.
+    pub progress_bar: bool,
+    pub(crate) best_passed_at: Option<u64>,
+    // Events since the last step, like a menu opening, handed out with the
+    // next one.
+    pub(crate) outbox: Vec<GameEvent>,
+    // Blanked by SIGUSR1 until SIGUSR2 or a key: nothing is drawn or stepped.
+    pub blanked: bool,
+    // Every finished run, oldest first, from the history file and this session.
+    pub runs: Vec<RunRecord>,
+    // Screensaver: the autopilot flies, pipes can't kill, and themes cycle.
+    pub screensaver: bool,
+    // Low power: still hills drawn from a cache, no decorations, 15 FPS.
+    pub low_power: bool,
+    // How long the title waits for a key before dozing, if ever, and the
+    // steps it's waited so far.
+    pub doze_after: Option<Duration>,
+    pub(crate) title_frames: u64,
+    // A flat sky in the terminal's background color, which is left unpainted.
+    pub transparent_sky: Option<Rgb>,
+    // Whether the event calendar applies, the event running, and its score
+    // multiplier.
+    pub events: bool,
+    pub(crate) event: Option<String>,
+    pub(crate) points: u32,
+    pub(crate) mutators: Mutators,
+    pub(crate) mutator_cursor: usize,
+    pub(crate) playground_cursor: usize,
+    // Index into `stats_categories` of the leaderboard on the stats screen.
+    pub(crate) stats_category: usize,
+    // The scene on top, and the ones under it that closing it goes back to.
+    pub state: State,
+    pub(crate) scenes: Vec<State>,
+    pub frame: u64,
+    pub(crate) dead_timer: u32,
+    pub(crate) restart_queued: bool,
+    pub(crate) show_hud: bool,
+    rng_state: u64,
+    deco_rng: u64,
+    pub forced_seed: Option<u64>,
+    // What the current run's RNG started from, for traces.
+    pub(crate) run_seed: u64,
+    pub(crate) gravity: f64,
+    pub(crate) flap_vel: f64,
+    pub(crate) pipe_speed: f64,
+    pub(crate) config: GameConfig,
+    // The theme's tuning, reapplied every run.
+    pub(crate) tuning: Tuning,
+    pub(crate) skin: BirdSkin,
+    pub(crate) pipe_style: &'static PipeStyle,
+    pub(crate) sky: Vec<Rgb>,
+    pub(crate) hills: HillShape,
+    // Seeds the hill noise; rerolled every run.
+    pub hill_seed: u64,
+    pub(crate) editor_part: usize,
+    pub(crate) editor_backup: BirdSkin,
+}
+
+impl Game {
+    pub fn new(pw: usize, ph: usize, config: GameConfig) -> Self {
+        let sy = ph as f64 / WORLD_H;
+        let world_w = pw as f64 / sy;
+        Game {
+            pw,
+            ph,
+            cam: Camera::new(sy, 0.0, pw, ph),
+            zoom: 1.0,
+            world_w,
+            bird_x: BIRD_X_PCT * world_w,
+            bird_y: SKY_H * 0.4,
+            bird_vy: 0.0,
+            glide_held: false,
+            stamina: 1.0,
+            energy: 1.0,
+            energy_rules: EnergyRules::default(),
+            gap_rules: GapRules::default(),
+            sound_palette: SoundPalette::default(),
+            world: World::new(Physics::Float),
+            pipes_spawned: 0,
+            zone_banner: None,
+            muted_at: None,
+            update: None,
+            time_debt: 0.0,
+            milestone: None,
+            encounter: None,
+            next_boss: BOSS_EVERY,
+            rush: None,
+            next_rush: RUSH_FIRST,
+            low_ceiling: None,
+            next_ceiling: CEILING_FIRST,
+            history: VecDeque::new(),
+            replay: None,
+            ground_x: 0.0,
+            score: 0,
+            score_roll: RollingNumber::default(),
+            best: 0,
+            best_before: 0,
+            bests: HashMap::new(),
+            session: Session::default(),
+            pointer: None,
+            touch: false,
+            resume: None,
+            kid: false,
+            bonk_frames: 0,
+            adaptive: None,
+            hint: false,
+            progress_bar: false,
+            best_passed_at: None,
+            outbox: Vec::new(),
+            blanked: false,
+            runs: Vec::new(),
+            screensaver: false,
+            low_power: false,
+            doze_after: Some(DOZE_AFTER),
+            title_frames: 0,
+            transparent_sky: None,
+            events: false,
+            event: None,
+            points: 1,
+            mutators: Mutators::default(),
+            mutator_cursor: 0,
+            playground_cursor: 0,
+            stats_category: 0,
+            state: State::Ready,
+            scenes: Vec::new(),
+            frame: 0,
+            dead_timer: 0,
+            restart_queued: false,
+            show_hud: false,
+            rng_state: 0,
+            deco_rng: 0,
+            forced_seed: None,
+            run_seed: 0,
+            gravity: config.gravity,
+            flap_vel: config.flap_vel,
+            pipe_speed: config.pipe_speed,
+            tuning: Tuning::default(),
+            skin: DEFAULT_SKIN,
+            pipe_style: &PIPE_STYLES[0],
+            sky: config.sky.clone(),
+            hills: HillShape::default(),
+            hill_seed: 0,
+            editor_part: 0,
+            editor_backup: DEFAULT_SKIN,
+            config,
+        }
+    }
+
+    pub fn resize(&mut self, pw: usize, ph: usize) {
+        let zoom = self.zoom;
+        *self = Game {
+            best: self.best,
+            bests: self.bests.clone(),
+            session: self.session,
+            pointer: self.pointer,
+            touch: self.touch,
+            resume: self.resume.take(),
+            update: self.update.take(),
+            kid: self.kid,
+            adaptive: self.adaptive.take(),
+            hint: self.hint,
+            progress_bar: self.progress_bar,
+            blanked: self.blanked,
+            runs: std::mem::take(&mut self.runs),
+            outbox: std::mem::take(&mut self.outbox),
+            screensaver: self.screensaver,
+            low_power: self.low_power,
+            doze_after: self.doze_after,
+            transparent_sky: self.transparent_sky,
+            events: self.events,
+            event: self.event.clone(),
+            points: self.points,
+            mutators: self.mutators,
+            forced_seed: self.forced_seed,
+            run_seed: self.run_seed,
+            world: World::new(self.world.physics),
+            skin: self.skin,
+            pipe_style: self.pipe_style,
+            sky: self.sky.clone(),
+            hills: self.hills,
+            hill_seed: self.hill_seed,
+            energy_rules: self.energy_rules,
+            gap_rules: self.gap_rules,
+            sound_palette: self.sound_palette,
+            tuning: self.tuning,
+            ..Game::new(pw, ph, self.config.clone())
+        };
+        self.apply_tuning();
+        self.set_zoom(zoom);
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    // Zoom scales the world against the terminal. Zoomed in, the view is
+    // shorter than the world and the camera follows the bird vertically;
+    // zoomed out, the ground stays at the bottom and the sky extends above.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        self.zoom = zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+        let scale = self.ph as f64 / WORLD_H * self.zoom;
+        self.world_w = self.pw as f64 / scale;
+        self.bird_x = BIRD_X_PCT * self.world_w;
+        self.cam = Camera::new(scale, 0.0, self.pw, self.ph);
+        self.cam.y = self.camera_target();
+    }
+
+    fn camera_target(&self) -> f64 {
+        let view_h = WORLD_H / self.zoom;
+        if view_h >= WORLD_H {
+            WORLD_H - view_h
+        } else {
+            (self.bird_y - view_h / 2.0).clamp(0.0, WORLD_H - view_h)
+        }
+    }
+
+    // Kid mode's crash: bounce back towards the middle of the sky and pass
+    // through anything for a moment.
+    fn bonk(&mut self) {
+        self.bonk_frames = KID_BONK_FRAMES;
+        let vy = if self.bird_y > SKY_H / 2.0 {
+            self.flap_vel
+        } else {
+            -self.flap_vel * 0.5
+        };
+        self.bird_vy = self.world.physics.snap(vy);
+    }
+
+    // The camera for this frame: the followed view plus any impact shake.
+    pub(crate) fn camera(&self) -> Camera {
+        let mut cam = self.cam;
+        if self.state == State::Dying && self.dead_timer < SHAKE_FRAMES {
+            let amp = (SHAKE_FRAMES - self.dead_timer) as f64 / SHAKE_FRAMES as f64 * SHAKE_PX;
+            let dir = |bit: u64| {
+                if self.frame >> bit & 1 == 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            };
+            cam.shake = (
+                (dir(0) * amp).round() as i32,
+                (dir(1) * amp / 2.0).round() as i32,
+            );
+        }
+        cam
+    }
+
+    // Whether the next pipe comes with a ground hazard, and which. Rolls
+    // nothing until hazards can appear, so early pipes stay as they were.
+    fn roll_hazard(&mut self) -> Option<HazardKind> {
+        // The cave's floor is its ceiling, which nothing walks on.
+        if self.kid || self.mutators.has(Mutator::Bat) || self.score < HAZARD_FIRST_SCORE {
+            return None;
+        }
+        let span = HAZARD_FULL_SCORE - HAZARD_FIRST_SCORE;
+        let ramp = (self.score - HAZARD_FIRST_SCORE).min(span) as f64 / span as f64;
+        let adaptive = self.adaptive.as_ref().map_or(1.0, Adaptive::hazard_scale);
+        let chance = HAZARD_CHANCE * ramp * adaptive;
+        let roll = self.rng_state.next_f64();
+        if roll >= chance {
+            return None;
+        }
+        // The roll under `chance` is uniform too, so it picks the kind.
+        Some(if roll < chance / 2.0 {
+            HazardKind::Spikes
+        } else {
+            HazardKind::Cat {
+                phase: roll / chance * std::f64::consts::TAU,
+            }
+        })
+    }
+
+    fn spawn_decorations(&mut self) {
+        let ground_x = self.ground_x;
+        self.world
+            .decorations
+            .retain(|d| d.x - ground_x > -DECORATION_MAX_W);
+        loop {
+            let next_x = match self.world.decorations.last() {
+                Some(last) if last.x - self.ground_x >= self.world_w + DECORATION_MAX_W => break,
+                Some(last) => last.x + 18.0 + lcg_next(&mut self.deco_rng) * 40.0,
+                None => self.ground_x + lcg_next(&mut self.deco_rng) * 30.0,
+            };
+            let roll = lcg_next(&mut self.deco_rng);
+            let kind = if roll < 0.5 {
+                let petals = FLOWER_COLORS[(roll * 10.0) as usize % FLOWER_COLORS.len()];
+                DecorationKind::Flower(petals)
+            } else if roll < 0.85 {
+                DecorationKind::Rock
+            } else {
+                DecorationKind::Sign
+            };
+            let id = self.world.next_id();
+            self.world
+                .decorations
+                .push(id, Decoration { x: next_x, kind });
+        }
+    }
+
+    pub fn flap(&mut self) -> Option<GameEvent> {
+        self.scene().flap(self)
+    }
+
+    // Sends the bird up, or down under mirrored controls.
+    pub(crate) fn flap_bird(&mut self) {
+        self.bird_vy = self.world.physics.snap(self.flap_vel * self.gravity_dir());
+    }
+
+    pub(crate) fn gap(&self) -> f64 {
+        let gap = if self.mutators.has(Mutator::TinyGaps) {
+            self.config.pipe_gap * 0.75
+        } else {
+            self.config.pipe_gap
+        };
+        let kid = if self.kid { KID_GAP_SCALE } else { 1.0 };
+        gap * kid * self.adaptive.as_ref().map_or(1.0, Adaptive::gap_scale)
+    }
+
+    // Double speed keeps the time between pipes, not the distance.
+    pub(crate) fn speed(&self) -> f64 {
+        self.world
+            .physics
+            .snap(self.pipe_speed * self.speed_factor())
+    }
+
+    fn spacing(&self) -> f64 {
+        let scale = self.adaptive.as_ref().map_or(1.0, Adaptive::spacing_scale);
+        let rush = self.rush.as_ref().map_or(1.0, Rush::spacing_scale);
+        self.config.pipe_spacing * self.speed_factor() * scale * rush
+    }
+
+    fn speed_factor(&self) -> f64 {
+        if self.mutators.has(Mutator::DoubleSpeed) {
+            2.0
+        } else {
+            1.0
+        }
+    }
+
+    // Narrows the spawn band `lo..lo + range` to the heights a player can
+    // reach from the last gap before the next pipe arrives, leaving the band
+    // untouched when all of it is reachable.
+    fn passable(&self, lo: f64, range: f64, prev: Option<f64>) -> (f64, f64) {
+        let Some(prev) = prev else {
+            return (lo, range);
+        };
+        let frames = ((self.spacing() - PIPE_W) / self.speed()).max(1.0) as u32;
+        let (mut vy, mut y, mut climb) = (0.0, 0.0, 0.0f64);
+        for frame in 0..frames {
+            if frame.is_multiple_of(TAP_FRAMES) {
+                vy = self.flap_vel;
+            }
+            vy += self.gravity;
+            y += vy;
+            climb = climb.max(-y);
+        }
+        let dive = self.gravity * (frames * (frames + 1)) as f64 / 2.0;
+        // The bird can leave through one edge of a gap and enter the next
+        // through the opposite one.
+        let slack = (self.gap() - BIRD_HITBOX_HH * 2.0).max(0.0);
+        let relax = REACH_EASY
+            + (1.0 - REACH_EASY) * self.score.min(REACH_FULL_SCORE) as f64
+                / REACH_FULL_SCORE as f64;
+        let (mut up, mut down) = (climb * relax + slack, dive * relax + slack);
+        if self.gravity_dir() < 0.0 {
+            std::mem::swap(&mut up, &mut down);
+        }
+        let from = (prev - up).max(lo);
+        let to = (prev + down).min(lo + range);
+        if to <= from || (from == lo && to == lo + range) {
+            return (lo, range);
+        }
+        (from, to - from)
+    }
+
+    // Mirrored controls flip gravity and flaps: the bird falls up and flaps down.
+    pub(crate) fn gravity_dir(&self) -> f64 {
+        if self.mutators.has(Mutator::MirroredControls) {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    // Holding flap in glide mode softens gravity while stamina lasts.
+    pub(crate) fn is_gliding(&self) -> bool {
+        self.mutators.has(Mutator::Glide) && self.glide_held && self.stamina > 0.0
+    }
+
+    // What this run's score is ranked against. The gap and spacing only join
+    // the hash when changed, which keeps hashes from before they could be.
+    pub(crate) fn category(&self) -> Category {
+        let mut tuned = vec![
+            (self.gravity, GRAVITY),
+            (self.flap_vel, FLAP_VEL),
+            (self.pipe_speed, PIPE_SPEED),
+            (self.tuning.coyote, COYOTE_FRAMES),
+        ];
+        let layout = [
+            (self.config.pipe_gap, PIPE_GAP),
+            (self.config.pipe_spacing, PIPE_SPACING),
+        ];
+        if layout.iter().any(|(v, stock)| v != stock) {
+            tuned.extend(layout);
+        }
+        let physics = if tuned.iter().all(|(v, stock)| v == stock) {
+            0
+        } else {
+            (tuned.iter())
+                .flat_map(|(v, _)| v.to_bits().to_le_bytes())
+                .fold(0xcbf2_9ce4_8422_2325, |h, byte| {
+                    (h ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+                })
+        };
+        let assists = [
+            (self.hint, ASSIST_HINT),
+            (self.kid, ASSIST_KID),
+            (self.adaptive.is_some(), ASSIST_ADAPTIVE),
+        ];
+        Category {
+            mutators: self.mutators.0,
+            assists: (assists.iter())
+                .filter(|(on, _)| *on)
+                .fold(0, |bits, (_, bit)| bits | bit),
+            physics,
+            points: self.points,
+        }
+    }
+
+    // Notes a finished run for the stats screen; the last of `runs` is the
+    // one to save.
+    fn record_run(&mut self) {
+        let run = RunRecord {
+            when: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            score: self.score,
+            category: self.category(),
+        };
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.record(self.score);
+        }
+        self.runs.push(run);
+    }
+
+    pub fn restart(&mut self) {
+        let (best, from) = (self.best, self.state);
+        self.resize(self.pw, self.ph);
+        self.best = best;
+        lcg_next(&mut self.hill_seed);
+        self.emit(GameEvent::StateChanged {
+            from,
+            to: self.state,
+        });
+    }
+
+    // Blanking pauses a run in progress, which stays paused when the screen
+    // comes back; the screensaver just carries on.
+    pub fn set_blanked(&mut self, on: bool) {
+        if on == self.blanked {
+            return;
+        }
+        self.blanked = on;
+        if on && self.state == State::Playing {
+            self.open(State::Paused);
+        } else if !on && self.screensaver && self.state == State::Paused {
+            self.close();
+        }
+    }
+
+    // Flashes the muted icon when the audio device goes away.
+    pub fn set_muted(&mut self, on: bool) {
+        self.muted_at = on.then_some(self.frame);
+    }
+
+    pub fn set_update(&mut self, version: String) {
+        self.update = Some(version);
+    }
+
+    // The session's best among runs under the current rules.
+    pub(crate) fn category_best(&self) -> u32 {
+        self.bests.get(&self.category()).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn start_run(&mut self) {
+        self.resume = None;
+        self.session.runs += 1;
+        // The tuning keys may have changed the category since.
+        self.best = self.category_best();
+        // Unseeded runs stay within the seeds that have a word code.
+        self.rng_state = self.forced_seed.unwrap_or(self.frame % SEED_CODES);
+        self.run_seed = self.rng_state;
+        self.deco_rng = self.rng_state ^ 0x9e37_79b9_7f4a_7c15;
+        self.bird_y = self.world.physics.snap(self.bird_y);
+        self.bird_vy = self.world.physics.snap(self.flap_vel * self.gravity_dir());
+    }
+
+    pub(crate) fn crash(&mut self) {
+        self.dead_timer = 0;
+        self.bird_vy = self.flap_vel * 0.6;
+        self.best_before = self.best;
+        if self.score > self.best {
+            self.best = self.score;
+            self.bests.insert(self.category(), self.score);
+        }
+        if !self.screensaver {
+            self.record_run();
+        }
+    }
+
+    pub(crate) fn land(&mut self) {
+        self.bird_y = SKY_H - 3.0 * VU;
+        self.dead_timer = 0;
+        if self.restart_queued {
+            self.restart();
+        } else {
+            self.start_replay();
+        }
+    }
+
+    // Steps the game and returns what happened since the last step. A flap's
+    // own event comes back from `flap`; anything else between steps, like a
+    // menu opening, comes back from here.
+    pub fn update(&mut self) -> Vec<GameEvent> {
+        self.frame += 1;
+        if self.screensaver {
+            self.screensaver_step();
+        }
+        self.cam.y += (self.camera_target() - self.cam.y) * 0.25;
+        self.scene().update(self);
+        std::mem::take(&mut self.outbox)
+    }
+
+    pub(crate) fn emit(&mut self, event: GameEvent) {
+        self.outbox.push(event);
+    }
+
+    // The bird waiting for the first flap: bobbing, or hanging from the
+    // ceiling in a cave, while the ground scrolls by.
+    pub(crate) fn wait(&mut self) {
+        if self.mutators.has(Mutator::Bat) {
+            self.bird_y = SKY_H - BIRD_HITBOX_HH - 0.5;
+        } else {
+            let bob = self.world.physics.sin(self.frame as f64 * 0.08);
+            self.bird_y = SKY_H * 0.4 + bob * BIRD_BOB_AMP;
+        }
+        self.ground_x += 0.5;
+        self.spawn_decorations();
+    }
+
+    // A step of the run: the bird, the pipes and anything else in the world
+    // move, points are scored and collisions checked.
+    pub(crate) fn fly(&mut self) {
+        let gravity = if self.is_gliding() {
+            self.stamina = (self.stamina - GLIDE_DRAIN).max(0.0);
+            self.gravity * GLIDE_GRAVITY
+        } else {
+            self.stamina = (self.stamina + GLIDE_RECHARGE).min(1.0);
+            self.gravity
+        };
+        self.bird_vy += self.world.physics.snap(gravity * self.gravity_dir());
+        let refill = self.energy_rules.refill / SIM_FPS as f64;
+        self.energy = (self.energy + refill).min(1.0);
+        self.bird_y += self.bird_vy;
+        self.ground_x += self.speed();
+        self.spawn_decorations();
+
+        let clear = self
+            .world
+            .pipes
+            .last()
+            .is_none_or(|p| p.x < self.world_w - self.spacing());
+        if self.encounter.is_none() && self.score >= self.next_boss {
+            self.next_boss += BOSS_EVERY;
+            self.encounter = Some(Encounter::new());
+        }
+        if let Some(rush) = &mut self.rush {
+            rush.frame += 1;
+            if rush.is_over() {
+                self.rush = None;
+                self.emit(GameEvent::RushHour(false));
+            }
+        } else if self.encounter.is_none() && self.score >= self.next_rush {
+            self.next_rush += BOSS_EVERY;
+            self.rush = Some(Rush { frame: 0 });
+            self.emit(GameEvent::RushHour(true));
+        }
+        if let Some(low) = &mut self.low_ceiling {
+            low.frame += 1;
+            if low.pipes == 0 {
+                self.low_ceiling = None;
+            }
+        } else if self.encounter.is_none()
+            && self.rush.is_none()
+            && self.score >= self.next_ceiling
+            // Bat mode's top edge is the cave floor, and kid mode keeps to
+            // the plain course.
+            && !self.kid
+            && !self.mutators.has(Mutator::Bat)
+        {
+            self.next_ceiling += BOSS_EVERY;
+            let fans = self.rng_state.next_f64() < 0.5;
+            self.low_ceiling = Some(LowCeiling {
+                frame: 0,
+                fans,
+                pipes: CEILING_PIPES,
+                lowered: false,
+            });
+            self.emit(GameEvent::LowCeiling);
+        }
+        let (speed, spacing, gap) = (self.speed(), self.spacing(), self.gap());
+        if let Some(enc) = &mut self.encounter {
+            enc.update(
+                &mut self.world,
+                self.world_w,
+                spacing,
+                gap,
+                clear,
+                &mut self.rng_state,
+            );
+            if enc.is_over(&self.world, self.world_w, spacing) {
+                self.encounter = None;
+            }
+        } else if clear {
+            let margin = self.gap() * 0.7;
+            let range = SKY_H - margin * 2.0;
+            let prev = self.world.pipes.last().map(|p| p.gap_center);
+            let (lo, mut range) = self.passable(margin, range, prev);
+            let hazard = prev.and_then(|_| self.roll_hazard());
+            if let Some(kind) = hazard {
+                let floor = SKY_H - kind.height() - self.gap() / 2.0 - BIRD_HITBOX_HH;
+                range = range.min(floor - lo).max(0.0);
+            }
+            let low = prev
+                .and(self.low_ceiling)
+                .and_then(|low| Some((low.kind()?, low.lowered)));
+            let hanging = low.and_then(|(kind, lowered)| lowered.then_some(kind));
+            let mut lo = lo;
+            if let Some((kind, _)) = low {
+                let top = kind.height() + self.gap() / 2.0 + BIRD_HITBOX_HH;
+                range = (lo + range - top).max(0.0);
+                lo = lo.max(top);
+            }
+            let center = self.gap_rules.pick(&mut self.rng_state, lo, range, prev);
+            self.pipes_spawned += 1;
+            let x = self.world.physics.snap(self.world_w + 2.0);
+            let id = self.world.next_id();
+            self.world.pipes.push(
+                id,
+                Pipe {
+                    x,
+                    gap_center: self.world.physics.snap(center),
+                    number: self.pipes_spawned,
+                },
+            );
+            self.emit(GameEvent::PipeSpawned);
+            if let Some(kind) = hazard {
+                // Halfway between this pipe and the last.
+                let w = Hazard { x: 0.0, kind }.width();
+                let x = x - (self.spacing() - PIPE_W + w) / 2.0;
+                let id = self.world.next_id();
+                let x = self.world.physics.snap(x);
+                self.world.hazards.push(id, Hazard { x, kind });
+            }
+            if let Some(kind) = hanging {
+                // Icicles fill the space between the pipes' caps; a fan
+                // hangs in the middle of it.
+                let pad = self.pipe_style.cap_extra + CEILING_PAD;
+                let room = self.spacing() - PIPE_W - pad * 2.0;
+                let kind = match kind {
+                    HazardKind::Icicles { .. } => HazardKind::Icicles { w: room },
+                    kind => kind,
+                };
+                let w = Hazard { x: 0.0, kind }.width();
+                let x = x - self.spacing() + PIPE_W + pad + (room - w) / 2.0;
+                let id = self.world.next_id();
+                let x = self.world.physics.snap(x);
+                self.world.hazards.push(id, Hazard { x, kind });
+            }
+            if let (Some(section), Some(_)) = (&mut self.low_ceiling, low) {
+                section.lowered = true;
+                section.pipes -= u32::from(hanging.is_some());
+            }
+        }
+
+        self.world.scroll(speed);
+        let (bx, by) = (self.bird_x, self.bird_y);
+        let openings: Vec<_> = (self.world.pipes.reaching(bx, speed))
+            .map(|p| p.opening(gap))
+            .chain(
+                (self.world.pistons.reaching(bx, speed))
+                    .map(|p| p.opening(gap, self.frame, self.world.physics)),
+            )
+            .collect();
+        for opening in openings {
+            self.emit(GameEvent::Whoosh(whoosh_volume(by, opening)));
+        }
+        let passed: Vec<_> = self
+            .world
+            .pipes
+            .passed(self.bird_x, self.world.scored)
+            .map(|(id, p)| (id, p.number, p.opening(gap)))
+            .collect();
+        for (id, number, (top, bottom)) in passed {
+            self.world.scored = Some(id);
+            let before = self.score;
+            self.score += self.points;
+            self.score_roll.set(self.score, self.frame);
+            if before <= self.best && self.score > self.best && self.best > 0 {
+                self.best_passed_at = Some(self.frame);
+                self.emit(GameEvent::NewBest(self.score));
+            }
+            self.session.pipes += 1;
+            if self.category() == Category::default() {
+                self.session.best = self.session.best.max(self.score);
+            }
+            self.emit(GameEvent::Score(self.score));
+            let clearance = (by - BIRD_HITBOX_HH - top).min(bottom - by - BIRD_HITBOX_HH);
+            if clearance < NEAR_MISS {
+                self.emit(GameEvent::NearMiss);
+            }
+            let crossed = |(at, _): &(u32, &str)| (before + 1..=self.score).contains(at);
+            if let Some(i) = MILESTONES.iter().position(crossed) {
+                self.milestone = Some((i, self.frame));
+                self.emit(GameEvent::Milestone);
+            }
+            if let Some((_, medal)) = MEDALS.iter().find(|(at, _)| *at == number) {
+                self.zone_banner = Some((*medal, self.frame));
+            }
+        }
+
+        if self.kid {
+            self.bonk_frames = self.bonk_frames.saturating_sub(1);
+            self.bird_y = self.bird_y.clamp(BIRD_HITBOX_HH, SKY_H - BIRD_HITBOX_HH);
+            if self.bonk_frames == 0 && self.check_collision() {
+                self.bonk();
+                self.emit(GameEvent::Bonk);
+            }
+        } else if self.check_collision() {
+            self.enter(State::Dying);
+            self.emit(GameEvent::Death);
+        }
+    }
+
+    // No pipes and no dying: the sky's edges just stop the bird.
+    pub(crate) fn drift(&mut self) {
+        self.bird_vy += self.world.physics.snap(self.gravity * self.gravity_dir());
+        self.bird_y += self.bird_vy;
+        let (top, floor) = (BIRD_HITBOX_HH, SKY_H - BIRD_HITBOX_HH);
+        if !(top..=floor).contains(&self.bird_y) {
+            self.bird_y = self.bird_y.clamp(top, floor);
+            self.bird_vy = 0.0;
+        }
+        self.ground_x += self.speed();
+        self.spawn_decorations();
+    }
+
+    // The crashed bird dropping to the ground. Returns whether it's there.
+    pub(crate) fn fall(&mut self) -> bool {
+        self.bird_vy += self.gravity;
+        self.bird_y += self.bird_vy;
+        self.dead_timer += 1;
+        self.bird_y >= SKY_H - 3.0 * VU
+    }
+
+    // Moves the death replay on, or the game-over panel's timer once it's
+    // done.
+    pub(crate) fn step_replay(&mut self) {
+        match &mut self.replay {
+            Some(replay) if replay.step + 1 < replay.steps() => {
+                replay.step += 1;
+                replay.seek();
+            }
+            Some(_) => self.replay = None,
+            None => self.dead_timer += 1,
+        }
+    }
+
+    // Plays as many steps as `dt` seconds cover and carries the rest to the
+    // next call, so a terminal that can't keep up with the tick rate plays at
+    // the same speed, just less smoothly. Every step is still a whole fixed
+    // step, which keeps seeded runs and replays identical at any frame rate.
+    // Half a step of slack either way absorbs timer jitter, which would
+    // otherwise turn into a skipped step followed by a double one.
+    pub fn update_dt(&mut self, dt: f64) -> Vec<GameEvent> {
+        let step = self.config.frame_dur.as_secs_f64();
+        self.time_debt += dt.max(0.0);
+        let mut events = Vec::new();
+        for _ in 0..MAX_CATCH_UP {
+            if self.time_debt < step / 2.0 {
+                break;
+            }
+            self.time_debt -= step;
+            events.extend(self.update());
+        }
+        if self.time_debt >= step / 2.0 {
+            self.time_debt = 0.0;
+        }
+        events
+    }
+
+    // Which full screen is showing, for picking transitions.
+    pub fn screen(&self) -> Screen {
+        self.scene().screen(self)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.blanked || self.scene().is_idle(self)
+    }
+
+    // How many frames a second the host should draw, when it's not the usual.
+    pub fn frame_rate(&self) -> Option<u32> {
+        self.scene().frame_rate(self)
+    }
+
+    pub fn is_dozing(&self) -> bool {
+        let waited = self.config.frame_dur.as_secs_f64() * self.title_frames as f64;
+        self.state == State::Ready && self.doze_after.is_some_and(|d| waited >= d.as_secs_f64())
+    }
+
+    fn check_collision(&self) -> bool {
+        if self.screensaver {
+            return false;
+        }
+        let bx = self.bird_x;
+        let by = self.bird_y;
+
+        if by + BIRD_HITBOX_HH >= SKY_H || by - BIRD_HITBOX_HH < 0.0 {
+            return true;
+        }
+
+        let gap = self.gap();
+        let grace = self.tuning.coyote * self.speed();
+        let caps = Caps {
+            h: self.pipe_style.cap_h,
+            extra: self.pipe_style.cap_extra,
+            chamfer: PIPE_CAP_CHAMFER,
+        };
+        let bird = HitRect::around(bx, by, BIRD_HITBOX_HW, BIRD_HITBOX_HH);
+        let hazards = self.world.hazards.iter().any(|h| {
+            let (x0, y0, x1, y1) = h.extent(self.frame, self.world.physics);
+            HitRect::new(x0, y0, x1, y1).hit_by(&bird, 0.0)
+        });
+        hazards
+            || self
+                .world
+                .pistons
+                .hits(bx, by, grace, Caps::default(), |p| {
+                    p.opening(gap, self.frame, self.world.physics)
+                })
+            || self
+                .world
+                .pipes
+                .hits(bx, by, grace, caps, |p| p.opening(gap))
+    }
+
+    pub(crate) fn apply_tuning(&mut self) {
+        self.gravity = self.config.gravity * self.tuning.gravity;
+        self.flap_vel = self.config.flap_vel * self.tuning.flap;
+        self.pipe_speed = self.config.pipe_speed * self.tuning.speed;
+    }
+
+    pub(crate) fn tune_gravity(&mut self, delta: f64) {
+        self.show_hud = true;
+        self.gravity = (self.gravity + delta * VU).max(self.config.gravity * 0.25);
+    }
+
+    pub(crate) fn tune_flap(&mut self, delta: f64) {
+        self.show_hud = true;
+        self.flap_vel = (self.flap_vel + delta * VU).min(self.config.flap_vel * 0.25);
+    }
+
+    pub(crate) fn tune_speed(&mut self, delta: f64) {
+        self.show_hud = true;
+        self.pipe_speed = (self.pipe_speed + delta * VU).max(self.config.pipe_speed * 0.167);
+    }
+
+    pub(crate) fn cycle_pipe_style(&mut self) {
+        let idx = PIPE_STYLES
+            .iter()
+            .position(|style| std::ptr::eq(style, self.pipe_style))
+            .map_or(0, |i| (i + 1) % PIPE_STYLES.len());
+        self.pipe_style = &PIPE_STYLES[idx];
+    }
+}
+
+// Uniform numbers in [0, 1), for the draws that shape a run.
+pub(crate) trait Rng {
+    fn next_f64(&mut self) -> f64;
+}
+
+// The bare LCG state is itself a generator.
+impl Rng for u64 {
+    fn next_f64(&mut self) -> f64 {
+        lcg_next(self)
+    }
+}
+
+fn lcg_next(state: &mut u64) -> f64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    let bits = (*state >> 33) ^ *state;
+    (bits % 1000) as f64 / 1000.0
+}
+
+// ── Screensaver ─────────────────────────────────────────────────────────────
+
+// Skies the screensaver fades between, top to bottom.
+const SCREENSAVER_SKIES: [[Rgb; 3]; 4] = [
+    [SKY_TOP, Rgb(130, 206, 222), SKY_BOT],
+    [Rgb(42, 30, 92), Rgb(200, 88, 122), Rgb(246, 180, 106)],
+    [Rgb(8, 12, 40), Rgb(24, 36, 84), Rgb(60, 70, 120)],
+    [Rgb(120, 150, 220), Rgb(240, 170, 190), Rgb(253, 227, 167)],
+];
+// Frames spent on each sky; the pipes change style with it.
+const SCREENSAVER_THEME_FRAMES: u64 = SIM_FPS as u64 * 30;
+// How far below the gap's center the autopilot lets the bird sink before flapping.
+const AUTOPILOT_SINK: f64 = 8.0;
+
+impl Game {
+    fn screensaver_step(&mut self) {
+        self.autopilot();
+        let n = SCREENSAVER_SKIES.len() as u64;
+        let i = self.frame / SCREENSAVER_THEME_FRAMES;
+        let t = (self.frame % SCREENSAVER_THEME_FRAMES * 256 / SCREENSAVER_THEME_FRAMES) as u16;
+        let (from, to) = (
+            SCREENSAVER_SKIES[(i % n) as usize],
+            SCREENSAVER_SKIES[((i + 1) % n) as usize],
+        );
+        self.sky = from
+            .iter()
+            .zip(to)
+            .map(|(a, b)| Rgb::lerp_linear(*a, b, t))
+            .collect();
+        self.pipe_style = &PIPE_STYLES[i as usize % PIPE_STYLES.len()];
+    }
+
+    // Starts a run, then flaps whenever the bird sinks too far below the next
+    // opening.
+    pub(crate) fn autopilot(&mut self) -> Option<GameEvent> {
+        let sinking = self.bird_y > self.autopilot_target() + AUTOPILOT_SINK;
+        match self.state {
+            State::Ready => self.flap(),
+            State::Playing if sinking => self.flap(),
+            _ => None,
+        }
+    }
+
+    // The middle of the next opening the bird has yet to clear.
+    fn autopilot_target(&self) -> f64 {
+        let behind = self.bird_x - BIRD_HITBOX_HW - self.pipe_style.cap_extra;
+        let pipes = self
+            .world
+            .pipes
+            .iter()
+            .map(|p| (p.x + PIPE_W, p.gap_center));
+        let pistons = self
+            .world
+            .pistons
+            .iter()
+            .map(|p| (p.x + PISTON_W, p.center));
+        pipes
+            .chain(pistons)
+            .filter(|(end, _)| *end > behind)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(SKY_H * 0.45, |(_, center)| center)
+    }
+}
+
+// ── Headless ────────────────────────────────────────────────────────────────
+
+// A pipe, piston pair or hazard as a bot sees it, in world units with
+// y growing downwards: the left edge, the width, and the opening this step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obstacle {
+    pub x: f64,
+    pub w: f64,
+    pub top: f64,
+    pub bottom: f64,
+}
+
+impl Game {
+    // A game with no terminal, audio or files behind it, for tests and bots,
+    // played with `flap` and `update`, which never draw. Fixed-point physics
+    // make a seed play out the same on every machine.
+    pub fn headless(seed: u64) -> Game {
+        let (w, h) = DEMO_SIZE;
+        let mut game = Game::new(w, h, GameConfig::default());
+        game.forced_seed = Some(seed);
+        game.world.physics = Physics::Fixed;
+        game.hill_seed = seed;
+        game
+    }
+
+    // Flaps first if asked, then steps once: a bot's turn.
+    pub fn step(&mut self, flap: bool) -> Vec<GameEvent> {
+        let mut events: Vec<GameEvent> = flap.then(|| self.flap()).flatten().into_iter().collect();
+        events.extend(self.update());
+        events
+    }
+
+    // The middle of the bird, and how fast it's falling per step.
+    pub fn bird(&self) -> (f64, f64) {
+        (self.bird_x, self.bird_y)
+    }
+
+    pub fn bird_velocity(&self) -> f64 {
+        self.bird_vy
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    // Whether the bird has crashed. A flap from here on queues the next run.
+    pub fn is_over(&self) -> bool {
+        matches!(self.state, State::Dying | State::Dead)
+    }
+
+    // How wide the world on screen is, and how far down the ground starts.
+    pub fn bounds(&self) -> (f64, f64) {
+        (self.world_w, SKY_H)
+    }
+
+    // Pipes and pistons on screen or about to be, left to right.
+    pub fn obstacles(&self) -> Vec<Obstacle> {
+        let gap = self.gap();
+        let pipes = self
+            .world
+            .pipes
+            .iter()
+            .map(|p| (p.x, PIPE_W, p.opening(gap)));
+        let pistons = (self.world.pistons.iter()).map(|p| {
+            (
+                p.x,
+                PISTON_W,
+                p.opening(gap, self.frame, self.world.physics),
+            )
+        });
+        // A hazard's opening is all the sky above it, or below it for one
+        // that hangs.
+        let hazards = (self.world.hazards.iter()).map(|h| {
+            let (x0, y0, x1, y1) = h.extent(self.frame, self.world.physics);
+            let opening = if h.kind.hangs() {
+                (y1, SKY_H)
+            } else {
+                (0.0, y0)
+            };
+            (x0, x1 - x0, opening)
+        });
+        let mut all: Vec<Obstacle> = pipes
+            .chain(pistons)
+            .chain(hazards)
+            .map(|(x, w, (top, bottom))| Obstacle { x, w, top, bottom })
+            .collect();
+        all.sort_by(|a, b| a.x.total_cmp(&b.x));
+        all
+    }
+}
+
+// ── Snapshots ───────────────────────────────────────────────────────────────
+
+// Everything that decides how a run plays out from here: the bird, every
+// entity, the RNGs and the score. Settings and terminal size are left out.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    state: State,
+    pub frame: u64,
+    dead_timer: u32,
+    restart_queued: bool,
+    bird_y: f64,
+    bird_vy: f64,
+    glide_held: bool,
+    stamina: f64,
+    energy: f64,
+    pub(crate) score: u32,
+    mutators: Mutators,
+    rng_state: u64,
+    deco_rng: u64,
+    hill_seed: u64,
+    ground_x: f64,
+    world: World,
+    pipes_spawned: u32,
+    zone_banner: Option<(Medal, u64)>,
+    milestone: Option<(usize, u64)>,
+    encounter: Option<Encounter>,
+    next_boss: u32,
+    rush: Option<Rush>,
+    next_rush: u32,
+    low_ceiling: Option<LowCeiling>,
+    next_ceiling: u32,
+}
+
+impl Snapshot {
+    // A fixed little-endian encoding of every field, so equal states give
+    // equal bytes on any platform.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::new();
+        let f = |b: &mut Vec<u8>, v: f64| b.extend(v.to_bits().to_le_bytes());
+        b.push(self.state as u8);
+        b.extend(self.frame.to_le_bytes());
+        b.extend(self.dead_timer.to_le_bytes());
+        b.push(self.restart_queued as u8);
+        f(&mut b, self.bird_y);
+        f(&mut b, self.bird_vy);
+        b.push(self.glide_held as u8);
+        f(&mut b, self.stamina);
+        f(&mut b, self.energy);
+        b.extend(self.score.to_le_bytes());
+        b.extend(self.mutators.0.to_le_bytes());
+        b.extend(self.rng_state.to_le_bytes());
+        b.extend(self.deco_rng.to_le_bytes());
+        b.extend(self.hill_seed.to_le_bytes());
+        f(&mut b, self.ground_x);
+        b.push(self.world.physics as u8);
+        b.extend(self.world.next_id.to_le_bytes());
+        b.extend(self.world.scored.map_or(0, |id| id.0).to_le_bytes());
+        b.extend((self.world.pipes.items.len() as u32).to_le_bytes());
+        for (id, p) in &self.world.pipes.items {
+            b.extend(id.0.to_le_bytes());
+            f(&mut b, p.x);
+            f(&mut b, p.gap_center);
+            b.extend(p.number.to_le_bytes());
+        }
+        b.extend((self.world.pistons.items.len() as u32).to_le_bytes());
+        for (id, p) in &self.world.pistons.items {
+            b.extend(id.0.to_le_bytes());
+            f(&mut b, p.x);
+            f(&mut b, p.center);
+            f(&mut b, p.phase);
+        }
+        b.extend((self.world.decorations.items.len() as u32).to_le_bytes());
+        for (id, d) in &self.world.decorations.items {
+            b.extend(id.0.to_le_bytes());
+            f(&mut b, d.x);
+            match d.kind {
+                DecorationKind::Flower(c) => b.extend([0, c.0, c.1, c.2]),
+                DecorationKind::Rock => b.push(1),
+                DecorationKind::Sign => b.push(2),
+            }
+        }
+        b.extend((self.world.hazards.items.len() as u32).to_le_bytes());
+        for (id, h) in &self.world.hazards.items {
+            b.extend(id.0.to_le_bytes());
+            f(&mut b, h.x);
+            match h.kind {
+                HazardKind::Spikes => b.push(0),
+                HazardKind::Cat { phase } => {
+                    b.push(1);
+                    f(&mut b, phase);
+                }
+                HazardKind::Icicles { w } => {
+                    b.push(2);
+                    f(&mut b, w);
+                }
+                HazardKind::Fan => b.push(3),
+            }
+        }
+        b.extend(self.pipes_spawned.to_le_bytes());
+        b.push(self.zone_banner.is_some() as u8);
+        if let Some((medal, at)) = self.zone_banner {
+            b.push(medal as u8);
+            b.extend(at.to_le_bytes());
+        }
+        b.push(self.milestone.is_some() as u8);
+        if let Some((i, at)) = self.milestone {
+            b.push(i as u8);
+            b.extend(at.to_le_bytes());
+        }
+        b.push(self.encounter.is_some() as u8);
+        if let Some(enc) = &self.encounter {
+            b.extend(enc.frame.to_le_bytes());
+            b.extend((enc.spawned as u32).to_le_bytes());
+        }
+        b.extend(self.next_boss.to_le_bytes());
+        b.push(self.rush.is_some() as u8);
+        if let Some(rush) = self.rush {
+            b.extend(rush.frame.to_le_bytes());
+        }
+        b.extend(self.next_rush.to_le_bytes());
+        b.push(self.low_ceiling.is_some() as u8);
+        if let Some(low) = self.low_ceiling {
+            b.extend(low.frame.to_le_bytes());
+            b.push(low.fans as u8);
+            b.extend(low.pipes.to_le_bytes());
+            b.push(low.lowered as u8);
+        }
+        b.extend(self.next_ceiling.to_le_bytes());
+        b
+    }
+
+    // FNV-1a over `to_bytes`: stable across runs, platforms and Rust versions,
+    // unlike `DefaultHasher`.
+    pub fn hash(&self) -> u64 {
+        self.to_bytes()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325, |h, &byte| {
+                (h ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+impl Game {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state,
+            frame: self.frame,
+            dead_timer: self.dead_timer,
+            restart_queued: self.restart_queued,
+            bird_y: self.bird_y,
+            bird_vy: self.bird_vy,
+            glide_held: self.glide_held,
+            stamina: self.stamina,
+            energy: self.energy,
+            score: self.score,
+            mutators: self.mutators,
+            rng_state: self.rng_state,
+            deco_rng: self.deco_rng,
+            hill_seed: self.hill_seed,
+            ground_x: self.ground_x,
+            world: self.world.clone(),
+            pipes_spawned: self.pipes_spawned,
+            zone_banner: self.zone_banner,
+            milestone: self.milestone,
+            encounter: self.encounter.clone(),
+            next_boss: self.next_boss,
+            rush: self.rush,
+            next_rush: self.next_rush,
+            low_ceiling: self.low_ceiling,
+            next_ceiling: self.next_ceiling,
+        }
+    }
+
+    // Picks the crashed run back up, paused so the player can get ready.
+    pub fn resume_run(&mut self) {
+        let Some((seed, snap)) = self.resume.take() else {
+            return;
+        };
+        self.restore(&snap);
+        self.run_seed = seed;
+        self.session.runs += 1;
+        self.set_scene(State::Paused);
+    }
+
+    pub(crate) fn restore(&mut self, snap: &Snapshot) {
+        self.set_scene(snap.state);
+        self.frame = snap.frame;
+        self.dead_timer = snap.dead_timer;
+        self.restart_queued = snap.restart_queued;
+        self.bird_y = snap.bird_y;
+        self.bird_vy = snap.bird_vy;
+        self.glide_held = snap.glide_held;
+        self.stamina = snap.stamina;
+        self.energy = snap.energy;
+        self.score = snap.score;
+        self.mutators = snap.mutators;
+        self.rng_state = snap.rng_state;
+        self.deco_rng = snap.deco_rng;
+        self.hill_seed = snap.hill_seed;
+        self.ground_x = snap.ground_x;
+        self.world = snap.world.clone();
+        self.pipes_spawned = snap.pipes_spawned;
+        self.zone_banner = snap.zone_banner;
+        self.milestone = snap.milestone;
+        self.encounter = snap.encounter.clone();
+        self.next_boss = snap.next_boss;
+        self.rush = snap.rush;
+        self.next_rush = snap.next_rush;
+        self.low_ceiling = snap.low_ceiling;
+        self.next_ceiling = snap.next_ceiling;
+        self.cam.y = self.camera_target();
+    }
+
+    // Draws the game `t` of the way from `prev` to now, for frames in
+    // between steps. Only the drawn motion is blended, in place, and put
+    // back once `draw` is done; anything else shows as it is now.
+    pub fn draw_between(&mut self, prev: &Step, t: f64, draw: impl FnOnce(&Game)) {
+        let now = self.blend(&prev.prev, prev.cam_y, t);
+        draw(self);
+        if let Some(now) = now {
+            self.set_motion(now);
+        }
+    }
+
+    // Moves what's drawn `t` of the way from `prev` to now, returning the
+    // motion it replaced, or None if `prev` isn't the step just before.
+    fn blend(&mut self, prev: &Snapshot, prev_cam_y: f64, t: f64) -> Option<Motion> {
+        if prev.frame + 1 != self.frame || prev.state != self.state {
+            return None;
+        }
+        let now = self.motion();
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        self.bird_y = lerp(prev.bird_y, now.bird_y);
+        self.bird_vy = lerp(prev.bird_vy, now.bird_vy);
+        self.ground_x = lerp(prev.ground_x, now.ground_x);
+        self.cam.y = lerp(prev_cam_y, now.cam_y);
+        self.world.pipes.interpolate(&prev.world.pipes, t);
+        self.world.pistons.interpolate(&prev.world.pistons, t);
+        self.world.hazards.interpolate(&prev.world.hazards, t);
+        Some(now)
+    }
+
+    fn motion(&self) -> Motion {
+        let world = &self.world;
+        Motion {
+            bird_y: self.bird_y,
+            bird_vy: self.bird_vy,
+            ground_x: self.ground_x,
+            cam_y: self.cam.y,
+            xs: (world.pipes.xs().chain(world.pistons.xs()))
+                .chain(world.hazards.xs())
+                .collect(),
+        }
+    }
+
+    fn set_motion(&mut self, motion: Motion) {
+        self.bird_y = motion.bird_y;
+        self.bird_vy = motion.bird_vy;
+        self.ground_x = motion.ground_x;
+        self.cam.y = motion.cam_y;
+        let xs = &mut motion.xs.into_iter();
+        self.world.pipes.set_xs(xs);
+        self.world.pistons.set_xs(xs);
+        self.world.hazards.set_xs(xs);
+    }
+
+    pub(crate) fn record(&mut self) {
+        if self.history.len() == REPLAY_FRAMES {
+            self.history.pop_front();
+        }
+        self.history.push_back(Rc::new(self.snapshot()));
+    }
+
+    fn start_replay(&mut self) {
+        self.record();
+        let frames: Vec<_> = self.history.drain(..).collect();
+        if frames.len() > 1 {
+            // The one copy of the game the replay makes; every step after
+            // only restores the recorded run into it. The icon stays live.
+            let mut view = Box::new(self.clone());
+            view.muted_at = None;
+            let mut replay = Replay {
+                frames,
+                step: 0,
+                view,
+            };
+            replay.seek();
+            self.replay = Some(replay);
+        }
+    }
+
+    // The run as it stood at the replay's position.
+    pub(crate) fn replay_view(&self) -> Option<&Game> {
+        self.replay.as_ref().map(|replay| &*replay.view)
+    }
+}
+
+// A recorded stretch of play being shown back after death.
+#[derive(Clone)]
+pub(crate) struct Replay {
+    frames: Vec<Rc<Snapshot>>,
+    step: u32,
+    view: Box<Game>,
+}
+
+impl Replay {
+    fn steps(&self) -> u32 {
+        (self.frames.len() as u32 - 1) * REPLAY_SLOWDOWN + 1
+    }
+
+    // Moves the view to the replay's position, blended between the two
+    // recorded steps it falls between.
+    fn seek(&mut self) {
+        let at = self.step as f64 / REPLAY_SLOWDOWN as f64;
+        let i = at as usize;
+        self.view.restore(&self.frames[i]);
+        if let Some(next) = self.frames.get(i + 1) {
+            let cam_y = self.view.cam.y;
+            self.view.restore(next);
+            self.view.blend(&self.frames[i], cam_y, at.fract());
+        }
+    }
+}
+
+// The drawn motion of one step, as blending between steps moves it.
+struct Motion {
+    bird_y: f64,
+    bird_vy: f64,
+    ground_x: f64,
+    cam_y: f64,
+    // Every pipe's, piston's and hazard's left edge, in that order.
+    xs: Vec<f64>,
+}
+
+// The state before the latest simulation step, to draw frames in between.
+pub struct Step {
+    pub prev: Snapshot,
+    pub cam_y: f64,
+    pub at: Instant,
+}
+impl Step {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            prev: game.snapshot(),
+            cam_y: game.cam.y,
+            at: Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Steps a headless game, flapping to start and whenever the bird drops
+    // below the middle of the screen.
+    fn play(seed: u64, steps: usize) -> Game {
+        let mut game = Game::headless(seed);
+        let (_, h) = game.bounds();
+        for _ in 0..steps {
+            let falling = game.bird().1 > h / 2.0 && game.bird_velocity() > 0.0;
+            let flap = game.state == State::Ready || falling;
+            game.step(flap);
+        }
+        game
+    }
+
+    #[test]
+    fn seed_codes_round_trip() {
+        for seed in [0, 1, 99, 100, 4321, SEED_CODES - 1] {
+            assert_eq!(decode_seed(&encode_seed(seed)), Some(seed));
+        }
+        assert_eq!(encode_seed(SEED_CODES), SEED_CODES.to_string());
+        assert_eq!(decode_seed(&SEED_CODES.to_string()), Some(SEED_CODES));
+    }
+
+    #[test]
+    fn seed_codes_ignore_case_and_reject_junk() {
+        let code = encode_seed(1234);
+        assert_eq!(decode_seed(&code.to_ascii_uppercase()), Some(1234));
+        assert_eq!(decode_seed(&format!("  {code} ")), Some(1234));
+        assert_eq!(decode_seed("not-a-seed"), None);
+        assert_eq!(decode_seed(&format!("{code}-1")), None);
+    }
+
+    #[test]
+    fn history_reads_old_and_new_lines() {
+        let old = RunRecord::parse("1700000000 12 0").unwrap();
+        assert_eq!(old.score, 12);
+        assert!(old.category == Category::default());
+
+        let assisted = RunRecord::parse("1700000000 7 3 2").unwrap();
+        assert_eq!(assisted.category.mutators, 3);
+        assert_eq!(assisted.category.assists, ASSIST_KID);
+        assert_eq!(assisted.category.physics, 0);
+
+        let tuned = RunRecord::parse("1700000000 7 0 0 99").unwrap();
+        assert_eq!(tuned.category.physics, 99);
+        assert_eq!(tuned.category.points, 1);
+
+        let doubled = RunRecord::parse("1700000000 14 0 0 0 2").unwrap();
+        assert_eq!(doubled.category.points, 2);
+        assert!(doubled.category != Category::default());
+
+        assert!(RunRecord::parse("1700000000").is_none());
+        assert!(RunRecord::parse("1700000000 x 0").is_none());
+    }
+
+    #[test]
+    fn category_labels() {
+        assert_eq!(Category::default().label(false), "STANDARD");
+        let assisted = Category {
+            assists: ASSIST_HINT | ASSIST_ADAPTIVE,
+            physics: 1,
+            ..Category::default()
+        };
+        assert_eq!(assisted.label(false), "ASSIST ADAPTIVE TUNED");
+        assert_eq!(assisted.label(true), "A A T");
+        let doubled = Category {
+            points: 2,
+            ..Category::default()
+        };
+        assert_eq!(doubled.label(false), "x2");
+    }
+
+    #[test]
+    fn config_sets_the_gap_and_marks_the_category() {
+        let config = GameConfig {
+            pipe_gap: 40.0,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(DEMO_SIZE.0, DEMO_SIZE.1, config);
+        game.world.physics = Physics::Fixed;
+        for _ in 0..120 {
+            let flap = game.state == State::Ready || game.bird().1 > SKY_H / 2.0;
+            game.step(flap);
+        }
+        let obstacle = game.obstacles().into_iter().next().unwrap();
+        assert!((obstacle.bottom - obstacle.top - 40.0).abs() < 1e-9);
+        assert!(game.category().physics != 0);
+        assert!(Game::headless(1).category() == Category::default());
+    }
+
+    #[test]
+    fn instant_restart_skips_the_fall() {
+        for instant in [false, true] {
+            let config = GameConfig {
+                instant_restart: instant,
+                ..GameConfig::default()
+            };
+            let mut game = Game::new(DEMO_SIZE.0, DEMO_SIZE.1, config);
+            game.step(true);
+            while game.state == State::Playing {
+                game.step(game.bird().1 > SKY_H / 2.0 && game.bird_velocity() > 0.0);
+            }
+            assert!(game.state == State::Dying);
+            game.step(true);
+            assert!(game.state == State::Dying);
+            for _ in 0..RESTART_BUFFER_DELAY {
+                game.step(false);
+            }
+            let events = game.step(true);
+            if instant {
+                assert!(game.state == State::Playing);
+                assert!(matches!(events.first(), Some(GameEvent::Flap)));
+            } else {
+                assert!(game.state == State::Dying && game.restart_queued);
+            }
+        }
+    }
+
+    #[test]
+    fn passing_the_best_is_noted_once() {
+        let mut game = Game::headless(42);
+        game.step(true);
+        game.best = 1;
+        let mut new_bests = Vec::new();
+        while !game.is_over() && game.score() < 3 {
+            let (x, y) = game.bird();
+            let next = game.obstacles().into_iter().find(|o| o.x + o.w > x);
+            let gap = next.map_or(45.0, |o| (o.top + o.bottom) / 2.0);
+            let events = game.step(game.state == State::Ready || y > gap + 8.0);
+            new_bests.extend(events.iter().filter_map(|e| match e {
+                GameEvent::NewBest(score) => Some(*score),
+                _ => None,
+            }));
+            if game.score() < 2 {
+                assert!(game.best_passed_at.is_none());
+            }
+        }
+        assert_eq!(game.score(), 3);
+        assert!(game.best_passed_at.is_some_and(|at| at < game.frame));
+        assert_eq!(new_bests, [2]);
+    }
+
+    #[test]
+    fn only_standard_runs_set_the_session_best() {
+        for kid in [false, true] {
+            let mut game = Game::headless(42);
+            game.kid = kid;
+            while !game.is_over() && game.score() < 2 {
+                let (x, y) = game.bird();
+                let next = game.obstacles().into_iter().find(|o| o.x + o.w > x);
+                let gap = next.map_or(45.0, |o| (o.top + o.bottom) / 2.0);
+                game.step(game.state == State::Ready || y > gap + 8.0);
+            }
+            assert_eq!(game.score(), 2);
+            assert_eq!(game.session.best, if kid { 0 } else { 2 });
+        }
+    }
+
+    #[test]
+    fn ground_hazards_wait_for_the_score_then_mix() {
+        let mut game = Game::headless(5);
+        let rng = game.rng_state;
+        assert!(game.roll_hazard().is_none());
+        assert_eq!(game.rng_state, rng);
+        game.score = HAZARD_FULL_SCORE;
+        let kinds: Vec<_> = (0..200).filter_map(|_| game.roll_hazard()).collect();
+        assert!(kinds.iter().any(|k| matches!(k, HazardKind::Spikes)));
+        assert!(kinds.iter().any(|k| matches!(k, HazardKind::Cat { .. })));
+    }
+
+    #[test]
+    fn landing_on_spikes_ends_the_run() {
+        let mut game = Game::headless(5);
+        game.step(true);
+        let id = game.world.next_id();
+        let x = game.bird().0 - SPIKES_W / 2.0;
+        let kind = HazardKind::Spikes;
+        game.world.hazards.push(id, Hazard { x, kind });
+        game.bird_y = SKY_H - SPIKES_H - 1.0;
+        game.bird_vy = 0.0;
+        game.step(false);
+        assert!(game.state == State::Dying);
+    }
+
+    #[test]
+    fn rush_hour_bunches_the_pipes_up_for_a_while() {
+        let mut game = Game::headless(3);
+        game.kid = true;
+        game.step(true);
+        let normal = game.spacing();
+        game.score = RUSH_FIRST;
+        let mut rushes = Vec::new();
+        for frame in 0..=RUSH_WARN_FRAMES + RUSH_FRAMES {
+            let events = game.step(game.bird().1 > SKY_H / 2.0 && game.bird_velocity() > 0.0);
+            rushes.extend(events.iter().filter_map(|e| match e {
+                GameEvent::RushHour(on) => Some(*on),
+                _ => None,
+            }));
+            if frame == RUSH_WARN_FRAMES {
+                assert_eq!(game.spacing(), normal * RUSH_SPACING);
+            }
+        }
+        assert_eq!(rushes, [true, false]);
+        assert!(game.rush.is_none());
+        assert_eq!(game.spacing(), normal);
+        assert_eq!(game.next_rush, RUSH_FIRST + BOSS_EVERY);
+    }
+
+    #[test]
+    fn low_ceilings_hang_over_the_next_few_pipes() {
+        let mut game = Game::headless(9);
+        game.step(true);
+        game.score = CEILING_FIRST;
+        game.next_rush = u32::MAX;
+        let mut seen = Vec::new();
+        for _ in 0..3000 {
+            // Held in the middle of the sky, whatever it runs into.
+            game.bird_y = SKY_H / 2.0;
+            game.bird_vy = 0.0;
+            game.fly();
+            let gap = game.gap();
+            let last = game.world.pipes.last().map(|p| p.opening(gap).0);
+            for (id, h) in &game.world.hazards.items {
+                if h.kind.hangs() && !seen.contains(&id.0) {
+                    seen.push(id.0);
+                    let (_, _, _, bottom) = h.extent(game.frame, game.world.physics);
+                    assert!(last.unwrap() >= bottom + BIRD_HITBOX_HH);
+                }
+            }
+            if !seen.is_empty() && game.low_ceiling.is_none() {
+                break;
+            }
+        }
+        let events = std::mem::take(&mut game.outbox);
+        let warnings = events.iter().filter(|e| matches!(e, GameEvent::LowCeiling));
+        assert_eq!(warnings.count(), 1);
+        assert_eq!(seen.len(), CEILING_PIPES as usize);
+        assert!(game.low_ceiling.is_none());
+        assert_eq!(game.next_ceiling, CEILING_FIRST + BOSS_EVERY);
+    }
+
+    #[test]
+    fn update_dt_plays_whole_steps_at_any_frame_rate() {
+        let step = FRAME_DUR.as_secs_f64();
+        let mut fixed = Game::headless(5);
+        let mut slow = Game::headless(5);
+        let mut jittery = Game::headless(5);
+        fixed.flap();
+        slow.flap();
+        jittery.flap();
+        for _ in 0..90 {
+            fixed.update();
+        }
+        for _ in 0..30 {
+            slow.update_dt(step * 3.0);
+        }
+        for i in 0..90 {
+            jittery.update_dt(step * if i % 2 == 0 { 0.8 } else { 1.2 });
+        }
+        assert_eq!(slow.frame, fixed.frame);
+        assert_eq!(jittery.frame, fixed.frame);
+        assert_eq!(slow.snapshot().hash(), fixed.snapshot().hash());
+        assert_eq!(jittery.snapshot().hash(), fixed.snapshot().hash());
+    }
+
+    #[test]
+    fn update_dt_skips_long_stalls() {
+        let mut game = Game::headless(5);
+        game.update_dt(60.0);
+        assert_eq!(game.frame, MAX_CATCH_UP as u64);
+        game.update_dt(FRAME_DUR.as_secs_f64());
+        assert_eq!(game.frame, MAX_CATCH_UP as u64 + 1);
+    }
+
+    #[test]
+    fn headless_runs_are_deterministic() {
+        let a = play(42, 600);
+        let b = play(42, 600);
+        assert_eq!(a.snapshot().hash(), b.snapshot().hash());
+        assert_eq!(a.score(), b.score());
+        assert_ne!(a.snapshot().hash(), play(43, 600).snapshot().hash());
+    }
+
+    #[test]
+    fn snapshots_round_trip_through_json() {
+        let mut snapshot = play(7, 300).snapshot();
+        let id = snapshot.world.next_id();
+        let kind = HazardKind::Cat { phase: 0.3 };
+        snapshot.world.hazards.push(id, Hazard { x: 50.0, kind });
+        let id = snapshot.world.next_id();
+        let kind = HazardKind::Icicles { w: 31.5 };
+        snapshot.world.hazards.push(id, Hazard { x: 80.0, kind });
+        snapshot.rush = Some(Rush { frame: 9 });
+        snapshot.low_ceiling = Some(LowCeiling {
+            frame: 50,
+            fans: false,
+            pipes: 2,
+            lowered: true,
+        });
+        let text = serde_json::to_string(&snapshot).unwrap();
+        let back: Snapshot = serde_json::from_str(&text).unwrap();
+        assert_eq!(back.to_bytes(), snapshot.to_bytes());
+        let cut = text.replace("\"next_rush\"", "\"later_rush\"");
+        assert!(serde_json::from_str::<Snapshot>(&cut).is_err());
+    }
+
+    #[test]
+    fn saved_runs_from_the_future_arent_resumed() {
+        let run = play(9, 200).snapshot();
+        assert!(resumable(&run));
+        let later = Snapshot {
+            milestone: Some((0, run.frame + 1000)),
+            ..run.clone()
+        };
+        assert!(!resumable(&later));
+        let later = Snapshot {
+            zone_banner: Some((Medal::Bronze, run.frame + 1)),
+            ..run.clone()
+        };
+        assert!(!resumable(&later));
+        let unknown = Snapshot {
+            milestone: Some((MILESTONES.len(), run.frame)),
+            ..run
+        };
+        assert!(!resumable(&unknown));
+    }
+
+    #[test]
+    fn restoring_a_snapshot_replays_the_same_run() {
+        let mut game = play(9, 200);
+        let snapshot = game.snapshot();
+        let ahead: Vec<u64> = (0..100)
+            .map(|i| {
+                game.step(i % 15 == 0);
+                game.snapshot().hash()
+            })
+            .collect();
+        game.restore(&snapshot);
+        let again: Vec<u64> = (0..100)
+            .map(|i| {
+                game.step(i % 15 == 0);
+                game.snapshot().hash()
+            })
+            .collect();
+        assert_eq!(ahead, again);
+    }
+
+    #[test]
+    fn drawing_between_steps_puts_the_game_back() {
+        let mut game = play(9, 200);
+        let prev = Step::new(&game);
+        game.step(false);
+        let after = game.snapshot().hash();
+        let mut drawn = None;
+        game.draw_between(&prev, 0.5, |view| drawn = Some(view.bird_y));
+        let halfway = (prev.prev.bird_y + game.bird_y) / 2.0;
+        assert!((drawn.unwrap() - halfway).abs() < 1e-9);
+        assert_eq!(game.snapshot().hash(), after);
+    }
+}
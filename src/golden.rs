@@ -0,0 +1,211 @@
+//! Golden images: the scene viewer's scenes drawn headlessly and compared,
+//! pixel for pixel as a terminal would show them, with references checked in
+//! under `tests/golden`. A drawing or rendering change that alters any of
+//! them fails here; after a deliberate one, `FLAPPY_GOLDEN=update cargo test`
+//! writes them afresh. A mismatch leaves what was drawn beside the
+//! reference, as `<name>.actual.ppm`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::pixels::{ANSI16, CUBE_LEVELS, ColorMode, PixelBuf, PostFx, Rgb, TermColor};
+use crate::viewer::{SCENES, scene_game};
+
+// ── Golden images ───────────────────────────────────────────────────────────
+
+const GOLDEN_SIZE: (usize, usize) = (128, 72);
+
+// A frame to check: a viewer scene, and how it's drawn and shown.
+struct Golden {
+    name: &'static str,
+    scene: &'static str,
+    color_mode: ColorMode,
+    effects: &'static [PostFx],
+    zoom: f64,
+}
+
+// Beyond every scene as it stands, these cover the color reductions, the
+// retro effects and zooming.
+const VARIANTS: [Golden; 4] = [
+    Golden {
+        name: "title-256",
+        scene: "TITLE",
+        color_mode: ColorMode::Ansi256,
+        effects: &[],
+        zoom: 1.0,
+    },
+    Golden {
+        name: "title-16",
+        scene: "TITLE",
+        color_mode: ColorMode::Ansi16,
+        effects: &[],
+        zoom: 1.0,
+    },
+    Golden {
+        name: "playing-effects",
+        scene: "PLAYING",
+        color_mode: ColorMode::TrueColor,
+        effects: &[PostFx::Scanlines, PostFx::Vignette, PostFx::Bleed],
+        zoom: 1.0,
+    },
+    Golden {
+        name: "playing-zoomed",
+        scene: "PLAYING",
+        color_mode: ColorMode::TrueColor,
+        effects: &[],
+        zoom: 2.0,
+    },
+];
+
+fn goldens() -> Vec<Golden> {
+    let scenes = SCENES.iter().map(|staged| Golden {
+        name: staged.name,
+        scene: staged.name,
+        color_mode: ColorMode::TrueColor,
+        effects: &[],
+        zoom: 1.0,
+    });
+    scenes.chain(VARIANTS).collect()
+}
+
+impl Golden {
+    fn file_name(&self) -> String {
+        self.name.to_lowercase().replace(' ', "-")
+    }
+
+    // The frame as a terminal shows it, row by row.
+    fn draw(&self) -> (usize, usize, Vec<Rgb>) {
+        let (w, h) = GOLDEN_SIZE;
+        let mut game = scene_game(w, h);
+        game.set_zoom(self.zoom);
+        if let Some(staged) = SCENES.iter().find(|s| s.name == self.scene) {
+            (staged.stage)(&mut game);
+        }
+        let mut buf = PixelBuf::new(w, h);
+        buf.color_mode = self.color_mode;
+        buf.effects = self.effects.to_vec();
+        game.draw(&mut buf);
+        buf.apply_effects();
+        let px = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| shown(buf.term_color(x, y)))
+            .collect();
+        (w, h, px)
+    }
+
+    // Compares the frame with its reference, or with FLAPPY_GOLDEN=update,
+    // writes it as the new one.
+    fn check(&self) -> Result<(), String> {
+        let (w, h, px) = self.draw();
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let path = dir.join(format!("{}.ppm", self.file_name()));
+        let actual = dir.join(format!("{}.actual.ppm", self.file_name()));
+        if std::env::var("FLAPPY_GOLDEN").as_deref() == Ok("update") {
+            let _ = fs::remove_file(&actual);
+            return fs::create_dir_all(&dir)
+                .and_then(|_| fs::write(&path, encode_ppm(w, h, &px)))
+                .map_err(|e| format!("{}: can't write {}: {e}", self.name, path.display()));
+        }
+        let Some((rw, rh, reference)) = fs::read(&path).ok().and_then(|b| decode_ppm(&b)) else {
+            return Err(format!(
+                "{}: no golden image at {}; FLAPPY_GOLDEN=update writes it",
+                self.name,
+                path.display()
+            ));
+        };
+        let differ = |(a, b): (&Rgb, &Rgb)| a != b;
+        let first = px.iter().zip(&reference).position(differ);
+        if (rw, rh) == (w, h) && first.is_none() {
+            let _ = fs::remove_file(&actual);
+            return Ok(());
+        }
+        let _ = fs::write(&actual, encode_ppm(w, h, &px));
+        let detail = match first {
+            Some(i) if (rw, rh) == (w, h) => {
+                let n = px.iter().zip(&reference).filter(|p| differ(*p)).count();
+                format!(
+                    "{n} of {} pixels differ, the first at ({}, {})",
+                    w * h,
+                    i % w,
+                    i / w
+                )
+            }
+            _ => format!("drawn at {w}x{h}, but the reference is {rw}x{rh}"),
+        };
+        Err(format!("{}: {detail}; see {}", self.name, actual.display()))
+    }
+}
+
+// The color xterm shows for a terminal color; the sky left unpainted for a
+// transparent background shows as black.
+fn shown(color: TermColor) -> Rgb {
+    match color {
+        TermColor::Rgb { r, g, b } => Rgb(r, g, b),
+        TermColor::AnsiValue(n) if n >= 232 => {
+            let v = 8 + (n - 232) * 10;
+            Rgb(v, v, v)
+        }
+        TermColor::AnsiValue(n) if n >= 16 => {
+            let n = (n - 16) as usize;
+            let level = |i: usize| CUBE_LEVELS[i % 6];
+            Rgb(level(n / 36), level(n / 6), level(n))
+        }
+        TermColor::AnsiValue(n) => ANSI16[n as usize],
+        TermColor::Reset => Rgb(0, 0, 0),
+    }
+}
+
+fn encode_ppm(w: usize, h: usize, px: &[Rgb]) -> Vec<u8> {
+    let mut out = format!("P6\n{w} {h}\n255\n").into_bytes();
+    for Rgb(r, g, b) in px {
+        out.extend([*r, *g, *b]);
+    }
+    out
+}
+
+fn decode_ppm(bytes: &[u8]) -> Option<(usize, usize, Vec<Rgb>)> {
+    let mut fields = bytes.splitn(5, |b| b.is_ascii_whitespace());
+    let mut field = || std::str::from_utf8(fields.next()?).ok();
+    if field()? != "P6" {
+        return None;
+    }
+    let (w, h): (usize, usize) = (field()?.parse().ok()?, field()?.parse().ok()?);
+    if field()? != "255" {
+        return None;
+    }
+    let data = fields.next()?;
+    let px: Vec<Rgb> = data
+        .chunks_exact(3)
+        .map(|c| Rgb(c[0], c[1], c[2]))
+        .collect();
+    (px.len() == w * h).then_some((w, h, px))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_round_trips() {
+        let px = vec![Rgb(1, 2, 3), Rgb(10, 32, 255), Rgb(0, 0, 0), Rgb(9, 13, 10)];
+        let ppm = encode_ppm(2, 2, &px);
+        assert!(ppm.starts_with(b"P6\n2 2\n255\n"));
+        let (w, h, back) = decode_ppm(&ppm).unwrap();
+        assert_eq!((w, h), (2, 2));
+        assert!(back == px);
+        assert!(decode_ppm(&ppm[..ppm.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn terminal_colors_show_as_xterm_draws_them() {
+        assert!(shown(TermColor::AnsiValue(16 + 36 * 5 + 2)) == Rgb(255, 0, 135));
+        assert!(shown(TermColor::AnsiValue(232)) == Rgb(8, 8, 8));
+        assert!(shown(TermColor::AnsiValue(4)) == Rgb(0, 0, 238));
+    }
+
+    #[test]
+    fn scenes_match_their_golden_images() {
+        let failures: Vec<String> = goldens().iter().filter_map(|g| g.check().err()).collect();
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+}
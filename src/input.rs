@@ -0,0 +1,449 @@
+//! Keyboard layouts and turning terminal events into game actions.
+
+use std::time::Duration;
+// std can't read the clock in a browser; web-time asks the page instead.
+#[cfg(not(feature = "web"))]
+use std::time::Instant;
+#[cfg(feature = "web")]
+use web_time::Instant;
+
+use crate::audio::Sound;
+use crate::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+use crate::game::{
+    Camera, Game, KEY_HOLD_TIMEOUT, KEY_REPEAT_WINDOW, MIN_COLS, State, UiEvent, WORLD_H,
+};
+use crate::pixels::{CellLayout, PixelBuf};
+use crate::ui::Screen;
+
+// ── Key layouts ─────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Action {
+    Flap,
+    GravityUp,
+    GravityDown,
+    FlapUp,
+    FlapDown,
+    SpeedUp,
+    SpeedDown,
+    PipeStyle,
+    Mutators,
+    Editor,
+    Stats,
+    Playground,
+    Quit,
+}
+
+// Preset bindings, picked with FLAPPY_KEYS. Space, arrows, Enter and Esc
+// work the same in every layout; presets only move the character keys.
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyLayout {
+    Standard,
+    // Tuning on the right-hand side of the keyboard.
+    LeftHanded,
+    // Tuning on the digit row, which Dvorak and QWERTY share.
+    Dvorak,
+    // Everything on the numeric keypad.
+    Numpad,
+}
+
+pub(crate) const TUNING_ACTIONS: [Action; 6] = [
+    Action::GravityUp,
+    Action::GravityDown,
+    Action::FlapUp,
+    Action::FlapDown,
+    Action::SpeedUp,
+    Action::SpeedDown,
+];
+
+impl KeyLayout {
+    pub fn from_env() -> KeyLayout {
+        match std::env::var("FLAPPY_KEYS").as_deref() {
+            Ok("left") => KeyLayout::LeftHanded,
+            Ok("dvorak") => KeyLayout::Dvorak,
+            Ok("numpad") => KeyLayout::Numpad,
+            _ => KeyLayout::Standard,
+        }
+    }
+
+    // Keys for each of TUNING_ACTIONS, in order.
+    fn tuning_keys(self) -> [char; 6] {
+        match self {
+            KeyLayout::Standard => ['a', 'z', 's', 'x', 'd', 'c'],
+            KeyLayout::LeftHanded => ['u', 'j', 'i', 'k', 'o', 'l'],
+            KeyLayout::Dvorak => ['1', '2', '3', '4', '5', '6'],
+            KeyLayout::Numpad => ['7', '1', '8', '2', '9', '3'],
+        }
+    }
+
+    fn action(self, code: KeyCode) -> Option<Action> {
+        let c = match code {
+            KeyCode::Char(' ') | KeyCode::Up | KeyCode::Enter => return Some(Action::Flap),
+            KeyCode::Char(c) => c,
+            _ => return None,
+        };
+        if let Some(i) = self.tuning_keys().iter().position(|k| *k == c) {
+            return Some(TUNING_ACTIONS[i]);
+        }
+        match (self, c) {
+            (KeyLayout::Numpad, '0') => Some(Action::Flap),
+            (KeyLayout::Numpad, '/') => Some(Action::PipeStyle),
+            (KeyLayout::Numpad, '*') => Some(Action::Mutators),
+            (KeyLayout::Numpad, '-') => Some(Action::Editor),
+            (KeyLayout::Numpad, '+') => Some(Action::Stats),
+            (KeyLayout::Numpad, '.') => Some(Action::Playground),
+            (_, 'p') => Some(Action::PipeStyle),
+            (_, 'm') => Some(Action::Mutators),
+            (_, 'e') => Some(Action::Editor),
+            (_, 'h') => Some(Action::Stats),
+            (_, 'g') => Some(Action::Playground),
+            (_, 'q') => Some(Action::Quit),
+            _ => None,
+        }
+    }
+}
+
+// ── Input ───────────────────────────────────────────────────────────────────
+
+// Turns flap key events into flaps. With the kitty keyboard protocol the
+// terminal tells us about repeats and releases. Elsewhere repeats arrive as
+// fresh presses, so a press arriving faster than anyone can tap is treated as
+// auto-repeat and dropped, and a stream of repeats means the key is held.
+// Held state drives the opt-in hold-to-flap assist.
+pub struct FlapInput {
+    hold_interval: Option<Duration>,
+    // True when the terminal reports key event kinds (kitty protocol).
+    precise: bool,
+    last_key: Option<Instant>,
+    last_flap: Option<Instant>,
+    held: bool,
+}
+
+impl FlapInput {
+    pub fn new(hold_rate: Option<f64>, precise: bool) -> Self {
+        FlapInput {
+            hold_interval: hold_rate
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            precise,
+            last_key: None,
+            last_flap: None,
+            held: false,
+        }
+    }
+
+    // Returns true when this key event is a real flap.
+    fn key(&mut self, kind: KeyEventKind, now: Instant) -> bool {
+        if kind == KeyEventKind::Release {
+            self.held = false;
+            return false;
+        }
+        let repeat = kind == KeyEventKind::Repeat
+            || !self.precise
+                && self
+                    .last_key
+                    .is_some_and(|t| now.duration_since(t) < KEY_REPEAT_WINDOW);
+        self.last_key = Some(now);
+        self.held = self.precise || repeat;
+        if !repeat {
+            self.last_flap = Some(now);
+        }
+        !repeat
+    }
+
+    pub fn is_held(&self, now: Instant) -> bool {
+        self.held
+            && (self.precise
+                || self
+                    .last_key
+                    .is_some_and(|t| now.duration_since(t) < KEY_HOLD_TIMEOUT))
+    }
+
+    // Returns true when a held key is due for another assisted flap.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let Some(interval) = self.hold_interval else {
+            return false;
+        };
+        let still_held = self.is_held(now);
+        let due = self
+            .last_flap
+            .is_none_or(|t| now.duration_since(t) >= interval);
+        if still_held && due {
+            self.last_flap = Some(now);
+        }
+        still_held && due
+    }
+}
+
+fn press_flap(game: &mut Game, sounds: &mut Vec<Sound>) {
+    let restarting = game.state == State::Dead;
+    if let Some(event) = game.flap() {
+        sounds.push(Sound::Game(event));
+    } else if restarting {
+        sounds.push(Sound::Ui(UiEvent::Select));
+    }
+}
+
+// What a scene handling input gets besides the game: the key layout, the
+// flap key's state and the sounds to play.
+pub(crate) struct Controls<'a> {
+    keys: KeyLayout,
+    flap_input: &'a mut FlapInput,
+    sounds: &'a mut Vec<Sound>,
+}
+
+impl Controls<'_> {
+    pub(crate) fn action(&self, code: KeyCode) -> Option<Action> {
+        self.keys.action(code)
+    }
+
+    pub(crate) fn ui(&mut self, event: UiEvent) {
+        self.sounds.push(Sound::Ui(event));
+    }
+
+    pub(crate) fn flap(&mut self, game: &mut Game) {
+        press_flap(game, self.sounds);
+    }
+
+    // Flaps for a flap key event, unless it's a release or auto-repeat.
+    pub(crate) fn flap_key(&mut self, game: &mut Game, kind: KeyEventKind) {
+        if self.flap_input.key(kind, Instant::now()) {
+            self.flap(game);
+        }
+    }
+}
+
+// With FLAPPY_MOUSE: hovering highlights what a click would hit, clicks work
+// menus and flap in play, and the wheel adjusts a hovered tuning value.
+fn handle_mouse(mouse: MouseEvent, game: &mut Game, layout: CellLayout, controls: &mut Controls) {
+    game.pointer = Some(layout.cell_to_pixel(mouse.column, mouse.row));
+    let click = mouse.kind == MouseEventKind::Down(MouseButton::Left);
+    if game.touch && click {
+        let running = matches!(game.state, State::Playing | State::Paused);
+        if running && game.hovers(game.pause_button()) {
+            if game.state == State::Paused {
+                game.close();
+            } else {
+                game.open(State::Paused);
+            }
+            controls.ui(UiEvent::Select);
+            return;
+        }
+        if (running || game.state == State::Dying) && game.hovers(game.restart_button()) {
+            game.restart();
+            controls.ui(UiEvent::Select);
+            return;
+        }
+    }
+    if game
+        .scene()
+        .handle_input(game, &Event::Mouse(mouse), controls)
+    {
+        return;
+    }
+    let scroll = match mouse.kind {
+        MouseEventKind::ScrollUp => 1.0,
+        MouseEventKind::ScrollDown => -1.0,
+        _ => 0.0,
+    };
+    let rows = game.tuning_rows();
+    if game.show_hud && scroll != 0.0 {
+        match (0..3).find(|&i| game.hovers(rows[i])) {
+            Some(0) => game.tune_gravity(0.02 * scroll),
+            Some(1) => game.tune_flap(0.2 * scroll),
+            Some(_) => game.tune_speed(0.1 * scroll),
+            None => return,
+        }
+        controls.ui(UiEvent::Move);
+    } else if click
+        && (game.touch || game.screen() != Screen::GameOver || game.hovers(game.game_over_panel()))
+    {
+        controls.flap(game);
+    }
+}
+
+// The keys every run scene shares, once the scene has passed on a key: flap,
+// the tuning keys, and closing the tuning overlay or quitting. Returns false
+// when the player quits.
+fn handle_key(key: KeyEvent, game: &mut Game, controls: &mut Controls) -> bool {
+    match (key.code, controls.action(key.code)) {
+        (KeyCode::Esc, _) if game.show_hud => {
+            game.show_hud = false;
+            controls.ui(UiEvent::Back);
+        }
+        (KeyCode::Esc, _) | (_, Some(Action::Quit)) => return false,
+        (_, Some(Action::Flap)) => controls.flap_key(game, key.kind),
+        (_, Some(action)) if TUNING_ACTIONS.contains(&action) => {
+            match action {
+                Action::GravityUp => game.tune_gravity(0.02),
+                Action::GravityDown => game.tune_gravity(-0.02),
+                Action::FlapUp => game.tune_flap(0.2), // more negative = stronger
+                Action::FlapDown => game.tune_flap(-0.2),
+                Action::SpeedUp => game.tune_speed(0.1),
+                _ => game.tune_speed(-0.1),
+            }
+            controls.ui(UiEvent::Move);
+        }
+        _ => {}
+    }
+    true
+}
+
+// Applies one terminal event to the game, offering it to the scene on top
+// first. Returns false when the player quits.
+pub fn handle_input(
+    event: Event,
+    game: &mut Game,
+    buf: &mut PixelBuf,
+    layout: CellLayout,
+    keys: KeyLayout,
+    flap_input: &mut FlapInput,
+    sounds: &mut Vec<Sound>,
+) -> bool {
+    let flap_key = |code| keys.action(code) == Some(Action::Flap);
+    let mut controls = Controls {
+        keys,
+        flap_input,
+        sounds,
+    };
+    // Any key or click puts off the title's doze, and one that ends it does
+    // only that.
+    if matches!(event, Event::Key(_) | Event::Mouse(_)) {
+        let dozing = game.is_dozing();
+        game.title_frames = 0;
+        if dozing {
+            return true;
+        }
+    }
+    match event {
+        // Any key ends the screensaver.
+        Event::Key(key) if game.screensaver => return key.kind == KeyEventKind::Release,
+        Event::Key(key) if key.kind == KeyEventKind::Release && !flap_key(key.code) => {}
+        Event::Key(_) if game.scene().handle_input(game, &event, &mut controls) => {}
+        Event::Key(key) => return handle_key(key, game, &mut controls),
+        Event::Mouse(mouse) if game.screensaver => {
+            return !matches!(mouse.kind, MouseEventKind::Down(_));
+        }
+        Event::Mouse(mouse) => handle_mouse(mouse, game, layout, &mut controls),
+        Event::Resize(c, r) => {
+            tracing::info!(cols = c, rows = r, "resize");
+            let r = r.saturating_sub(buf.status.is_some() as u16);
+            let (npw, nph) = layout.pixel_size(c, r);
+            buf.resize(npw, nph);
+            if c < MIN_COLS || r < layout.min_rows() {
+                game.enter(State::TooSmall);
+                game.pw = npw;
+                game.ph = nph;
+                game.cam = Camera::new(nph as f64 / WORLD_H, 0.0, npw, nph);
+                game.world_w = npw as f64 / game.cam.scale;
+            } else if game.state == State::TooSmall {
+                let best = game.best;
+                game.resize(npw, nph);
+                game.best = best;
+            } else {
+                // Keep the run going at the new size.
+                let snap = game.snapshot();
+                game.resize(npw, nph);
+                game.restore(&snap);
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{DOZE_FPS, SIM_FPS};
+
+    #[test]
+    fn layouts_move_only_the_character_keys() {
+        for layout in [
+            KeyLayout::Standard,
+            KeyLayout::LeftHanded,
+            KeyLayout::Dvorak,
+            KeyLayout::Numpad,
+        ] {
+            assert!(layout.action(KeyCode::Char(' ')) == Some(Action::Flap));
+            assert!(layout.action(KeyCode::Up) == Some(Action::Flap));
+            assert!(layout.action(KeyCode::Esc).is_none());
+            let tuning = layout
+                .tuning_keys()
+                .map(|c| layout.action(KeyCode::Char(c)));
+            assert!(
+                tuning
+                    .iter()
+                    .zip(TUNING_ACTIONS)
+                    .all(|(a, b)| *a == Some(b))
+            );
+        }
+        assert!(KeyLayout::Numpad.action(KeyCode::Char('0')) == Some(Action::Flap));
+        assert!(KeyLayout::Standard.action(KeyCode::Char('0')).is_none());
+        assert!(KeyLayout::Standard.action(KeyCode::Char('q')) == Some(Action::Quit));
+    }
+
+    #[test]
+    fn fast_presses_count_as_auto_repeat() {
+        let mut input = FlapInput::new(None, false);
+        let t = Instant::now();
+        assert!(input.key(KeyEventKind::Press, t));
+        assert!(!input.key(KeyEventKind::Press, t + KEY_REPEAT_WINDOW / 2));
+        assert!(input.is_held(t + KEY_REPEAT_WINDOW / 2));
+        let later = t + KEY_REPEAT_WINDOW * 2 + KEY_HOLD_TIMEOUT;
+        assert!(!input.is_held(later));
+        assert!(input.key(KeyEventKind::Press, later));
+    }
+
+    #[test]
+    fn held_keys_flap_at_the_hold_rate() {
+        let mut input = FlapInput::new(Some(10.0), true);
+        let t = Instant::now();
+        assert!(input.key(KeyEventKind::Press, t));
+        assert!(!input.tick(t + Duration::from_millis(50)));
+        assert!(input.tick(t + Duration::from_millis(100)));
+        assert!(!input.key(KeyEventKind::Repeat, t + Duration::from_millis(120)));
+        input.key(KeyEventKind::Release, t + Duration::from_millis(150));
+        assert!(!input.tick(t + Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn a_key_wakes_a_dozing_title_without_flapping() {
+        let mut game = Game::headless(3);
+        game.doze_after = Some(Duration::from_secs(1));
+        for _ in 0..=SIM_FPS {
+            game.update();
+        }
+        assert!(game.is_dozing());
+        assert_eq!(game.frame_rate(), Some(DOZE_FPS));
+        let mut buf = PixelBuf::new(game.pw, game.ph);
+        let mut input = FlapInput::new(None, true);
+        let space = Event::Key(KeyEvent::from(KeyCode::Char(' ')));
+        let keys = KeyLayout::Standard;
+        let mut sounds = Vec::new();
+        let layout = CellLayout::HalfBlock;
+        handle_input(
+            space.clone(),
+            &mut game,
+            &mut buf,
+            layout,
+            keys,
+            &mut input,
+            &mut sounds,
+        );
+        assert!(!game.is_dozing() && game.state == State::Ready);
+        assert_eq!(game.frame_rate(), None);
+        handle_input(
+            space,
+            &mut game,
+            &mut buf,
+            layout,
+            keys,
+            &mut input,
+            &mut sounds,
+        );
+        assert!(game.state == State::Playing);
+    }
+}
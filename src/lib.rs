@@ -0,0 +1,59 @@
+//! The game behind the `flappy-tui` binary: `Game` steps and draws a run into
+//! a `PixelBuf`, which renders itself as terminal cells, and `Audio` plays the
+//! `GameEvent`s that `Game::update` returns, which an `EventBus` hands to
+//! whatever else subscribes. The binary adds the terminal setup and the event
+//! loop; the `web` feature adds `WebGame`, which runs the game in a browser.
+//!
+//! The simulation lives in `game`. Drawing is in `draw`, `render` and `ui`,
+//! over the reusable `pixels`; sounds are made in `audio` and played in
+//! `speaker`; keys are handled in `input`; and `scene` says which of it each
+//! screen uses. What fails comes back as a `FlappyError`. Everything public
+//! is re-exported here, so callers don't need to know which module an item
+//! is in.
+mod audio;
+mod bus;
+mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+mod diag;
+mod draw;
+mod error;
+mod event;
+mod game;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod golden;
+mod input;
+#[cfg(not(target_arch = "wasm32"))]
+mod mirror;
+pub mod pixels;
+mod render;
+mod scene;
+#[cfg(not(target_arch = "wasm32"))]
+mod speaker;
+mod theme;
+mod ui;
+mod update;
+#[cfg(not(target_arch = "wasm32"))]
+mod viewer;
+#[cfg(feature = "web")]
+mod web;
+
+pub use audio::*;
+pub use bus::*;
+pub use capture::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use diag::*;
+pub use error::*;
+pub use game::*;
+pub use input::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use mirror::*;
+pub use pixels::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use speaker::*;
+pub use theme::*;
+pub use ui::*;
+pub use update::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use viewer::*;
+#[cfg(feature = "web")]
+pub use web::*;
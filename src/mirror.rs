@@ -0,0 +1,132 @@
+//! `--mirror`: the game drawn again to other terminals or files. Terminal
+//! only, as a browser has neither.
+
+use crossterm::{cursor, execute, queue, style, terminal};
+use std::fs;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::time::Duration;
+
+use crate::error::FlappyError;
+use crate::pixels::{PixelBuf, Renderer, render_cells};
+
+// How long quitting waits on a mirror to take its last frame.
+const MIRROR_DRAIN: Duration = Duration::from_millis(500);
+
+// A second screen or a recorder, drawn as cells like the terminal and fed by
+// its own thread, so a slow one drops frames rather than holding up the game.
+pub struct Mirror {
+    bytes: Vec<u8>,
+    size: (usize, usize),
+    frames: Option<SyncSender<(Arc<[u8]>, bool)>>,
+    // Set by a resize until a frame carrying the clear gets through.
+    clear_pending: bool,
+    // Hangs up when the writer thread finishes.
+    done: Receiver<()>,
+}
+
+impl Mirror {
+    pub fn spawn(mut file: fs::File) -> Mirror {
+        let (frames, rx) = mpsc::sync_channel::<(Arc<[u8]>, bool)>(1);
+        let (done_tx, done) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _done = done_tx;
+            let _ = queue!(file, cursor::Hide, terminal::DisableLineWrap);
+            for (bytes, clear) in rx {
+                // A frame of another size leaves the edges of the last one behind.
+                if clear {
+                    let _ = queue!(file, terminal::Clear(terminal::ClearType::All));
+                }
+                if let Err(e) = file.write_all(&bytes) {
+                    // The reader went away; later frames are dropped.
+                    tracing::warn!(error = %e, "mirror stopped");
+                    return;
+                }
+            }
+            let _ = execute!(
+                file,
+                style::ResetColor,
+                cursor::Show,
+                terminal::EnableLineWrap
+            );
+        });
+        Mirror {
+            bytes: Vec::new(),
+            size: (0, 0),
+            frames: Some(frames),
+            clear_pending: false,
+            done,
+        }
+    }
+}
+
+impl Renderer for Mirror {
+    // Drops the frame if the mirror is still busy with the last one. A
+    // resize's clear rides on the next frame that gets through. A mirror that
+    // fails goes dark without stopping the game.
+    fn present(&mut self, buf: &PixelBuf) -> io::Result<()> {
+        let Some(frames) = &self.frames else {
+            return Ok(());
+        };
+        self.bytes.clear();
+        render_cells(buf, &mut self.bytes)?;
+        self.clear_pending |= self.size != (buf.w, buf.h);
+        self.size = (buf.w, buf.h);
+        match frames.try_send((self.bytes.as_slice().into(), self.clear_pending)) {
+            Ok(()) => self.clear_pending = false,
+            Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => self.frames = None,
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Mirror {
+    // Gives the writer a moment to finish its frame and restore the reader's
+    // terminal, then leaves it be; a stalled reader can't hold up quitting.
+    fn drop(&mut self) {
+        self.frames = None;
+        let _ = self.done.recv_timeout(MIRROR_DRAIN);
+    }
+}
+
+// Opens the `--mirror` targets given on the command line. A FIFO's reader has
+// to be running already, as opening one waits for it.
+pub fn open_mirrors() -> Result<Vec<Mirror>, FlappyError> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    args.windows(2)
+        .filter(|pair| pair[0] == "--mirror")
+        .map(|pair| {
+            let path = &pair[1];
+            let file = fs::OpenOptions::new().write(true).open(path);
+            file.map(Mirror::spawn)
+                .map_err(FlappyError::asset("a mirror", path.into()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_busy_mirror_keeps_the_resize_clear_for_the_next_frame() {
+        let (frames, rx) = mpsc::sync_channel(1);
+        let (_, done) = mpsc::channel();
+        let mut mirror = Mirror {
+            bytes: Vec::new(),
+            size: (4, 4),
+            frames: Some(frames),
+            clear_pending: false,
+            done,
+        };
+        mirror.present(&PixelBuf::new(4, 4)).unwrap();
+        mirror.present(&PixelBuf::new(6, 4)).unwrap();
+        assert!(!rx.recv().unwrap().1);
+        mirror.present(&PixelBuf::new(6, 4)).unwrap();
+        assert!(rx.recv().unwrap().1);
+        mirror.present(&PixelBuf::new(6, 4)).unwrap();
+        assert!(!rx.recv().unwrap().1);
+    }
+}
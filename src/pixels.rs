@@ -0,0 +1,1448 @@
+//! A pixel renderer for terminal games: draw into a `PixelBuf` with its
+//! primitives, sprites and bitmap font, and a `Renderer` puts it on screen as
+//! colored cells, two pixels to a cell with half blocks, in true color or
+//! reduced to 256 or 16 with ordered dithering. A `SwapChain` writes frames
+//! on a thread of its own, and `PostFx` adds retro display passes. None of it
+//! knows about the game, which draws with it in `draw` and `ui`.
+//!
+//! ```no_run
+//! use flappy_tui::pixels::{CellLayout, CellRenderer, PixelBuf, Renderer, Rgb, draw_text_4x6};
+//!
+//! let (w, h) = CellLayout::HalfBlock.pixel_size(40, 12);
+//! let mut buf = PixelBuf::new(w, h);
+//! buf.clear(Rgb(20, 24, 40));
+//! buf.fill_circle(20.0, 12.0, 6.0, Rgb(245, 200, 66));
+//! draw_text_4x6(&mut buf, 2, 2, "HI", Rgb(255, 255, 255), 1);
+//! CellRenderer::new(std::io::stdout()).present(&buf)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::ops::Range;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::Instant;
+
+// How long to wait for the terminal to answer a query.
+#[cfg(unix)]
+const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(200);
+
+// Buffers with at least this many pixels fill rows in parallel.
+const PARALLEL_MIN_PIXELS: usize = 160 * 80;
+
+// ── Colors ──────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const fn lerp(a: Rgb, b: Rgb, t_256: u16) -> Rgb {
+        let t = t_256 as i32;
+        Rgb(
+            (a.0 as i32 + (b.0 as i32 - a.0 as i32) * t / 256) as u8,
+            (a.1 as i32 + (b.1 as i32 - a.1 as i32) * t / 256) as u8,
+            (a.2 as i32 + (b.2 as i32 - a.2 as i32) * t / 256) as u8,
+        )
+    }
+
+    // Like `lerp`, but mixes in linear light so gradients don't sag into a
+    // dark, muddy middle. `lerp` stays as the fast path for per-pixel effects.
+    pub fn lerp_linear(a: Rgb, b: Rgb, t_256: u16) -> Rgb {
+        let (to_lin, to_srgb) = gamma_tables();
+        let t = t_256.min(256) as u32;
+        let mix = |a: u8, b: u8| {
+            let (la, lb) = (to_lin[a as usize] as u32, to_lin[b as usize] as u32);
+            to_srgb[((la * (256 - t) + lb * t) / 256) as usize]
+        };
+        Rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+    }
+
+    // Turns the hue by `degrees` while keeping luminance, with the same
+    // matrix as CSS `hue-rotate`.
+    pub fn hue_rotate(self, degrees: f64) -> Rgb {
+        if degrees == 0.0 {
+            return self;
+        }
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (r, g, b) = (self.0 as f64, self.1 as f64, self.2 as f64);
+        let ch = |kr: [f64; 3], kg: [f64; 3], kb: [f64; 3]| {
+            let k = |m: [f64; 3]| m[0] + m[1] * cos + m[2] * sin;
+            (r * k(kr) + g * k(kg) + b * k(kb))
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        Rgb(
+            ch(
+                [0.213, 0.787, -0.213],
+                [0.715, -0.715, -0.715],
+                [0.072, -0.072, 0.928],
+            ),
+            ch(
+                [0.213, -0.213, 0.143],
+                [0.715, 0.285, 0.140],
+                [0.072, -0.072, -0.283],
+            ),
+            ch(
+                [0.213, -0.213, -0.787],
+                [0.715, -0.715, 0.715],
+                [0.072, 0.928, 0.072],
+            ),
+        )
+    }
+}
+
+// sRGB channel → 12-bit linear, and back.
+fn gamma_tables() -> &'static ([u16; 256], [u8; 4096]) {
+    static TABLES: OnceLock<([u16; 256], [u8; 4096])> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut to_lin = [0u16; 256];
+        for (i, l) in to_lin.iter_mut().enumerate() {
+            let c = i as f64 / 255.0;
+            let lin = if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            };
+            *l = (lin * 4095.0).round() as u16;
+        }
+        let mut to_srgb = [0u8; 4096];
+        for (i, c) in to_srgb.iter_mut().enumerate() {
+            let l = i as f64 / 4095.0;
+            let srgb = if l <= 0.003_130_8 {
+                l * 12.92
+            } else {
+                1.055 * l.powf(1.0 / 2.4) - 0.055
+            };
+            *c = (srgb * 255.0).round() as u8;
+        }
+        (to_lin, to_srgb)
+    })
+}
+
+const BLACK: Rgb = Rgb(0, 0, 0);
+
+// ── Terminal ────────────────────────────────────────────────────────────────
+
+// The colors cells are drawn in: exact, or the nearest of xterm's 256 or 16.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+// A cell's color as the terminal is told it: its own default, an index into
+// its palette (the first 16 being its base colors), or exact.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TermColor {
+    Reset,
+    AnsiValue(u8),
+    Rgb { r: u8, g: u8, b: u8 },
+}
+
+impl ColorMode {
+    // True color, except that tmux and screen get 256 colors unless they show
+    // they pass true color through. Also returns whether that fallback kicked
+    // in.
+    pub fn probe() -> (ColorMode, bool) {
+        let env = |name| std::env::var(name).unwrap_or_default();
+        let term = env("TERM");
+        let multiplexed = std::env::var_os("TMUX").is_some()
+            || std::env::var_os("STY").is_some()
+            || term.starts_with("tmux")
+            || term.starts_with("screen");
+        if !multiplexed {
+            return (ColorMode::TrueColor, false);
+        }
+        let truecolor = probe_truecolor()
+            .unwrap_or_else(|| matches!(env("COLORTERM").as_str(), "truecolor" | "24bit"));
+        if truecolor {
+            (ColorMode::TrueColor, false)
+        } else {
+            (ColorMode::Ansi256, true)
+        }
+    }
+}
+
+// Sets a true color and asks the terminal what it ended up with (DECRQSS).
+// Terminals that keep only a palette index answer with that instead, and
+// ones that don't answer leave it unknown.
+fn probe_truecolor() -> Option<bool> {
+    let reply = query_terminal("\x1b[38;2;1;2;3m\x1bP$qm\x1b\\\x1b[0m")?;
+    let answer = reply.split("$r").nth(1)?;
+    Some(
+        ["2;1;2;3", "2:1:2:3", "2::1:2:3"]
+            .iter()
+            .any(|rgb| answer.contains(rgb)),
+    )
+}
+
+// The terminal's background color, from its answer to OSC 11.
+pub fn query_background() -> Option<Rgb> {
+    parse_background(&query_terminal("\x1b]11;?\x1b\\")?)
+}
+
+fn parse_background(reply: &str) -> Option<Rgb> {
+    let spec = reply.split("rgb:").nth(1)?;
+    let spec = spec.trim_end_matches(['\x1b', '\\', '\x07']);
+    // Each channel is 1 to 4 hex digits; the top byte is all we draw with.
+    let channel = |hex: &str| {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let v = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len() as u32)) - 1;
+        Some((v * 255 / max) as u8)
+    };
+    let mut parts = spec.split('/').map(channel);
+    Some(Rgb(parts.next()??, parts.next()??, parts.next()??))
+}
+
+// Writes `request` and collects the reply, up to a string terminator (ST or
+// BEL) or QUERY_TIMEOUT. Runs before the event stream starts reading.
+#[cfg(unix)]
+fn query_terminal(request: &str) -> Option<String> {
+    use std::os::fd::AsRawFd;
+    let mut out = io::stdout();
+    write!(out, "{request}").ok()?;
+    out.flush().ok()?;
+    let fd = io::stdin().as_raw_fd();
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut reply = Vec::new();
+    while !reply.ends_with(b"\x1b\\") && !reply.ends_with(b"\x07") {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `poll` points to one valid pollfd.
+        if left.is_zero() || unsafe { libc::poll(&mut poll, 1, left.as_millis() as i32) } <= 0 {
+            break;
+        }
+        let mut chunk = [0u8; 64];
+        // SAFETY: reads at most `chunk.len()` bytes into `chunk`.
+        let n = unsafe { libc::read(fd, chunk.as_mut_ptr().cast(), chunk.len()) };
+        if n <= 0 {
+            break;
+        }
+        reply.extend_from_slice(&chunk[..n as usize]);
+    }
+    (!reply.is_empty()).then(|| String::from_utf8_lossy(&reply).into_owned())
+}
+
+#[cfg(not(unix))]
+fn query_terminal(_request: &str) -> Option<String> {
+    None
+}
+
+// How pixels map onto terminal cells.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CellLayout {
+    // Two pixels stacked per cell with `▀`, for full resolution.
+    HalfBlock,
+    // One square pixel per pair of cells, background color only, for chunkier
+    // sprites on tiny terminals.
+    FullBlock,
+}
+
+impl CellLayout {
+    pub fn pixel_size(self, cols: u16, rows: u16) -> (usize, usize) {
+        match self {
+            CellLayout::HalfBlock => (cols as usize, rows as usize * 2),
+            CellLayout::FullBlock => (cols as usize / 2, rows as usize),
+        }
+    }
+
+    pub fn cell_size(self, w: usize, h: usize) -> (u16, u16) {
+        match self {
+            CellLayout::HalfBlock => (w as u16, (h / 2) as u16),
+            CellLayout::FullBlock => (w as u16 * 2, h as u16),
+        }
+    }
+
+    // The top-left pixel of a cell, for hit-testing the mouse.
+    pub fn cell_to_pixel(self, col: u16, row: u16) -> (i32, i32) {
+        match self {
+            CellLayout::HalfBlock => (col as i32, row as i32 * 2),
+            CellLayout::FullBlock => (col as i32 / 2, row as i32),
+        }
+    }
+}
+
+// ── Pixel buffer ────────────────────────────────────────────────────────────
+
+// Mirror transform applied to drawing calls; the game's world is drawn with
+// it set and the HUD without.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Flip {
+    pub x: bool,
+    pub y: bool,
+}
+
+// A frame of pixels, drawn into with (x, y) from the top left and rendered
+// as terminal cells. Drawing off the edges is clipped.
+#[derive(Clone)]
+pub struct PixelBuf {
+    pub(crate) w: usize,
+    pub(crate) h: usize, // pixel height = terminal rows * 2 (half blocks) or rows (full blocks)
+    pub(crate) px: Vec<Rgb>,
+    // Pixels from smooth fills, like gradients, that get ordered dithering in
+    // low-color modes.
+    pub(crate) dither: Vec<bool>,
+    pub color_mode: ColorMode,
+    pub layout: CellLayout,
+    pub flip: Flip,
+    pub effects: Vec<PostFx>,
+    // Plain text for the terminal row under the pixels, when the status bar is on.
+    pub status: Option<String>,
+    // Pixels saved by `cached`, with the key they were drawn for.
+    cache: Option<(Vec<u8>, Vec<Rgb>, Vec<bool>)>,
+    // Pixels of exactly this color are left in the terminal's own background.
+    pub transparent: Option<Rgb>,
+}
+
+impl PixelBuf {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self {
+            w,
+            h,
+            px: vec![BLACK; w * h],
+            dither: vec![false; w * h],
+            color_mode: ColorMode::TrueColor,
+            layout: CellLayout::HalfBlock,
+            flip: Flip::default(),
+            effects: Vec::new(),
+            status: None,
+            cache: None,
+            transparent: None,
+        }
+    }
+
+    // Runs `draw` once, then copies back what it left in the buffer for as
+    // long as `key` stays the same. Whatever is drawn afterwards must cover
+    // anything `draw` didn't.
+    pub fn cached(&mut self, key: Vec<u8>, draw: impl FnOnce(&mut PixelBuf)) {
+        if let Some((cached_key, px, dither)) = &self.cache
+            && *cached_key == key
+            && px.len() == self.px.len()
+        {
+            self.px.copy_from_slice(px);
+            self.dither.copy_from_slice(dither);
+            return;
+        }
+        draw(self);
+        self.cache = Some((key, self.px.clone(), self.dither.clone()));
+    }
+
+    pub fn resize(&mut self, w: usize, h: usize) {
+        self.w = w;
+        self.h = h;
+        self.px.resize(w * h, BLACK);
+        self.dither.resize(w * h, false);
+    }
+
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    // Buffer index for a drawing position, after the mirror transform.
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.w || y as usize >= self.h {
+            return None;
+        }
+        let (mut x, mut y) = (x as usize, y as usize);
+        if self.flip.x {
+            x = self.w - 1 - x;
+        }
+        if self.flip.y {
+            y = self.h - 1 - y;
+        }
+        Some(y * self.w + x)
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, c: Rgb) {
+        if let Some(i) = self.index(x, y) {
+            self.px[i] = c;
+            self.dither[i] = false;
+        }
+    }
+
+    // Mixes `c` over the current pixel; `alpha` is out of 256.
+    pub fn blend(&mut self, x: i32, y: i32, c: Rgb, alpha: u16) {
+        if let Some(i) = self.index(x, y) {
+            if alpha >= 256 {
+                self.px[i] = c;
+                self.dither[i] = false;
+            } else {
+                self.px[i] = Rgb::lerp(self.px[i], c, alpha);
+            }
+        }
+    }
+
+    // Sets each pixel in the given rows and columns that `shade` returns a
+    // color for. Big buffers fill their rows in parallel on rayon's pool,
+    // each row through a disjoint slice of `px`.
+    pub fn fill_rows(
+        &mut self,
+        rows: Range<i32>,
+        cols: Range<i32>,
+        dithered: bool,
+        shade: impl Fn(i32, i32) -> Option<Rgb> + Sync,
+    ) {
+        let (w, h, flip) = (self.w, self.h, self.flip);
+        if w == 0 || h == 0 {
+            return;
+        }
+        let fill_row = |(by, (px, dither)): (usize, (&mut [Rgb], &mut [bool]))| {
+            let y = if flip.y { h - 1 - by } else { by } as i32;
+            if !rows.contains(&y) {
+                return;
+            }
+            for bx in 0..w {
+                let x = if flip.x { w - 1 - bx } else { bx } as i32;
+                if !cols.contains(&x) {
+                    continue;
+                }
+                if let Some(c) = shade(x, y) {
+                    px[bx] = c;
+                    dither[bx] = dithered;
+                }
+            }
+        };
+
+        if self.px.len() < PARALLEL_MIN_PIXELS {
+            let rows = self.px.chunks_mut(w).zip(self.dither.chunks_mut(w));
+            rows.enumerate().for_each(fill_row);
+            return;
+        }
+        let rows = self.px.par_chunks_mut(w).zip(self.dither.par_chunks_mut(w));
+        rows.enumerate().for_each(fill_row);
+    }
+
+    // Reads the stored pixel, ignoring the mirror transform.
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        self.px[y * self.w + x]
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, c: Rgb) {
+        for dy in 0..h {
+            for dx in 0..w {
+                self.set(x + dx, y + dy, c);
+            }
+        }
+    }
+
+    // Fills the whole buffer, whatever the mirror transform.
+    pub fn clear(&mut self, c: Rgb) {
+        self.px.fill(c);
+        self.dither.fill(false);
+    }
+
+    // The one-pixel outline of the rectangle `fill_rect` would fill.
+    pub fn stroke_rect(&mut self, x: i32, y: i32, w: i32, h: i32, c: Rgb) {
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        self.fill_rect(x, y, w, 1, c);
+        self.fill_rect(x, y + h - 1, w, 1, c);
+        self.fill_rect(x, y + 1, 1, h - 2, c);
+        self.fill_rect(x + w - 1, y + 1, 1, h - 2, c);
+    }
+
+    // Bresenham line, both ends included.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, c: Rgb) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.set(x, y, c);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // Fills the pixels whose centers fall inside the ellipse.
+    pub fn fill_ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64, c: Rgb) {
+        if rx <= 0.0 || ry <= 0.0 {
+            return;
+        }
+        for y in (cy - ry).floor() as i32..=(cy + ry).ceil() as i32 {
+            let t = (y as f64 + 0.5 - cy) / ry;
+            if t.abs() > 1.0 {
+                continue;
+            }
+            let half = rx * (1.0 - t * t).sqrt();
+            let x0 = (cx - half - 0.5).ceil() as i32;
+            let x1 = (cx + half - 0.5).floor() as i32;
+            self.fill_rect(x0, y, x1 - x0 + 1, 1, c);
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: f64, cy: f64, r: f64, c: Rgb) {
+        self.fill_ellipse(cx, cy, r, r, c);
+    }
+
+    // Fills the pixels whose centers fall inside the triangle, either winding.
+    pub fn fill_triangle(&mut self, a: (f64, f64), b: (f64, f64), c: (f64, f64), color: Rgb) {
+        let edge = |p: (f64, f64), q: (f64, f64), x: f64, y: f64| {
+            (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0)
+        };
+        let area = edge(a, b, c.0, c.1);
+        if area == 0.0 {
+            return;
+        }
+        let x_min = a.0.min(b.0).min(c.0).floor() as i32;
+        let x_max = a.0.max(b.0).max(c.0).ceil() as i32;
+        let y_min = a.1.min(b.1).min(c.1).floor() as i32;
+        let y_max = a.1.max(b.1).max(c.1).ceil() as i32;
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                let w = [edge(b, c, px, py), edge(c, a, px, py), edge(a, b, px, py)];
+                if w.iter().all(|w| w * area >= 0.0) {
+                    self.set(x, y, color);
+                }
+            }
+        }
+    }
+
+    pub fn term_color(&self, x: usize, y: usize) -> TermColor {
+        let c = self.get(x, y);
+        if Some(c) == self.transparent {
+            return TermColor::Reset;
+        }
+        let dither = self.dither[y * self.w + x];
+        match self.color_mode {
+            ColorMode::TrueColor => TermColor::Rgb {
+                r: c.0,
+                g: c.1,
+                b: c.2,
+            },
+            ColorMode::Ansi256 => {
+                let c = if dither { bayer_offset(c, x, y, 48) } else { c };
+                TermColor::AnsiValue(ansi256_index(c))
+            }
+            ColorMode::Ansi16 => {
+                let c = if dither {
+                    bayer_offset(c, x, y, 128)
+                } else {
+                    c
+                };
+                ansi16_color(c)
+            }
+        }
+    }
+
+    // Runs the post-process passes over the finished frame.
+    pub fn apply_effects(&mut self) {
+        let effects = std::mem::take(&mut self.effects);
+        for fx in &effects {
+            fx.apply(self);
+        }
+        self.effects = effects;
+    }
+}
+
+// ── Low-color quantization ──────────────────────────────────────────────────
+
+#[rustfmt::skip]
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [ 0,  8,  2, 10],
+    [12,  4, 14,  6],
+    [ 3, 11,  1,  9],
+    [15,  7, 13,  5],
+];
+
+// Nudges each channel by the Bayer threshold at (x, y), scaled to roughly the
+// palette step, so nearest-color quantization alternates between neighbors.
+fn bayer_offset(c: Rgb, x: usize, y: usize, spread: i32) -> Rgb {
+    let t = BAYER_4X4[y % 4][x % 4] as i32;
+    let d = (t * 2 - 15) * spread / 32;
+    let ch = |v: u8| (v as i32 + d).clamp(0, 255) as u8;
+    Rgb(ch(c.0), ch(c.1), ch(c.2))
+}
+
+pub(crate) const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn color_dist(a: Rgb, b: Rgb) -> i32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+    d(a.0, b.0) * 3 + d(a.1, b.1) * 4 + d(a.2, b.2) * 2
+}
+
+fn ansi256_index(c: Rgb) -> u8 {
+    let level = |v: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (level(c.0), level(c.1), level(c.2));
+    let cube = Rgb(CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+
+    let avg = (c.0 as i32 + c.1 as i32 + c.2 as i32) / 3;
+    let gray_idx = ((avg - 8) / 10).clamp(0, 23);
+    let gv = (8 + gray_idx * 10) as u8;
+
+    if color_dist(c, Rgb(gv, gv, gv)) < color_dist(c, cube) {
+        232 + gray_idx as u8
+    } else {
+        16 + (36 * r + 6 * g + b) as u8
+    }
+}
+
+// xterm's default values for the 16 base colors, by palette index.
+pub(crate) const ANSI16: [Rgb; 16] = [
+    Rgb(0, 0, 0),
+    Rgb(205, 0, 0),
+    Rgb(0, 205, 0),
+    Rgb(205, 205, 0),
+    Rgb(0, 0, 238),
+    Rgb(205, 0, 205),
+    Rgb(0, 205, 205),
+    Rgb(229, 229, 229),
+    Rgb(127, 127, 127),
+    Rgb(255, 0, 0),
+    Rgb(0, 255, 0),
+    Rgb(255, 255, 0),
+    Rgb(92, 92, 255),
+    Rgb(255, 0, 255),
+    Rgb(0, 255, 255),
+    Rgb(255, 255, 255),
+];
+
+fn ansi16_color(c: Rgb) -> TermColor {
+    (0..ANSI16.len())
+        .min_by_key(|&i| color_dist(c, ANSI16[i]))
+        .map_or(TermColor::AnsiValue(15), |i| TermColor::AnsiValue(i as u8))
+}
+
+// ── Post-processing ─────────────────────────────────────────────────────────
+
+// Retro display passes, run over the finished frame by
+// `PixelBuf::apply_effects` in the order given.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PostFx {
+    Scanlines,
+    Vignette,
+    Bleed,
+}
+
+impl PostFx {
+    // A comma-separated list of effects by name, with `crt` for all three.
+    // Unknown names are skipped.
+    pub fn parse_list(list: &str) -> Vec<PostFx> {
+        let mut effects = Vec::new();
+        for name in list.split(',').map(str::trim) {
+            match name {
+                "scanlines" => effects.push(PostFx::Scanlines),
+                "vignette" => effects.push(PostFx::Vignette),
+                "bleed" => effects.push(PostFx::Bleed),
+                "crt" => effects.extend([PostFx::Bleed, PostFx::Scanlines, PostFx::Vignette]),
+                _ => {}
+            }
+        }
+        effects
+    }
+
+    fn apply(self, buf: &mut PixelBuf) {
+        match self {
+            PostFx::Scanlines => scanlines_pass(buf),
+            PostFx::Vignette => vignette_pass(buf),
+            PostFx::Bleed => bleed_pass(buf),
+        }
+    }
+}
+
+// Darkens every other pixel row.
+fn scanlines_pass(buf: &mut PixelBuf) {
+    for y in (1..buf.h).step_by(2) {
+        for px in &mut buf.px[y * buf.w..(y + 1) * buf.w] {
+            *px = Rgb::lerp(*px, BLACK, 56);
+        }
+    }
+}
+
+// Darkens toward the corners, quadratically with distance from the center.
+fn vignette_pass(buf: &mut PixelBuf) {
+    let (cx, cy) = (buf.w as f64 / 2.0, buf.h as f64 / 2.0);
+    let r2 = cx * cx + cy * cy;
+    for y in 0..buf.h {
+        for x in 0..buf.w {
+            let d2 = (x as f64 - cx).powi(2) + (y as f64 - cy).powi(2);
+            let i = y * buf.w + x;
+            buf.px[i] = Rgb::lerp(buf.px[i], BLACK, (d2 / r2 * 110.0) as u16);
+        }
+    }
+}
+
+// Smears red to the right and blue to the left, like a slightly misconverged CRT.
+fn bleed_pass(buf: &mut PixelBuf) {
+    for y in 0..buf.h {
+        let row = buf.px[y * buf.w..(y + 1) * buf.w].to_vec();
+        for x in 0..buf.w {
+            let left = row[x.saturating_sub(1)];
+            let right = row[(x + 1).min(buf.w - 1)];
+            let c = row[x];
+            let mix = |a: u8, b: u8| ((a as u16 * 5 + b as u16 * 3) / 8) as u8;
+            buf.px[y * buf.w + x] = Rgb(mix(c.0, left.0), c.1, mix(c.2, right.2));
+        }
+    }
+}
+
+// ── Renderers ───────────────────────────────────────────────────────────────
+
+// Puts finished frames on a screen. `CellRenderer` draws them as terminal
+// cells; other backends only need to implement this to be handed to the swap
+// chain, with whatever draws into the buffer none the wiser.
+pub trait Renderer: Send {
+    fn present(&mut self, buf: &PixelBuf) -> io::Result<()>;
+}
+
+// Draws frames as colored cells, two pixels to a cell or one
+// to two cells as the buffer's `CellLayout` says, and the status bar below.
+// Each frame is built up in memory and written out in one go.
+pub struct CellRenderer<W> {
+    out: W,
+    bytes: Vec<u8>,
+}
+
+impl<W: Write> CellRenderer<W> {
+    pub fn new(out: W) -> Self {
+        CellRenderer {
+            out,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write + Send> Renderer for CellRenderer<W> {
+    fn present(&mut self, buf: &PixelBuf) -> io::Result<()> {
+        self.bytes.clear();
+        render_cells(buf, &mut self.bytes)?;
+        self.out.write_all(&self.bytes)?;
+        self.out.flush()
+    }
+}
+
+// Draws nothing, for running with no screen at all.
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn present(&mut self, _buf: &PixelBuf) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn render_cells(buf: &PixelBuf, out: &mut impl Write) -> io::Result<()> {
+    if let Some(status) = &buf.status {
+        render_status(buf, status, out)?;
+    }
+    match buf.layout {
+        CellLayout::HalfBlock => render_half_blocks(buf, out),
+        CellLayout::FullBlock => render_full_blocks(buf, out),
+    }
+}
+
+// The status bar is drawn in the terminal's own colors, padded so it
+// overwrites the whole row.
+fn render_status(buf: &PixelBuf, status: &str, out: &mut impl Write) -> io::Result<()> {
+    let (cols, rows) = buf.layout.cell_size(buf.w, buf.h);
+    let text: String = status.chars().take(cols as usize).collect();
+    move_to(out, 0, rows)?;
+    write!(out, "{RESET_COLOR}{text:<width$}", width = cols as usize)
+}
+
+fn render_full_blocks(buf: &PixelBuf, out: &mut impl Write) -> io::Result<()> {
+    move_to(out, 0, 0)?;
+    for row in 0..buf.h {
+        let mut prev_bg = None;
+        for col in 0..buf.w {
+            let c = buf.term_color(col, row);
+            if prev_bg != Some(c) {
+                c.write_sgr(out, BACKGROUND)?;
+                prev_bg = Some(c);
+            }
+            out.write_all(b"  ")?;
+        }
+        if row < buf.h - 1 {
+            write!(out, "{RESET_COLOR}\r\n")?;
+        }
+    }
+    write!(out, "{RESET_COLOR}")
+}
+
+fn render_half_blocks(buf: &PixelBuf, out: &mut impl Write) -> io::Result<()> {
+    move_to(out, 0, 0)?;
+    let rows = buf.h / 2;
+    let mut prev_fg = TermColor::Reset;
+    let mut prev_bg = TermColor::Reset;
+    let mut need_fg = true;
+    let mut need_bg = true;
+
+    for row in 0..rows {
+        for col in 0..buf.w {
+            let top = buf.term_color(col, row * 2);
+            let bot = buf.term_color(col, row * 2 + 1);
+
+            if top == bot {
+                if need_bg || prev_bg != top {
+                    top.write_sgr(out, BACKGROUND)?;
+                    prev_bg = top;
+                    need_bg = false;
+                }
+                out.write_all(b" ")?;
+            } else {
+                // A see-through top half has to be the cell's background,
+                // so the bottom half is drawn as the glyph instead.
+                let (fg, bg, glyph) = if top == TermColor::Reset {
+                    (bot, top, '\u{2584}') // ▄
+                } else {
+                    (top, bot, '\u{2580}') // ▀
+                };
+                if need_fg || prev_fg != fg {
+                    fg.write_sgr(out, FOREGROUND)?;
+                    prev_fg = fg;
+                    need_fg = false;
+                }
+                if need_bg || prev_bg != bg {
+                    bg.write_sgr(out, BACKGROUND)?;
+                    prev_bg = bg;
+                    need_bg = false;
+                }
+                write!(out, "{glyph}")?;
+            }
+        }
+        if row < rows - 1 {
+            write!(out, "{RESET_COLOR}\r\n")?;
+            need_fg = true;
+            need_bg = true;
+        }
+    }
+    write!(out, "{RESET_COLOR}")
+}
+
+// The escape sequences cells are drawn with, written out by hand like the
+// queries above, as crossterm doesn't build for the web.
+const RESET_COLOR: &str = "\x1b[0m";
+const FOREGROUND: u8 = 38;
+const BACKGROUND: u8 = 48;
+
+fn move_to(out: &mut impl Write, col: u16, row: u16) -> io::Result<()> {
+    write!(out, "\x1b[{};{}H", row + 1, col + 1)
+}
+
+impl TermColor {
+    // Selects the color for the foreground or background, by its SGR code.
+    fn write_sgr(self, out: &mut impl Write, layer: u8) -> io::Result<()> {
+        match self {
+            TermColor::Reset => write!(out, "\x1b[{}m", layer + 1),
+            TermColor::AnsiValue(n) => write!(out, "\x1b[{layer};5;{n}m"),
+            TermColor::Rgb { r, g, b } => write!(out, "\x1b[{layer};2;{r};{g};{b}m"),
+        }
+    }
+}
+
+// ── Swap chain ──────────────────────────────────────────────────────────────
+
+// Frames are handed to the renderers on a writer thread, so drawing, input
+// and the simulation never wait on the terminal. Three buffers take turns:
+// one being drawn, one being written, and the newest finished frame waiting
+// in between. A frame still waiting when the next is finished is dropped for
+// it, so a slow terminal shows fewer frames rather than falling behind.
+pub struct SwapChain {
+    pub back: PixelBuf,
+    mailbox: Arc<(Mutex<Mailbox>, Condvar)>,
+    writer: Option<std::thread::JoinHandle<io::Result<()>>>,
+    // Frames presented over the last whole second, and the count so far.
+    pub fps: u32,
+    counted: (u32, Instant),
+}
+
+#[derive(Default)]
+struct Mailbox {
+    // The newest finished frame, not picked up by the writer yet.
+    frame: Option<PixelBuf>,
+    // Buffers free to draw the next frame into.
+    spare: Vec<PixelBuf>,
+    closed: bool,
+}
+
+impl SwapChain {
+    pub fn new(buf: PixelBuf, mut renderers: Vec<Box<dyn Renderer>>) -> Self {
+        let mailbox = Arc::new((
+            Mutex::new(Mailbox {
+                spare: vec![buf.clone(), buf.clone()],
+                ..Mailbox::default()
+            }),
+            Condvar::new(),
+        ));
+        let shared = mailbox.clone();
+        let writer = std::thread::spawn(move || {
+            let (lock, ready) = &*shared;
+            // The drawing side only ever holds the lock to swap buffers, so it
+            // can't be poisoned while that's still running.
+            let Ok(mut mailbox) = lock.lock() else {
+                return Ok(());
+            };
+            loop {
+                let Some(mut frame) = mailbox.frame.take() else {
+                    if mailbox.closed {
+                        return Ok(());
+                    }
+                    let Ok(woken) = ready.wait(mailbox) else {
+                        return Ok(());
+                    };
+                    mailbox = woken;
+                    continue;
+                };
+                drop(mailbox);
+                frame.apply_effects();
+                for renderer in &mut renderers {
+                    renderer.present(&frame)?;
+                }
+                let Ok(relocked) = lock.lock() else {
+                    return Ok(());
+                };
+                mailbox = relocked;
+                mailbox.spare.push(frame);
+            }
+        });
+        Self {
+            back: buf,
+            mailbox,
+            writer: Some(writer),
+            fps: 0,
+            counted: (0, Instant::now()),
+        }
+    }
+
+    // Hands the drawn frame to the writer and takes a free buffer back,
+    // without waiting for the terminal.
+    pub fn present(&mut self) -> io::Result<()> {
+        self.counted.0 += 1;
+        let elapsed = self.counted.1.elapsed().as_secs_f64();
+        if elapsed >= 1.0 {
+            self.fps = (self.counted.0 as f64 / elapsed).round() as u32;
+            self.counted = (0, Instant::now());
+        }
+        if self.writer.as_ref().is_none_or(|w| w.is_finished()) {
+            let stopped = io::Error::other("terminal writer stopped");
+            return self.finish().and(Err(stopped));
+        }
+        let (w, h) = (self.back.w, self.back.h);
+        {
+            let (lock, ready) = &*self.mailbox;
+            let mut mailbox = lock
+                .lock()
+                .map_err(|_| io::Error::other("terminal writer panicked"))?;
+            // A frame the writer never got to is drawn over as the next one.
+            let next = match mailbox.frame.take() {
+                Some(stale) => stale,
+                None => mailbox.spare.pop().unwrap_or_else(|| self.back.clone()),
+            };
+            mailbox.frame = Some(std::mem::replace(&mut self.back, next));
+            ready.notify_one();
+        }
+        // The terminal may have been resized while this one was out.
+        if (self.back.w, self.back.h) != (w, h) {
+            self.back.resize(w, h);
+        }
+        Ok(())
+    }
+
+    // Waits for the last frame presented to be written, then stops the
+    // writer.
+    pub fn finish(&mut self) -> io::Result<()> {
+        let (lock, ready) = &*self.mailbox;
+        if let Ok(mut mailbox) = lock.lock() {
+            mailbox.closed = true;
+        }
+        ready.notify_one();
+        match self.writer.take() {
+            Some(writer) => writer
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("terminal writer panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+// ── Sprites ─────────────────────────────────────────────────────────────────
+
+// A small image with transparent pixels, drawn with `PixelBuf::blit`.
+pub struct Sprite {
+    w: usize,
+    h: usize,
+    px: Vec<Option<Rgb>>,
+}
+
+impl Sprite {
+    // Builds a sprite from rows of characters looked up in `palette`; any
+    // character not in the palette (by convention '.') is transparent.
+    pub fn from_art(rows: &[&str], palette: &[(char, Rgb)]) -> Sprite {
+        let w = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut px = vec![None; w * rows.len()];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                px[y * w + x] = palette.iter().find(|(k, _)| *k == ch).map(|(_, c)| *c);
+            }
+        }
+        Sprite {
+            w,
+            h: rows.len(),
+            px,
+        }
+    }
+}
+
+impl PixelBuf {
+    // Draws `sprite` with its top-left corner at (x, y), scaled by nearest
+    // neighbour and optionally mirrored left to right.
+    pub fn blit(&mut self, sprite: &Sprite, x: i32, y: i32, scale: f64, flip_x: bool) {
+        if sprite.w == 0 || sprite.h == 0 {
+            return;
+        }
+        let dw = ((sprite.w as f64 * scale).round() as i32).max(1);
+        let dh = ((sprite.h as f64 * scale).round() as i32).max(1);
+        for dy in 0..dh {
+            let sy = ((dy as f64 / scale) as usize).min(sprite.h - 1);
+            for dx in 0..dw {
+                let mut sx = ((dx as f64 / scale) as usize).min(sprite.w - 1);
+                if flip_x {
+                    sx = sprite.w - 1 - sx;
+                }
+                if let Some(c) = sprite.px[sy * sprite.w + sx] {
+                    self.set(x + dx, y + dy, c);
+                }
+            }
+        }
+    }
+}
+
+impl PixelBuf {
+    // Like `blit` (without mirroring) at a fractional y: where the sprite's top
+    // or bottom edge falls partway through a pixel, it's blended by coverage,
+    // so slow vertical motion doesn't step a whole pixel at a time.
+    pub fn blit_subpixel(&mut self, sprite: &Sprite, x: i32, y: f64, scale: f64) {
+        if sprite.w == 0 || sprite.h == 0 {
+            return;
+        }
+        let dw = ((sprite.w as f64 * scale).round() as i32).max(1);
+        let dh = ((sprite.h as f64 * scale).round() as i32).max(1);
+        let sample = |dx: i32, dy: i32| {
+            if dy < 0 || dy >= dh {
+                return None;
+            }
+            let sx = ((dx as f64 / scale) as usize).min(sprite.w - 1);
+            let sy = ((dy as f64 / scale) as usize).min(sprite.h - 1);
+            sprite.px[sy * sprite.w + sx]
+        };
+        let y0 = y.floor() as i32;
+        let f = y - y0 as f64;
+        for dy in 0..=dh {
+            for dx in 0..dw {
+                // This pixel holds 1 - f of sprite row dy and f of the row above.
+                match (sample(dx, dy - 1), sample(dx, dy)) {
+                    (Some(above), Some(here)) => {
+                        self.set(x + dx, y0 + dy, if f < 0.5 { here } else { above })
+                    }
+                    (Some(above), None) => self.blend(x + dx, y0 + dy, above, (f * 256.0) as u16),
+                    (None, Some(here)) => {
+                        self.blend(x + dx, y0 + dy, here, ((1.0 - f) * 256.0) as u16)
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+}
+
+// ── 4x6 bitmap font ─────────────────────────────────────────────────────────
+
+/// 4x6 pixel font covering ASCII 32–127 (from font4x6.cpp).
+/// Each entry is 6 bytes (one per row), with the top 4 bits encoding the 4 columns.
+const FONT_4X6: [[u8; 6]; 96] = [
+    // 32 ' '
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    // 33 '!'
+    [0x40, 0x40, 0x40, 0x00, 0x40, 0x00],
+    // 34 '"'
+    [0xA0, 0xA0, 0x00, 0x00, 0x00, 0x00],
+    // 35 '#'
+    [0xA0, 0xE0, 0xA0, 0xE0, 0xA0, 0x00],
+    // 36 '$'
+    [0xE0, 0xC0, 0x60, 0xE0, 0x40, 0x00],
+    // 37 '%'
+    [0xA0, 0x20, 0x40, 0x80, 0xA0, 0x00],
+    // 38 '&'
+    [0xC0, 0xC0, 0x00, 0xE0, 0xE0, 0x00],
+    // 39 '\''
+    [0x20, 0x40, 0x00, 0x00, 0x00, 0x00],
+    // 40 '('
+    [0x20, 0x40, 0x40, 0x40, 0x20, 0x00],
+    // 41 ')'
+    [0x80, 0x40, 0x40, 0x40, 0x80, 0x00],
+    // 42 '*'
+    [0x00, 0xA0, 0x40, 0xA0, 0x00, 0x00],
+    // 43 '+'
+    [0x00, 0x40, 0xE0, 0x40, 0x00, 0x00],
+    // 44 ','
+    [0x00, 0x00, 0x00, 0x00, 0x40, 0x40],
+    // 45 '-'
+    [0x00, 0x00, 0xE0, 0x00, 0x00, 0x00],
+    // 46 '.'
+    [0x00, 0x00, 0x00, 0x00, 0x40, 0x00],
+    // 47 '/'
+    [0x20, 0x40, 0x40, 0x40, 0x80, 0x00],
+    // 48 '0'
+    [0x40, 0xA0, 0xA0, 0xA0, 0x40, 0x00],
+    // 49 '1'
+    [0x40, 0xC0, 0x40, 0x40, 0x40, 0x00],
+    // 50 '2'
+    [0x40, 0xA0, 0x20, 0x40, 0xE0, 0x00],
+    // 51 '3'
+    [0xC0, 0x20, 0xC0, 0x20, 0xC0, 0x00],
+    // 52 '4'
+    [0x80, 0xA0, 0xE0, 0x20, 0x20, 0x00],
+    // 53 '5'
+    [0xE0, 0x80, 0x60, 0x20, 0xE0, 0x00],
+    // 54 '6'
+    [0x60, 0x80, 0xE0, 0xA0, 0xC0, 0x00],
+    // 55 '7'
+    [0xE0, 0x20, 0x40, 0x40, 0x40, 0x00],
+    // 56 '8'
+    [0x40, 0xA0, 0x40, 0xA0, 0x40, 0x00],
+    // 57 '9'
+    [0x60, 0xA0, 0xE0, 0x20, 0x40, 0x00],
+    // 58 ':'
+    [0x00, 0x40, 0x00, 0x00, 0x40, 0x00],
+    // 59 ';'
+    [0x00, 0x40, 0x00, 0x00, 0x40, 0x40],
+    // 60 '<'
+    [0x20, 0x40, 0x80, 0x40, 0x20, 0x00],
+    // 61 '='
+    [0x00, 0xE0, 0x00, 0xE0, 0x00, 0x00],
+    // 62 '>'
+    [0x80, 0x40, 0x20, 0x40, 0x80, 0x00],
+    // 63 '?'
+    [0xE0, 0x20, 0x40, 0x00, 0x40, 0x00],
+    // 64 '@'
+    [0x40, 0xA0, 0xA0, 0x80, 0x60, 0x00],
+    // 65 'A'
+    [0x40, 0xA0, 0xA0, 0xE0, 0xA0, 0x00],
+    // 66 'B'
+    [0xC0, 0xA0, 0xC0, 0xA0, 0xC0, 0x00],
+    // 67 'C'
+    [0x40, 0xA0, 0x80, 0xA0, 0x40, 0x00],
+    // 68 'D'
+    [0xC0, 0xA0, 0xA0, 0xA0, 0xC0, 0x00],
+    // 69 'E'
+    [0xE0, 0x80, 0xC0, 0x80, 0xE0, 0x00],
+    // 70 'F'
+    [0xE0, 0x80, 0xE0, 0x80, 0x80, 0x00],
+    // 71 'G'
+    [0x60, 0x80, 0x80, 0xA0, 0x60, 0x00],
+    // 72 'H'
+    [0xA0, 0xA0, 0xE0, 0xA0, 0xA0, 0x00],
+    // 73 'I'
+    [0xE0, 0x40, 0x40, 0x40, 0xE0, 0x00],
+    // 74 'J'
+    [0xE0, 0x20, 0x20, 0xA0, 0x40, 0x00],
+    // 75 'K'
+    [0xA0, 0xA0, 0xC0, 0xA0, 0xA0, 0x00],
+    // 76 'L'
+    [0x80, 0x80, 0x80, 0x80, 0xE0, 0x00],
+    // 77 'M'
+    [0xA0, 0xE0, 0xE0, 0xA0, 0xA0, 0x00],
+    // 78 'N'
+    [0xC0, 0xA0, 0xA0, 0xA0, 0xA0, 0x00],
+    // 79 'O'
+    [0x40, 0xA0, 0xA0, 0xA0, 0x40, 0x00],
+    // 80 'P'
+    [0xC0, 0xA0, 0xC0, 0x80, 0x80, 0x00],
+    // 81 'Q'
+    [0x40, 0xA0, 0xA0, 0xA0, 0x40, 0x20],
+    // 82 'R'
+    [0xC0, 0xA0, 0xC0, 0xA0, 0xA0, 0x00],
+    // 83 'S'
+    [0x60, 0x80, 0x40, 0x20, 0xC0, 0x00],
+    // 84 'T'
+    [0xE0, 0x40, 0x40, 0x40, 0x40, 0x00],
+    // 85 'U'
+    [0xA0, 0xA0, 0xA0, 0xA0, 0xE0, 0x00],
+    // 86 'V'
+    [0xA0, 0xA0, 0xA0, 0xA0, 0x40, 0x00],
+    // 87 'W'
+    [0xA0, 0xA0, 0xE0, 0xE0, 0xA0, 0x00],
+    // 88 'X'
+    [0xA0, 0xA0, 0x40, 0xA0, 0xA0, 0x00],
+    // 89 'Y'
+    [0xA0, 0xA0, 0xE0, 0x40, 0x40, 0x00],
+    // 90 'Z'
+    [0xE0, 0x20, 0x40, 0x80, 0xE0, 0x00],
+    // 91 '['
+    [0x60, 0x40, 0x40, 0x40, 0x60, 0x00],
+    // 92 '\\'
+    [0x80, 0x80, 0x40, 0x20, 0x20, 0x00],
+    // 93 ']'
+    [0x60, 0x20, 0x20, 0x20, 0x60, 0x00],
+    // 94 '^'
+    [0x00, 0x40, 0xA0, 0x00, 0x00, 0x00],
+    // 95 '_'
+    [0x00, 0x00, 0x00, 0x00, 0xE0, 0x00],
+    // 96 '`'
+    [0x00, 0x40, 0x20, 0x00, 0x00, 0x00],
+    // 97 'a'
+    [0x00, 0x60, 0xA0, 0xA0, 0x60, 0x00],
+    // 98 'b'
+    [0x80, 0xC0, 0xA0, 0xA0, 0x40, 0x00],
+    // 99 'c'
+    [0x00, 0x60, 0x80, 0x80, 0x60, 0x00],
+    // 100 'd'
+    [0x20, 0x60, 0xA0, 0xA0, 0x40, 0x00],
+    // 101 'e'
+    [0x00, 0x60, 0xE0, 0x80, 0xE0, 0x00],
+    // 102 'f'
+    [0x40, 0xA0, 0x80, 0xC0, 0x80, 0x00],
+    // 103 'g'
+    [0x00, 0x40, 0xA0, 0x40, 0x20, 0x40],
+    // 104 'h'
+    [0x80, 0xC0, 0xA0, 0xA0, 0xA0, 0x00],
+    // 105 'i'
+    [0x40, 0x00, 0x40, 0x40, 0x40, 0x00],
+    // 106 'j'
+    [0x40, 0x00, 0x40, 0x40, 0x40, 0x80],
+    // 107 'k'
+    [0x80, 0xA0, 0xA0, 0xC0, 0xA0, 0x00],
+    // 108 'l'
+    [0xC0, 0x40, 0x40, 0x40, 0x40, 0x00],
+    // 109 'm'
+    [0x00, 0xE0, 0xE0, 0xA0, 0xA0, 0x00],
+    // 110 'n'
+    [0x00, 0xC0, 0xA0, 0xA0, 0xA0, 0x00],
+    // 111 'o'
+    [0x00, 0x40, 0xA0, 0xA0, 0x40, 0x00],
+    // 112 'p'
+    [0x00, 0xC0, 0xA0, 0xA0, 0xC0, 0x80],
+    // 113 'q'
+    [0x00, 0x60, 0xA0, 0xA0, 0x60, 0x20],
+    // 114 'r'
+    [0x00, 0x60, 0x80, 0x80, 0x80, 0x00],
+    // 115 's'
+    [0x00, 0x60, 0x80, 0x20, 0xC0, 0x00],
+    // 116 't'
+    [0x40, 0xE0, 0x40, 0x40, 0x40, 0x00],
+    // 117 'u'
+    [0x00, 0xA0, 0xA0, 0xA0, 0x60, 0x00],
+    // 118 'v'
+    [0x00, 0xA0, 0xA0, 0xA0, 0x40, 0x00],
+    // 119 'w'
+    [0x00, 0xA0, 0xA0, 0xE0, 0xE0, 0x00],
+    // 120 'x'
+    [0x00, 0xA0, 0x40, 0x40, 0xA0, 0x00],
+    // 121 'y'
+    [0x00, 0xA0, 0xA0, 0x60, 0x20, 0x40],
+    // 122 'z'
+    [0x00, 0xE0, 0x20, 0x80, 0xE0, 0x00],
+    // 123 '{'
+    [0x20, 0x40, 0xC0, 0x40, 0x20, 0x00],
+    // 124 '|'
+    [0x40, 0x40, 0x40, 0x40, 0x40, 0x00],
+    // 125 '}'
+    [0x80, 0x40, 0x60, 0x40, 0x80, 0x00],
+    // 126 '~'
+    [0x00, 0x50, 0xA0, 0x00, 0x00, 0x00],
+    // 127 DEL (blank)
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+fn glyph_4x6(ch: char) -> [u8; 6] {
+    let code = ch as u32;
+    if (32..128).contains(&code) {
+        FONT_4X6[(code - 32) as usize]
+    } else {
+        [0; 6]
+    }
+}
+
+pub fn text_width_4x6(text: &str, scale: i32) -> i32 {
+    if text.is_empty() {
+        0
+    } else {
+        (text.chars().count() as i32 * 5 - 1) * scale.max(1)
+    }
+}
+
+pub fn draw_text_4x6(buf: &mut PixelBuf, x: i32, y: i32, text: &str, color: Rgb, scale: i32) {
+    let s = scale.max(1);
+    let mut cursor_x = x;
+
+    for ch in text.chars() {
+        let rows = glyph_4x6(ch);
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..4 {
+                if ((bits >> (7 - col)) & 1) == 1 {
+                    buf.fill_rect(cursor_x + col * s, y + row as i32 * s, s, s, color);
+                }
+            }
+        }
+        cursor_x += 5 * s;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgb = Rgb(255, 255, 255);
+
+    fn cells(buf: &PixelBuf) -> String {
+        let mut out = Vec::new();
+        render_cells(buf, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn filling_rows_shades_only_the_asked_columns() {
+        let (w, h) = (200, 100);
+        assert!(w * h >= PARALLEL_MIN_PIXELS);
+        let mut buf = PixelBuf::new(w, h);
+        buf.flip.x = true;
+        buf.fill_rows(10..90, 50..150, false, |x, _| {
+            assert!((50..150).contains(&x));
+            Some(WHITE)
+        });
+        assert!(buf.get(w - 1 - 50, 10) == WHITE);
+        assert!(buf.get(w - 1 - 49, 10) != WHITE);
+        assert!(buf.get(w - 1 - 50, 90) != WHITE);
+    }
+
+    #[test]
+    fn lerp_hits_both_ends() {
+        let (a, b) = (Rgb(0, 100, 200), Rgb(255, 50, 0));
+        assert!(Rgb::lerp(a, b, 0) == a);
+        assert!(Rgb::lerp(a, b, 256) == b);
+        assert!(Rgb::lerp_linear(a, b, 0) == a);
+        assert!(Rgb::lerp_linear(a, b, 256) == b);
+    }
+
+    #[test]
+    fn linear_mix_is_brighter_than_srgb_mix() {
+        let (black, white) = (Rgb(0, 0, 0), WHITE);
+        assert!(Rgb::lerp_linear(black, white, 128).0 > Rgb::lerp(black, white, 128).0);
+    }
+
+    #[test]
+    fn set_ignores_pixels_off_the_buffer() {
+        let mut buf = PixelBuf::new(4, 4);
+        buf.set(-1, 0, WHITE);
+        buf.set(0, 4, WHITE);
+        buf.set(2, 1, WHITE);
+        let lit = buf.px.iter().filter(|c| **c == WHITE).count();
+        assert_eq!(lit, 1);
+        assert!(buf.get(2, 1) == WHITE);
+    }
+
+    #[test]
+    fn ansi256_picks_exact_cube_and_gray_entries() {
+        assert_eq!(ansi256_index(Rgb(255, 0, 0)), 196);
+        assert_eq!(ansi256_index(Rgb(0, 0, 0)), 16);
+        assert_eq!(ansi256_index(Rgb(128, 128, 128)), 244);
+    }
+
+    #[test]
+    fn layouts_convert_between_cells_and_pixels() {
+        assert_eq!(CellLayout::HalfBlock.pixel_size(80, 24), (80, 48));
+        assert_eq!(CellLayout::FullBlock.pixel_size(80, 24), (40, 24));
+        for layout in [CellLayout::HalfBlock, CellLayout::FullBlock] {
+            let (w, h) = layout.pixel_size(80, 24);
+            assert_eq!(layout.cell_size(w, h), (80, 24));
+        }
+    }
+
+    #[test]
+    fn background_replies_scale_each_channel_to_a_byte() {
+        let bg = parse_background("\x1b]11;rgb:ffff/8080/00\x1b\\");
+        assert!(bg == Some(Rgb(255, 128, 0)));
+        assert!(parse_background("\x1b]11;rgb:f/8/0\x07") == Some(Rgb(255, 136, 0)));
+        assert!(parse_background("\x1b]11;rgb:ffffffff/0/0\x07").is_none());
+        assert!(parse_background("\x1b]11;rgb:ff//00\x07").is_none());
+    }
+
+    #[test]
+    fn half_blocks_draw_split_cells_as_glyphs() {
+        let mut buf = PixelBuf::new(2, 2);
+        assert!(!cells(&buf).contains('\u{2580}'));
+        buf.set(0, 0, WHITE);
+        let out = cells(&buf);
+        assert_eq!(out.matches('\u{2580}').count(), 1);
+        assert!(out.contains("38;2;255;255;255"));
+    }
+
+    #[test]
+    fn status_bar_is_padded_to_the_width() {
+        let mut buf = PixelBuf::new(10, 4);
+        buf.status = Some("hi".to_string());
+        assert!(cells(&buf).contains("hi        "));
+    }
+
+    #[test]
+    fn text_width_counts_glyphs_and_gaps() {
+        assert_eq!(text_width_4x6("", 1), 0);
+        assert_eq!(text_width_4x6("A", 1), 4);
+        assert_eq!(text_width_4x6("AB", 2), 18);
+    }
+
+    #[test]
+    fn stroked_rects_leave_the_inside_alone() {
+        let mut buf = PixelBuf::new(6, 6);
+        buf.stroke_rect(1, 1, 4, 3, WHITE);
+        let lit = buf.px.iter().filter(|c| **c == WHITE).count();
+        assert_eq!(lit, 10);
+        assert!(buf.get(2, 2) == BLACK);
+        assert!(buf.get(4, 3) == WHITE);
+    }
+
+    // Takes a while over each frame, noting the color of its corner.
+    struct SlowRenderer(Arc<Mutex<Vec<Rgb>>>);
+
+    impl Renderer for SlowRenderer {
+        fn present(&mut self, buf: &PixelBuf) -> io::Result<()> {
+            std::thread::sleep(std::time::Duration::from_millis(40));
+            self.0.lock().unwrap().push(buf.get(0, 0));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_slow_renderer_drops_frames_instead_of_holding_up_the_game() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let renderer = Box::new(SlowRenderer(seen.clone()));
+        let mut chain = SwapChain::new(PixelBuf::new(4, 4), vec![renderer]);
+        let start = Instant::now();
+        for i in 1..=10 {
+            chain.back.fill_rect(0, 0, 4, 4, Rgb(i, 0, 0));
+            chain.present().unwrap();
+        }
+        assert!(start.elapsed() < std::time::Duration::from_millis(200));
+        chain.finish().unwrap();
+        let seen = seen.lock().unwrap();
+        assert!(seen.len() < 10);
+        assert!(seen.last() == Some(&Rgb(10, 0, 0)));
+    }
+}
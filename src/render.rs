@@ -0,0 +1,302 @@
+//! The game's side of rendering, on top of `pixels`: its palette, the
+//! FLAPPY_* settings for colors, layout and effects, fog, and the HUD's
+//! medals, digits and logo.
+
+use std::ops::Range;
+
+use crate::game::{FOG_DENSITY, MIN_ROWS, MIN_ROWS_FULL_BLOCKS};
+use crate::pixels::{CellLayout, ColorMode, PixelBuf, PostFx, Rgb};
+
+// ── Palette ─────────────────────────────────────────────────────────────────
+
+pub(crate) const SKY_TOP: Rgb = Rgb(70, 180, 200);
+pub(crate) const SKY_BOT: Rgb = Rgb(190, 232, 245);
+pub(crate) const GRASS: Rgb = Rgb(84, 168, 55);
+pub(crate) const GRASS_LIGHT: Rgb = Rgb(110, 200, 70);
+pub(crate) const DIRT: Rgb = Rgb(210, 185, 110);
+pub(crate) const DIRT_DARK: Rgb = Rgb(185, 160, 90);
+pub(crate) const PIPE_L: Rgb = Rgb(74, 122, 26);
+pub(crate) const PIPE_M: Rgb = Rgb(100, 170, 40);
+pub(crate) const PIPE_R: Rgb = Rgb(115, 191, 46);
+pub(crate) const PIPE_HI: Rgb = Rgb(145, 215, 62);
+pub(crate) const CAP_DARK: Rgb = Rgb(60, 100, 20);
+pub(crate) const BIRD_Y: Rgb = Rgb(245, 200, 66);
+pub(crate) const BIRD_HI: Rgb = Rgb(255, 225, 100);
+pub(crate) const BIRD_WING: Rgb = Rgb(215, 165, 35);
+pub(crate) const BIRD_EYE: Rgb = Rgb(255, 255, 255);
+pub(crate) const BIRD_PUPIL: Rgb = Rgb(20, 20, 20);
+pub(crate) const BIRD_BEAK: Rgb = Rgb(225, 75, 35);
+pub(crate) const BIRD_BEAK_HI: Rgb = Rgb(240, 110, 50);
+pub(crate) const HILL_FAR: Rgb = Rgb(120, 195, 75);
+pub(crate) const HILL_NEAR: Rgb = Rgb(95, 175, 55);
+pub(crate) const ROCK: Rgb = Rgb(140, 140, 135);
+pub(crate) const ROCK_HI: Rgb = Rgb(175, 175, 168);
+pub(crate) const SIGN: Rgb = Rgb(190, 140, 80);
+pub(crate) const SIGN_DARK: Rgb = Rgb(120, 85, 45);
+pub(crate) const CAVE_SKY: [Rgb; 3] = [Rgb(20, 18, 34), Rgb(44, 36, 62), Rgb(70, 58, 84)];
+pub(crate) const FOG: Rgb = Rgb(205, 210, 215);
+pub(crate) const WHITE: Rgb = Rgb(255, 255, 255);
+pub(crate) const SHADOW: Rgb = Rgb(30, 30, 30);
+
+// ── Terminal settings ───────────────────────────────────────────────────────
+
+impl ColorMode {
+    fn from_env() -> ColorMode {
+        match std::env::var("FLAPPY_COLORS").as_deref() {
+            Ok("256") => ColorMode::Ansi256,
+            Ok("16") => ColorMode::Ansi16,
+            _ => ColorMode::TrueColor,
+        }
+    }
+
+    // `from_env`, or with FLAPPY_COLORS unset, what `probe` finds. Also
+    // returns whether that fell back to 256 colors.
+    pub fn detect() -> (ColorMode, bool) {
+        if std::env::var_os("FLAPPY_COLORS").is_some() {
+            return (ColorMode::from_env(), false);
+        }
+        ColorMode::probe()
+    }
+}
+
+impl CellLayout {
+    pub fn from_env() -> CellLayout {
+        match std::env::var("FLAPPY_FULL_BLOCKS").as_deref() {
+            Ok("1") => CellLayout::FullBlock,
+            _ => CellLayout::HalfBlock,
+        }
+    }
+
+    pub fn min_rows(self) -> u16 {
+        match self {
+            CellLayout::HalfBlock => MIN_ROWS,
+            CellLayout::FullBlock => MIN_ROWS_FULL_BLOCKS,
+        }
+    }
+}
+
+// ── Post-processing ─────────────────────────────────────────────────────────
+
+// Blends the finished frame toward `color` with distance from (cx, cy): clear
+// inside `radius`, easing into FOG_DENSITY over the next `falloff` pixels.
+pub(crate) fn fog_pass(
+    buf: &mut PixelBuf,
+    cx: f64,
+    cy: f64,
+    radius: f64,
+    falloff: f64,
+    color: Rgb,
+) {
+    for y in 0..buf.h {
+        for x in 0..buf.w {
+            let d = ((x as f64 - cx).powi(2) + (y as f64 - cy).powi(2)).sqrt();
+            let t = ((d - radius) / falloff).clamp(0.0, 1.0);
+            if t > 0.0 {
+                let t = t * t * (3.0 - 2.0 * t);
+                buf.blend(x as i32, y as i32, color, (t * FOG_DENSITY) as u16);
+            }
+        }
+    }
+}
+
+// Retro display passes from FLAPPY_EFFECTS.
+impl PostFx {
+    pub fn from_env() -> Vec<PostFx> {
+        std::env::var("FLAPPY_EFFECTS")
+            .map_or_else(|_| Vec::new(), |list| PostFx::parse_list(&list))
+    }
+}
+
+// ── Medals ──────────────────────────────────────────────────────────────────
+
+#[rustfmt::skip]
+pub(crate) const MEDAL_ART: [&str; 6] = [
+    ".MMMM.",
+    "MMMMMM",
+    "MMhhMM",
+    "MMhhMM",
+    "MMMMMM",
+    ".MMMM.",
+];
+
+// ── 3x5 bitmap digits ──────────────────────────────────────────────────────
+
+#[rustfmt::skip]
+const DIGITS: [[u8; 15]; 10] = [
+    [1,1,1, 1,0,1, 1,0,1, 1,0,1, 1,1,1], // 0
+    [0,1,0, 1,1,0, 0,1,0, 0,1,0, 1,1,1], // 1
+    [1,1,1, 0,0,1, 1,1,1, 1,0,0, 1,1,1], // 2
+    [1,1,1, 0,0,1, 0,1,1, 0,0,1, 1,1,1], // 3
+    [1,0,1, 1,0,1, 1,1,1, 0,0,1, 0,0,1], // 4
+    [1,1,1, 1,0,0, 1,1,1, 0,0,1, 1,1,1], // 5
+    [1,1,1, 1,0,0, 1,1,1, 1,0,1, 1,1,1], // 6
+    [1,1,1, 0,0,1, 0,1,0, 0,1,0, 0,1,0], // 7
+    [1,1,1, 1,0,1, 1,1,1, 1,0,1, 1,1,1], // 8
+    [1,1,1, 1,0,1, 1,1,1, 0,0,1, 1,1,1], // 9
+];
+
+// Draws a digit and its shadow, leaving out any rows outside `rows`.
+fn draw_digit(buf: &mut PixelBuf, x: i32, y: i32, d: u8, fg: Rgb, rows: Range<i32>) {
+    let glyph = &DIGITS[d as usize];
+    for row in 0..5 {
+        for col in 0..3 {
+            if glyph[row * 3 + col] == 1 {
+                let px = x + col as i32;
+                let py = y + row as i32;
+                if rows.contains(&(py + 1)) {
+                    buf.set(px + 1, py + 1, SHADOW);
+                }
+                if rows.contains(&py) {
+                    buf.set(px, py, fg);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn draw_number(buf: &mut PixelBuf, cx: i32, y: i32, n: u32, fg: Rgb) {
+    let s = n.to_string();
+    let total_w = s.len() as i32 * 4 - 1; // 3px per digit + 1px spacing
+    let start_x = cx - total_w / 2;
+    for (i, ch) in s.chars().enumerate() {
+        let d = ch as u8 - b'0';
+        draw_digit(buf, start_x + i as i32 * 4, y, d, fg, i32::MIN..i32::MAX);
+    }
+}
+
+// ── Rolling counter ─────────────────────────────────────────────────────────
+
+// Frames a changed digit takes to roll into place.
+const ROLL_FRAMES: u64 = 6;
+
+// A number in the 3x5 digits whose changed digits roll upward into place,
+// odometer style, instead of snapping. `at` is when the roll began, on
+// whichever frame clock the caller draws it with.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct RollingNumber {
+    from: u32,
+    to: u32,
+    at: u64,
+}
+
+impl RollingNumber {
+    pub(crate) fn new(from: u32, to: u32, at: u64) -> Self {
+        RollingNumber { from, to, at }
+    }
+
+    // Rolls on from the value showing now, if `n` is a new one.
+    pub(crate) fn set(&mut self, n: u32, now: u64) {
+        if n != self.to {
+            *self = RollingNumber::new(self.to, n, now);
+        }
+    }
+
+    // Draws `n` centred on `cx` like draw_number, mid-roll if it's the value
+    // this is rolling to. Any other value is drawn as it is, so a counter that
+    // missed a change can't show a stale number.
+    pub(crate) fn draw(&self, buf: &mut PixelBuf, cx: i32, y: i32, n: u32, now: u64, fg: Rgb) {
+        let t = now.saturating_sub(self.at);
+        if n != self.to || t >= ROLL_FRAMES {
+            draw_number(buf, cx, y, n, fg);
+            return;
+        }
+        // Digits are 5 rows with 1 between them, so a roll moves 6.
+        let shift = (t * 6 / ROLL_FRAMES) as i32;
+        let to = n.to_string();
+        // The old value lines up with the new one's last digits; digits it
+        // doesn't have roll up out of a blank.
+        let from = format!("{:>1$}", self.from, to.len());
+        let from = &from.as_bytes()[from.len() - to.len()..];
+        let start_x = cx - (to.len() as i32 * 4 - 1) / 2;
+        let rows = y..y + 6;
+        for (i, (&old, new)) in from.iter().zip(to.bytes()).enumerate() {
+            let x = start_x + i as i32 * 4;
+            if old == new {
+                draw_digit(buf, x, y, new - b'0', fg, rows.clone());
+                continue;
+            }
+            if old.is_ascii_digit() {
+                draw_digit(buf, x, y - shift, old - b'0', fg, rows.clone());
+            }
+            draw_digit(buf, x, y + 6 - shift, new - b'0', fg, rows.clone());
+        }
+    }
+}
+
+pub(crate) const FLAPPY_LOGO: [&str; 7] = [
+    " XXXXXXXXX  XXXX         XXXXXXXXX   XXXXXXXXX   XXXXXXXXX  XXX      XXX",
+    "XXXXXXXXXXX XXXX        XXXXXXXXXXX XXXXXXXXXXX XXXXXXXXXXX XXXX    XXXX",
+    "XXXX        XXXX        XXXX   XXXX XXXX   XXXX XXXX   XXXX  XXXX  XXXX",
+    "XXXXXXXX    XXXX        XXXXXXXXXXX XXXXXXXXXXX XXXXXXXXXXX   XXXXXXXX",
+    "XXXXXXXX    XXXX        XXXXXXXXXXX XXXXXXXXXX  XXXXXXXXXX      XXXX",
+    "XXXX        XXXXXXXXXXX XXXX   XXXX XXXX        XXXX            XXXX",
+    "XXXX         XXXXXXXXXX XXXX   XXXX XXXX        XXXX            XXXX",
+];
+
+const FLAPPY_LETTER_PITCH: i32 = 12;
+const FLAPPY_LETTER_GAP: i32 = 2;
+const FLAPPY_LETTER_COUNT: i32 = 6;
+
+pub(crate) fn flappy_logo_width(scale: i32) -> i32 {
+    let s = scale.max(1);
+    let base = FLAPPY_LOGO[0].chars().count() as i32 * s;
+    let extra = (FLAPPY_LETTER_COUNT - 1) * FLAPPY_LETTER_GAP * s;
+    base + extra
+}
+
+pub(crate) fn draw_flappy_logo(buf: &mut PixelBuf, x: i32, y: i32, scale: i32) {
+    let s = scale.max(1);
+
+    draw_flappy_logo_flat(buf, x - 1, y - 1, s, SHADOW);
+    draw_flappy_logo_flat(buf, x, y - 1, s, SHADOW);
+    draw_flappy_logo_flat(buf, x + 2, y, s, SHADOW);
+    draw_flappy_logo_flat(buf, x, y + 2, s, SHADOW);
+    draw_flappy_logo_flat(buf, x + 2, y + 2, s, SHADOW);
+
+    // First pass: light yellow.
+    draw_flappy_logo_flat(buf, x, y, s, BIRD_HI);
+
+    // Second pass: darker yellow offset for a 3D look.
+    draw_flappy_logo_flat(buf, x + 1, y + 1, s, BIRD_Y);
+}
+
+fn draw_flappy_logo_flat(buf: &mut PixelBuf, x: i32, y: i32, s: i32, color: Rgb) {
+    // Draw each source row as two pixel rows (sub-pixel friendly).
+    for (row, line) in FLAPPY_LOGO.iter().enumerate() {
+        for (col, ch) in line.chars().enumerate() {
+            if ch == 'X' {
+                let col_i32 = col as i32;
+                let letter_idx = (col_i32 / FLAPPY_LETTER_PITCH).clamp(0, FLAPPY_LETTER_COUNT - 1);
+                let px = x + col_i32 * s + letter_idx * FLAPPY_LETTER_GAP * s;
+                let py = y + row as i32 * (2 * s);
+                buf.fill_rect(px, py, s, s, color);
+                buf.fill_rect(px, py + s, s, s, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_numbers_settle_on_the_plain_digits() {
+        let plain = |n| {
+            let mut buf = PixelBuf::new(16, 8);
+            draw_number(&mut buf, 8, 1, n, WHITE);
+            buf.px
+        };
+        let rolled = |roll: RollingNumber, n, now| {
+            let mut buf = PixelBuf::new(16, 8);
+            roll.draw(&mut buf, 8, 1, n, now, WHITE);
+            buf.px
+        };
+        let roll = RollingNumber::new(19, 20, 100);
+        assert!(rolled(roll, 20, 100) == plain(19));
+        assert!(rolled(roll, 20, 103) != plain(20));
+        assert!(rolled(roll, 20, 100 + ROLL_FRAMES) == plain(20));
+        // A value it wasn't told about is drawn as it is.
+        assert!(rolled(roll, 21, 103) == plain(21));
+    }
+}
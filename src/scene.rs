@@ -0,0 +1,694 @@
+//! Scenes: what each `State` does on a step, a flap, a key or click and a
+//! draw, and the stack menus and the pause screen are opened onto.
+
+use crate::draw::{
+    DozeOverlay, EditorScreen, FlapArcLayer, GameOverOverlay, Layer, MutatorsOverlay,
+    PlaygroundOverlay, StatsOverlay, TitleOverlay, TooSmallScreen, insert_layer_after,
+    replace_layer,
+};
+use crate::event::{Event, KeyCode, MouseButton, MouseEventKind};
+use crate::game::{
+    DOZE_FPS, GAME_OVER_DELAY, Game, GameEvent, Mutator, RESTART_BUFFER_DELAY, State, UiEvent,
+    clear_autosave,
+};
+use crate::input::{Action, Controls, TUNING_ACTIONS};
+use crate::theme::{SKIN_PALETTE, save_skin};
+use crate::ui::Screen;
+
+// ── Scenes ──────────────────────────────────────────────────────────────────
+
+// One screen's behavior. A new screen is a `State`, a scene for it in
+// `scene`, and whatever it overrides here; the defaults do nothing.
+pub(crate) trait Scene: Sync {
+    // The scene this one opens over and closes back to, for menus and the
+    // like. Scenes without one are moved between with `Game::enter`.
+    fn parent(&self) -> Option<State> {
+        None
+    }
+
+    // The scene a run moves on to from this one.
+    fn next(&self) -> Option<State> {
+        None
+    }
+
+    fn enter(&self, _game: &mut Game) {}
+
+    fn exit(&self, _game: &mut Game) {}
+
+    // One simulation step.
+    fn update(&self, _game: &mut Game) {}
+
+    fn flap(&self, _game: &mut Game) -> Option<GameEvent> {
+        None
+    }
+
+    // Edits the default layer stack into what this scene draws.
+    fn draw(&self, _game: &Game, _layers: &mut Vec<&'static dyn Layer>) {}
+
+    // Takes a key or mouse event, or returns false to leave it to the
+    // controls every run scene shares: flap, tuning and quit.
+    fn handle_input(&self, _game: &mut Game, _event: &Event, _controls: &mut Controls) -> bool {
+        false
+    }
+
+    fn screen(&self, game: &Game) -> Screen;
+
+    // Whether nothing moves, so frames can be drawn less often.
+    fn is_idle(&self, _game: &Game) -> bool {
+        false
+    }
+
+    // Frames a second to draw at instead of the usual, for scenes that only
+    // move a little.
+    fn frame_rate(&self, _game: &Game) -> Option<u32> {
+        None
+    }
+}
+
+fn scene(state: State) -> &'static dyn Scene {
+    match state {
+        State::Ready => &Title,
+        State::Playing => &Playing,
+        State::Dying => &Dying,
+        State::Dead => &Dead,
+        State::TooSmall => &TooSmall,
+        State::Editor => &Editor,
+        State::Mutators => &Mutators,
+        State::Stats => &Stats,
+        State::Paused => &Paused,
+        State::Playground => &Playground,
+    }
+}
+
+impl Game {
+    pub(crate) fn scene(&self) -> &'static dyn Scene {
+        scene(self.state)
+    }
+
+    // Moves the run on to `to` in place of the scene on top, if that's where
+    // it leads. A shrinking terminal can interrupt anything and drops the
+    // whole stack; the game is rebuilt when it grows back. Returns whether
+    // it moved.
+    pub(crate) fn enter(&mut self, to: State) -> bool {
+        let shrunk = to == State::TooSmall && self.state != State::TooSmall;
+        if !shrunk && self.scene().next() != Some(to) {
+            return false;
+        }
+        tracing::info!(from = ?self.state, ?to, frame = self.frame, "state");
+        self.emit(GameEvent::StateChanged {
+            from: self.state,
+            to,
+        });
+        self.scene().exit(self);
+        if shrunk {
+            while let Some(under) = self.scenes.pop() {
+                scene(under).exit(self);
+            }
+        }
+        self.state = to;
+        scene(to).enter(self);
+        true
+    }
+
+    // Opens `to` over the scene on top, if it opens over that one.
+    pub(crate) fn open(&mut self, to: State) -> bool {
+        if scene(to).parent() != Some(self.state) {
+            return false;
+        }
+        tracing::info!(from = ?self.state, ?to, frame = self.frame, "state");
+        self.emit(GameEvent::StateChanged {
+            from: self.state,
+            to,
+        });
+        self.scenes.push(self.state);
+        self.state = to;
+        scene(to).enter(self);
+        true
+    }
+
+    // Closes the scene on top, back to the one it was opened over.
+    pub(crate) fn close(&mut self) -> bool {
+        let Some(to) = self.scenes.last().copied() else {
+            return false;
+        };
+        tracing::info!(from = ?self.state, ?to, frame = self.frame, "state");
+        self.emit(GameEvent::StateChanged {
+            from: self.state,
+            to,
+        });
+        self.scene().exit(self);
+        self.scenes.pop();
+        self.state = to;
+        true
+    }
+
+    // Puts `state` on top with the scene it opens over under it, running no
+    // hooks, for picking up a saved run.
+    pub(crate) fn set_scene(&mut self, state: State) {
+        self.scenes = scene(state).parent().into_iter().collect();
+        self.state = state;
+    }
+}
+
+// ── Run ─────────────────────────────────────────────────────────────────────
+
+struct Title;
+
+impl Scene for Title {
+    fn next(&self) -> Option<State> {
+        Some(State::Playing)
+    }
+
+    fn update(&self, game: &mut Game) {
+        game.title_frames += 1;
+        game.wait();
+    }
+
+    fn flap(&self, game: &mut Game) -> Option<GameEvent> {
+        game.enter(State::Playing);
+        Some(GameEvent::Flap)
+    }
+
+    fn draw(&self, game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        layers.push(&TitleOverlay);
+        if game.is_dozing() {
+            layers.push(&DozeOverlay);
+        }
+    }
+
+    fn handle_input(&self, game: &mut Game, event: &Event, controls: &mut Controls) -> bool {
+        let Event::Key(key) = event else {
+            return false;
+        };
+        match (key.code, controls.action(key.code)) {
+            (KeyCode::Char('y'), _) if game.resume.is_some() => {
+                game.resume_run();
+                controls.ui(UiEvent::Select);
+            }
+            (KeyCode::Char('n') | KeyCode::Esc, _) if game.resume.is_some() => {
+                game.resume = None;
+                clear_autosave();
+                controls.ui(UiEvent::Back);
+            }
+            (_, Some(Action::PipeStyle)) => {
+                game.cycle_pipe_style();
+                controls.ui(UiEvent::Move);
+            }
+            (_, Some(Action::Mutators)) => {
+                game.open_mutators();
+                controls.ui(UiEvent::Select);
+            }
+            (_, Some(Action::Editor)) => {
+                game.open_editor();
+                controls.ui(UiEvent::Select);
+            }
+            (_, Some(Action::Stats)) => {
+                game.open_stats();
+                controls.ui(UiEvent::Select);
+            }
+            (_, Some(Action::Playground)) => {
+                game.open_playground();
+                controls.ui(UiEvent::Select);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Title
+    }
+
+    fn frame_rate(&self, game: &Game) -> Option<u32> {
+        game.is_dozing().then_some(DOZE_FPS)
+    }
+}
+
+struct Playing;
+
+impl Scene for Playing {
+    fn next(&self) -> Option<State> {
+        Some(State::Dying)
+    }
+
+    fn enter(&self, game: &mut Game) {
+        game.start_run();
+    }
+
+    fn update(&self, game: &mut Game) {
+        game.fly();
+        game.record();
+    }
+
+    fn flap(&self, game: &mut Game) -> Option<GameEvent> {
+        if game.mutators.has(Mutator::Energy) {
+            // Out of energy, a flap does nothing.
+            if game.energy < game.energy_rules.cost {
+                return None;
+            }
+            game.energy -= game.energy_rules.cost;
+        }
+        game.flap_bird();
+        Some(GameEvent::Flap)
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Play
+    }
+}
+
+struct Dying;
+
+impl Scene for Dying {
+    fn next(&self) -> Option<State> {
+        Some(State::Dead)
+    }
+
+    fn enter(&self, game: &mut Game) {
+        game.crash();
+    }
+
+    fn update(&self, game: &mut Game) {
+        if game.fall() {
+            game.enter(State::Dead);
+        } else {
+            game.record();
+        }
+    }
+
+    // Panic presses right at the collision are dropped; a later one is held
+    // and restarts the run as soon as the bird lands, or with instant
+    // restart, starts the next one right away.
+    fn flap(&self, game: &mut Game) -> Option<GameEvent> {
+        if game.dead_timer < RESTART_BUFFER_DELAY {
+            None
+        } else if game.config.instant_restart {
+            game.restart();
+            game.flap()
+        } else {
+            game.restart_queued = true;
+            None
+        }
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Play
+    }
+}
+
+struct Dead;
+
+impl Scene for Dead {
+    fn enter(&self, game: &mut Game) {
+        game.land();
+    }
+
+    fn update(&self, game: &mut Game) {
+        game.step_replay();
+    }
+
+    // A flap during the replay skips ahead to the panel.
+    fn flap(&self, game: &mut Game) -> Option<GameEvent> {
+        if game.replay.take().is_none() {
+            game.restart();
+        }
+        None
+    }
+
+    fn draw(&self, _game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        layers.push(&GameOverOverlay);
+    }
+
+    fn screen(&self, game: &Game) -> Screen {
+        if game.dead_timer > GAME_OVER_DELAY {
+            Screen::GameOver
+        } else {
+            Screen::Play
+        }
+    }
+
+    fn is_idle(&self, game: &Game) -> bool {
+        game.dead_timer > GAME_OVER_DELAY
+    }
+}
+
+struct Paused;
+
+impl Scene for Paused {
+    fn parent(&self) -> Option<State> {
+        Some(State::Playing)
+    }
+
+    // Any flap picks the run back up.
+    fn flap(&self, game: &mut Game) -> Option<GameEvent> {
+        game.close();
+        None
+    }
+
+    fn handle_input(&self, _game: &mut Game, event: &Event, _controls: &mut Controls) -> bool {
+        matches!(event, Event::Mouse(_))
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Play
+    }
+
+    fn is_idle(&self, _game: &Game) -> bool {
+        true
+    }
+}
+
+struct TooSmall;
+
+impl Scene for TooSmall {
+    fn draw(&self, _game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        *layers = vec![&TooSmallScreen];
+    }
+
+    fn handle_input(&self, _game: &mut Game, event: &Event, _controls: &mut Controls) -> bool {
+        matches!(event, Event::Mouse(_))
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::TooSmall
+    }
+
+    fn is_idle(&self, _game: &Game) -> bool {
+        true
+    }
+}
+
+// ── Menus ───────────────────────────────────────────────────────────────────
+
+struct Editor;
+
+impl Scene for Editor {
+    fn parent(&self) -> Option<State> {
+        Some(State::Ready)
+    }
+
+    fn enter(&self, game: &mut Game) {
+        game.editor_part = 0;
+        game.editor_backup = game.skin;
+    }
+
+    fn draw(&self, _game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        *layers = vec![&EditorScreen];
+    }
+
+    fn handle_input(&self, game: &mut Game, event: &Event, controls: &mut Controls) -> bool {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Up | KeyCode::Down => {
+                    game.editor_select(if key.code == KeyCode::Up { -1 } else { 1 });
+                    controls.ui(UiEvent::Move);
+                }
+                KeyCode::Left | KeyCode::Right => {
+                    game.editor_cycle(if key.code == KeyCode::Left { -1 } else { 1 });
+                    controls.ui(UiEvent::Move);
+                }
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    game.close_editor(true);
+                    if let Err(e) = save_skin(game.skin) {
+                        tracing::warn!(error = %e, "bird not saved");
+                    }
+                    controls.ui(UiEvent::Select);
+                }
+                KeyCode::Esc => {
+                    game.close_editor(false);
+                    controls.ui(UiEvent::Back);
+                }
+                _ => {}
+            },
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                let (lx, _, lw, _) = game.editor_part_label();
+                let x = game.pointer.map_or(0, |(x, _)| x);
+                if let Some(i) =
+                    (0..SKIN_PALETTE.len()).find(|&i| game.hovers(game.editor_swatch(i)))
+                {
+                    game.editor_pick(i);
+                    controls.ui(UiEvent::Move);
+                } else if game.hovers(game.editor_part_label()) {
+                    // Left half goes back a part, right half forward.
+                    game.editor_select(if x < lx + lw / 2 { -1 } else { 1 });
+                    controls.ui(UiEvent::Move);
+                } else if game.hovers(game.editor_save_hint()) {
+                    game.close_editor(true);
+                    if let Err(e) = save_skin(game.skin) {
+                        tracing::warn!(error = %e, "bird not saved");
+                    }
+                    controls.ui(UiEvent::Select);
+                }
+            }
+            Event::Mouse(_) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Menu
+    }
+}
+
+struct Mutators;
+
+impl Scene for Mutators {
+    fn parent(&self) -> Option<State> {
+        Some(State::Ready)
+    }
+
+    fn enter(&self, game: &mut Game) {
+        game.mutator_cursor = 0;
+    }
+
+    // The mutators are part of the category bests are kept for.
+    fn exit(&self, game: &mut Game) {
+        game.best = game.category_best();
+    }
+
+    fn draw(&self, _game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        layers.push(&MutatorsOverlay);
+    }
+
+    fn handle_input(&self, game: &mut Game, event: &Event, controls: &mut Controls) -> bool {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Up | KeyCode::Down => {
+                    game.mutators_select(if key.code == KeyCode::Up { -1 } else { 1 });
+                    controls.ui(UiEvent::Move);
+                }
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    game.mutators_toggle();
+                    controls.ui(UiEvent::Select);
+                }
+                code if code == KeyCode::Esc || controls.action(code) == Some(Action::Mutators) => {
+                    game.close_mutators();
+                    controls.ui(UiEvent::Back);
+                }
+                _ => {}
+            },
+            Event::Mouse(mouse) => {
+                let (_, rows) = game.mutators_layout();
+                let Some(&(i, _)) = rows.iter().find(|(_, rect)| game.hovers(*rect)) else {
+                    return true;
+                };
+                if i != game.mutator_cursor {
+                    game.mutator_cursor = i;
+                    controls.ui(UiEvent::Move);
+                }
+                if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                    game.mutators_toggle();
+                    controls.ui(UiEvent::Select);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Menu
+    }
+}
+
+struct Stats;
+
+impl Scene for Stats {
+    fn parent(&self) -> Option<State> {
+        Some(State::Ready)
+    }
+
+    fn draw(&self, _game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        layers.push(&StatsOverlay);
+    }
+
+    fn handle_input(&self, game: &mut Game, event: &Event, controls: &mut Controls) -> bool {
+        match event {
+            Event::Key(key) => match (key.code, controls.action(key.code)) {
+                (KeyCode::Left | KeyCode::Right, _) => {
+                    game.stats_select(if key.code == KeyCode::Left { -1 } else { 1 });
+                    controls.ui(UiEvent::Move);
+                }
+                (KeyCode::Esc, _) | (_, Some(Action::Stats | Action::Flap)) => {
+                    game.close_stats();
+                    controls.ui(UiEvent::Back);
+                }
+                _ => {}
+            },
+            Event::Mouse(mouse) => {
+                if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                    game.close_stats();
+                    controls.ui(UiEvent::Back);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Menu
+    }
+}
+
+struct Playground;
+
+impl Scene for Playground {
+    fn parent(&self) -> Option<State> {
+        Some(State::Ready)
+    }
+
+    fn enter(&self, game: &mut Game) {
+        game.playground_cursor = 0;
+        game.bird_vy = 0.0;
+    }
+
+    // The sliders are part of the category bests are kept for.
+    fn exit(&self, game: &mut Game) {
+        game.best = game.category_best();
+    }
+
+    fn update(&self, game: &mut Game) {
+        game.drift();
+    }
+
+    fn flap(&self, game: &mut Game) -> Option<GameEvent> {
+        game.flap_bird();
+        Some(GameEvent::Flap)
+    }
+
+    fn draw(&self, _game: &Game, layers: &mut Vec<&'static dyn Layer>) {
+        insert_layer_after(layers, "bird", &FlapArcLayer);
+        replace_layer(layers, "hud", &PlaygroundOverlay);
+    }
+
+    fn handle_input(&self, game: &mut Game, event: &Event, controls: &mut Controls) -> bool {
+        match event {
+            Event::Key(key) => match (key.code, controls.action(key.code)) {
+                (KeyCode::Up | KeyCode::Down, _) => {
+                    game.playground_select(if key.code == KeyCode::Up { -1 } else { 1 });
+                    controls.ui(UiEvent::Move);
+                }
+                (KeyCode::Left | KeyCode::Right, _) => {
+                    let steps = if key.code == KeyCode::Left { -1.0 } else { 1.0 };
+                    game.playground_adjust(game.playground_cursor, steps);
+                    controls.ui(UiEvent::Move);
+                }
+                (KeyCode::Esc, _) | (_, Some(Action::Playground)) => {
+                    game.close_playground();
+                    controls.ui(UiEvent::Back);
+                }
+                (_, Some(Action::Flap)) => controls.flap_key(game, key.kind),
+                // The tuning keys move the sliders they match.
+                (_, Some(action)) => {
+                    if let Some(i) = TUNING_ACTIONS.iter().position(|a| *a == action) {
+                        game.playground_adjust(i / 2, if i % 2 == 0 { 1.0 } else { -1.0 });
+                        game.playground_cursor = i / 2;
+                        controls.ui(UiEvent::Move);
+                    }
+                }
+                _ => {}
+            },
+            // Clicking or dragging along a track sets it, the wheel steps a
+            // hovered slider, and clicks anywhere else flap.
+            Event::Mouse(mouse) => {
+                let (_, rows) = game.playground_layout();
+                let Some(i) = rows.iter().position(|(row, _)| game.hovers(*row)) else {
+                    if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                        controls.flap(game);
+                    }
+                    return true;
+                };
+                let (tx, _, tw, _) = rows[i].1;
+                let x = game.pointer.map_or(0, |(x, _)| x);
+                match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left)
+                    | MouseEventKind::Drag(MouseButton::Left) => {
+                        game.playground_set(i, (x - tx) as f64 / (tw - 2) as f64);
+                    }
+                    MouseEventKind::ScrollUp => game.playground_adjust(i, 1.0),
+                    MouseEventKind::ScrollDown => game.playground_adjust(i, -1.0),
+                    _ => return true,
+                }
+                game.playground_cursor = i;
+                controls.ui(UiEvent::Move);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn screen(&self, _game: &Game) -> Screen {
+        Screen::Play
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menus_open_over_the_title_and_close_back_to_it() {
+        let mut game = Game::headless(1);
+        assert!(game.open(State::Mutators));
+        assert!(!game.open(State::Stats));
+        assert!(game.state == State::Mutators && game.scenes == [State::Ready]);
+        assert!(game.close());
+        assert!(game.state == State::Ready && game.scenes.is_empty());
+        assert!(!game.close());
+        game.flap();
+        assert!(!game.open(State::Mutators));
+    }
+
+    #[test]
+    fn pausing_keeps_the_run_underneath() {
+        let mut game = Game::headless(2);
+        game.flap();
+        game.update();
+        let (runs, bird) = (game.session.runs, game.bird());
+        assert!(game.open(State::Paused));
+        game.update();
+        assert_eq!(game.bird(), bird);
+        assert!(game.flap().is_none());
+        assert!(game.state == State::Playing && game.session.runs == runs);
+    }
+
+    #[test]
+    fn a_shrinking_terminal_drops_the_stack() {
+        let mut game = Game::headless(3);
+        game.open(State::Playground);
+        assert!(game.enter(State::TooSmall));
+        assert!(game.scenes.is_empty());
+        assert!(!game.enter(State::TooSmall));
+        assert!(!game.enter(State::Playing));
+    }
+
+    #[test]
+    fn saved_scenes_come_back_over_their_parent() {
+        let mut game = Game::headless(4);
+        game.set_scene(State::Paused);
+        assert!(game.scenes == [State::Playing]);
+        assert!(game.close() && game.state == State::Playing);
+    }
+}
@@ -0,0 +1,325 @@
+//! The audio thread that plays sounds on the output device, and gamepad
+//! rumble. Terminal only: a browser has neither, and plays sounds through
+//! web audio instead (see `web`).
+
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source, buffer::SamplesBuffer};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::audio::{SAMPLE_RATE, Sound, SoundPalette, generate_rush_samples};
+use crate::error::FlappyError;
+use crate::game::{GameEvent, State};
+
+// ── Audio ───────────────────────────────────────────────────────────────────
+
+// How often the audio thread checks that sounds are still finishing, and how
+// often it tries to open a device again once they aren't. No sound is longer
+// than AUDIO_STALL, so one that hasn't finished by then never will.
+const AUDIO_CHECK: Duration = Duration::from_secs(1);
+const AUDIO_RETRY: Duration = Duration::from_secs(3);
+pub(crate) const AUDIO_STALL: Duration = Duration::from_secs(3);
+
+// The default output device. Sounds are synthesized when asked for and
+// played on rodio's own thread.
+pub struct Audio {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    // Sounds started, and those not finished yet, counted down by rodio as
+    // they run out. An unplugged device stops pulling samples, so the
+    // unfinished ones never do.
+    started: Cell<usize>,
+    unfinished: Arc<AtomicUsize>,
+    // How many had finished when last checked, and since when.
+    finished: Cell<(usize, Instant)>,
+    failed: Cell<bool>,
+    // The rush hour layer, looping until the rush is over. It never
+    // finishes, so it isn't counted above.
+    rush: RefCell<Option<Sink>>,
+}
+
+impl Audio {
+    pub fn new() -> Result<Self, FlappyError> {
+        let (stream, handle) = OutputStream::try_default()?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+            started: Cell::new(0),
+            unfinished: Arc::new(AtomicUsize::new(0)),
+            finished: Cell::new((0, Instant::now())),
+            failed: Cell::new(false),
+            rush: RefCell::new(None),
+        })
+    }
+
+    // Whether the device has stopped playing: a sound couldn't start, or
+    // none has finished for AUDIO_STALL while some are waiting to.
+    pub fn stalled(&self) -> bool {
+        let unfinished = self.unfinished.load(Ordering::Relaxed);
+        let finished = self.started.get() - unfinished;
+        let (last, since) = self.finished.get();
+        if unfinished == 0 || finished != last {
+            self.finished.set((finished, Instant::now()));
+            return self.failed.get();
+        }
+        self.failed.get() || since.elapsed() > AUDIO_STALL
+    }
+
+    // Starts a sound made with `palette` and returns without waiting for it
+    // to finish.
+    pub fn play(&self, sound: Sound, palette: &SoundPalette) {
+        match sound {
+            Sound::Game(GameEvent::RushHour(true)) => play_rush(self, palette),
+            Sound::Game(GameEvent::RushHour(false)) => *self.rush.borrow_mut() = None,
+            Sound::Game(GameEvent::StateChanged { to, .. }) => follow_rush(self, to),
+            sound => {
+                if let Some(samples) = sound.samples(palette) {
+                    play_samples(self, samples);
+                }
+            }
+        }
+    }
+}
+
+// Starts the audio thread and returns the channel it plays sounds from. It
+// owns the output device and synthesizes each sound as it comes in, so none
+// of that happens on the game's thread, and shakes gamepads that can rumble
+// for the game's events. When the device goes away mid-session, it says so
+// on `muted` and keeps trying to open one again, saying so again when it
+// does. With no device at all to begin with, it stays silent without
+// retrying. It stops once the returned sender is dropped.
+pub fn spawn_audio(muted: mpsc::UnboundedSender<bool>) -> std::sync::mpsc::Sender<Sound> {
+    let (tx, sounds) = std::sync::mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("audio".into())
+        .spawn(move || run_audio(sounds, muted));
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "no audio thread, playing silently");
+    }
+    tx
+}
+
+fn run_audio(sounds: std::sync::mpsc::Receiver<Sound>, muted: mpsc::UnboundedSender<bool>) {
+    let mut audio = Audio::new()
+        .inspect_err(|e| tracing::warn!(error = %e, "playing silently"))
+        .ok();
+    let mut rumble = Rumble::new();
+    let mut palette = SoundPalette::default();
+    let mut check_at = Instant::now() + AUDIO_CHECK;
+    let mut lost = false;
+    let mut retry_at = Instant::now();
+    loop {
+        match sounds.recv_timeout(check_at.saturating_duration_since(Instant::now())) {
+            Ok(Sound::Palette(p)) => palette = p,
+            Ok(sound) => {
+                if let (Some(rumble), Sound::Game(event)) = (&mut rumble, &sound) {
+                    rumble.play(event);
+                }
+                if let Some(audio) = &audio {
+                    audio.play(sound, &palette);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+            Err(RecvTimeoutError::Timeout) => {
+                check_at = Instant::now() + AUDIO_CHECK;
+                if audio.as_ref().is_some_and(Audio::stalled) {
+                    tracing::warn!("audio output stopped, muting until a device is back");
+                    audio = None;
+                    lost = true;
+                    retry_at = Instant::now() + AUDIO_RETRY;
+                    let _ = muted.send(true);
+                } else if lost && Instant::now() >= retry_at {
+                    if audio_available() {
+                        audio = Audio::new().ok();
+                    }
+                    if audio.is_some() {
+                        tracing::info!("audio output back");
+                        lost = false;
+                        let _ = muted.send(false);
+                    }
+                    retry_at = Instant::now() + AUDIO_RETRY;
+                    // What came in meanwhile is stale.
+                    while sounds.try_recv().is_ok() {}
+                }
+            }
+        }
+    }
+}
+
+// Tries opening the default device. ALSA complains about failing on stderr,
+// over the game.
+fn audio_available() -> bool {
+    quiet_stderr(|| OutputStream::try_default().is_ok())
+}
+
+#[cfg(unix)]
+fn quiet_stderr<T>(f: impl FnOnce() -> T) -> T {
+    use std::os::fd::AsRawFd;
+    let Ok(null) = fs::OpenOptions::new().write(true).open("/dev/null") else {
+        return f();
+    };
+    // SAFETY: `dup` only reads fd 2 and returns a new fd we own, or -1.
+    let saved = unsafe { libc::dup(2) };
+    if saved < 0 {
+        return f();
+    }
+    // SAFETY: `null` stays open across the call, and pointing fd 2 at it
+    // closes no fd any Rust value owns; the original is kept in `saved`.
+    unsafe { libc::dup2(null.as_raw_fd(), 2) };
+    let out = f();
+    // SAFETY: `saved` is the fd `dup` gave us above, still open and owned
+    // by nothing else, so it can be put back on fd 2 and then closed.
+    unsafe {
+        libc::dup2(saved, 2);
+        libc::close(saved);
+    }
+    out
+}
+
+#[cfg(not(unix))]
+fn quiet_stderr<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+fn play_rush(audio: &Audio, palette: &SoundPalette) {
+    match Sink::try_new(&audio.handle) {
+        Ok(sink) => {
+            let samples = generate_rush_samples(SAMPLE_RATE, palette);
+            sink.append(SamplesBuffer::new(1, SAMPLE_RATE, samples).repeat_infinite());
+            *audio.rush.borrow_mut() = Some(sink);
+        }
+        Err(e) => tracing::warn!(error = %e, "can't play a sound"),
+    }
+}
+
+// The rush hour layer holds while paused and stops once the run does.
+fn follow_rush(audio: &Audio, state: State) {
+    let mut rush = audio.rush.borrow_mut();
+    match (rush.as_ref(), state) {
+        (Some(sink), State::Playing) => sink.play(),
+        (Some(sink), State::Paused) => sink.pause(),
+        _ => *rush = None,
+    }
+}
+
+fn play_samples(audio: &Audio, samples: Vec<f32>) {
+    match Sink::try_new(&audio.handle) {
+        Ok(sink) => {
+            let source = SamplesBuffer::new(1, SAMPLE_RATE, samples);
+            audio.started.set(audio.started.get() + 1);
+            audio.unfinished.fetch_add(1, Ordering::Relaxed);
+            sink.append(rodio::source::Done::new(source, audio.unfinished.clone()));
+            sink.detach();
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "can't play a sound");
+            audio.failed.set(true);
+        }
+    }
+}
+
+// ── Rumble ──────────────────────────────────────────────────────────────────
+
+// (strong motor, weak motor, milliseconds) at full intensity: a hard jolt on
+// death and a light tap on scoring.
+const RUMBLE_DEATH: (u16, u16, u32) = (60_000, 40_000, 300);
+const RUMBLE_SCORE: (u16, u16, u32) = (0, 20_000, 60);
+
+// Force feedback on every connected gamepad that has it, scaled by
+// FLAPPY_RUMBLE from 0 (off) to 1 (the default).
+struct Rumble {
+    gilrs: gilrs::Gilrs,
+    intensity: f32,
+    death: Option<gilrs::ff::Effect>,
+    score: Option<gilrs::ff::Effect>,
+}
+
+impl Rumble {
+    // None when it's turned off or gamepads can't be reached at all.
+    fn new() -> Option<Self> {
+        let intensity = std::env::var("FLAPPY_RUMBLE")
+            .ok()
+            .and_then(|s| s.parse::<f32>().ok())
+            .map_or(1.0, |v| v.clamp(0.0, 1.0));
+        if intensity == 0.0 {
+            return None;
+        }
+        let mut rumble = Rumble {
+            gilrs: (gilrs::Gilrs::new())
+                .inspect_err(|e| tracing::info!(error = %e, "no gamepads, rumble is off"))
+                .ok()?,
+            intensity,
+            death: None,
+            score: None,
+        };
+        rumble.rebuild();
+        Some(rumble)
+    }
+
+    // Effects only play on the gamepads they were made for, so they're made
+    // again whenever one comes or goes.
+    fn poll(&mut self) {
+        let mut changed = false;
+        while let Some(event) = self.gilrs.next_event() {
+            changed |= matches!(
+                event.event,
+                gilrs::EventType::Connected | gilrs::EventType::Disconnected
+            );
+        }
+        if changed {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder, Repeat, Replay, Ticks};
+        let pads: Vec<_> = (self.gilrs.gamepads())
+            .filter(|(_, pad)| pad.is_ff_supported())
+            .map(|(id, _)| id)
+            .collect();
+        let scale = |magnitude: u16| (magnitude as f32 * self.intensity) as u16;
+        let mut effect = |(strong, weak, ms): (u16, u16, u32)| {
+            if pads.is_empty() {
+                return None;
+            }
+            let scheduling = Replay {
+                play_for: Ticks::from_ms(ms),
+                ..Default::default()
+            };
+            let motor = |kind| BaseEffect {
+                kind,
+                scheduling,
+                envelope: Default::default(),
+            };
+            EffectBuilder::new()
+                .add_effect(motor(BaseEffectType::Strong {
+                    magnitude: scale(strong),
+                }))
+                .add_effect(motor(BaseEffectType::Weak {
+                    magnitude: scale(weak),
+                }))
+                .repeat(Repeat::For(Ticks::from_ms(ms)))
+                .gamepads(&pads)
+                .finish(&mut self.gilrs)
+                .ok()
+        };
+        self.death = effect(RUMBLE_DEATH);
+        self.score = effect(RUMBLE_SCORE);
+    }
+
+    fn play(&mut self, event: &GameEvent) {
+        self.poll();
+        let effect = match event {
+            GameEvent::Death => &self.death,
+            GameEvent::Score(_) => &self.score,
+            _ => return,
+        };
+        if let Some(effect) = effect {
+            let _ = effect.play();
+        }
+    }
+}
@@ -0,0 +1,894 @@
+//! Themes, skins and tuning read from the config directory, and the dated
+//! events that change them.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::audio::{Noise, SoundPalette, Wave};
+use crate::error::FlappyError;
+use crate::game::{COYOTE_FRAMES, COYOTE_MAX, Game, PIPE_CAP_EXTRA, PIPE_CAP_H, Rng, State};
+use crate::pixels::Rgb;
+use crate::render::{
+    BIRD_BEAK, BIRD_BEAK_HI, BIRD_EYE, BIRD_HI, BIRD_PUPIL, BIRD_WING, BIRD_Y, CAP_DARK, PIPE_HI,
+    PIPE_L, PIPE_M, PIPE_R, WHITE,
+};
+
+// ── Themes ──────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct BirdSkin {
+    pub(crate) body: Rgb,
+    pub(crate) body_hi: Rgb,
+    pub(crate) wing: Rgb,
+    pub(crate) eye: Rgb,
+    pub(crate) pupil: Rgb,
+    pub(crate) beak: Rgb,
+    pub(crate) beak_hi: Rgb,
+}
+
+pub(crate) const DEFAULT_SKIN: BirdSkin = BirdSkin {
+    body: BIRD_Y,
+    body_hi: BIRD_HI,
+    wing: BIRD_WING,
+    eye: BIRD_EYE,
+    pupil: BIRD_PUPIL,
+    beak: BIRD_BEAK,
+    beak_hi: BIRD_BEAK_HI,
+};
+
+// Base color plus the highlight used where a part has one (body, beak).
+pub(crate) const SKIN_PALETTE: [(Rgb, Rgb); 10] = [
+    (BIRD_Y, BIRD_HI),
+    (BIRD_WING, Rgb(235, 190, 70)),
+    (BIRD_BEAK, BIRD_BEAK_HI),
+    (BIRD_EYE, BIRD_EYE),
+    (Rgb(90, 160, 230), Rgb(130, 190, 245)),
+    (Rgb(110, 195, 80), Rgb(150, 220, 110)),
+    (Rgb(190, 100, 215), Rgb(215, 140, 235)),
+    (Rgb(240, 130, 165), Rgb(250, 170, 195)),
+    (Rgb(150, 150, 160), Rgb(185, 185, 195)),
+    (Rgb(60, 60, 70), Rgb(95, 95, 105)),
+];
+
+#[derive(Clone, Copy)]
+pub(crate) enum BirdPart {
+    Body,
+    Wing,
+    Beak,
+    Eye,
+}
+
+pub(crate) const BIRD_PARTS: [BirdPart; 4] = [
+    BirdPart::Body,
+    BirdPart::Wing,
+    BirdPart::Beak,
+    BirdPart::Eye,
+];
+
+impl BirdPart {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            BirdPart::Body => "body",
+            BirdPart::Wing => "wing",
+            BirdPart::Beak => "beak",
+            BirdPart::Eye => "eye",
+        }
+    }
+}
+
+impl BirdSkin {
+    pub(crate) fn color(&self, part: BirdPart) -> Rgb {
+        match part {
+            BirdPart::Body => self.body,
+            BirdPart::Wing => self.wing,
+            BirdPart::Beak => self.beak,
+            BirdPart::Eye => self.eye,
+        }
+    }
+
+    pub(crate) fn set(&mut self, part: BirdPart, base: Rgb) {
+        let hi = SKIN_PALETTE
+            .iter()
+            .find(|(c, _)| *c == base)
+            .map_or(Rgb::lerp(base, WHITE, 64), |(_, hi)| *hi);
+        match part {
+            BirdPart::Body => {
+                self.body = base;
+                self.body_hi = hi;
+            }
+            BirdPart::Wing => self.wing = base,
+            BirdPart::Beak => {
+                self.beak = base;
+                self.beak_hi = hi;
+            }
+            BirdPart::Eye => self.eye = base,
+        }
+    }
+
+    // Skins are stored as a `[bird]` theme fragment with one `part = #rrggbb` line per part.
+    fn to_theme_fragment(self) -> String {
+        let mut s = String::from("[bird]\n");
+        for part in BIRD_PARTS {
+            let c = self.color(part);
+            s.push_str(&format!(
+                "{} = #{:02x}{:02x}{:02x}\n",
+                part.name(),
+                c.0,
+                c.1,
+                c.2
+            ));
+        }
+        s
+    }
+}
+
+// Pipe art: a horizontal gradient (stops at 0..256 across the pipe width),
+// cap geometry in world units, and an optional pattern over the body.
+pub(crate) struct PipeStyle {
+    name: &'static str,
+    pub(crate) stops: &'static [(u16, Rgb)],
+    pub(crate) cap_dark: Rgb,
+    pub(crate) cap_h: f64,
+    pub(crate) cap_extra: f64,
+    pub(crate) pattern: PipePattern,
+}
+
+pub(crate) enum PipePattern {
+    Plain,
+    // Diagonal bands of `color`, `period` world units apart.
+    Stripes { color: Rgb, period: f64 },
+    // Horizontal rings of `color`, `period` world units apart.
+    Rings { color: Rgb, period: f64 },
+}
+
+pub(crate) const PIPE_STYLES: [PipeStyle; 4] = [
+    PipeStyle {
+        name: "classic",
+        stops: &[
+            (0, PIPE_L),
+            (64, PIPE_M),
+            (100, PIPE_HI),
+            (160, PIPE_R),
+            (245, PIPE_L),
+        ],
+        cap_dark: CAP_DARK,
+        cap_h: PIPE_CAP_H,
+        cap_extra: PIPE_CAP_EXTRA,
+        pattern: PipePattern::Plain,
+    },
+    PipeStyle {
+        name: "metal",
+        stops: &[
+            (0, Rgb(90, 95, 105)),
+            (70, Rgb(150, 155, 165)),
+            (110, Rgb(215, 220, 228)),
+            (170, Rgb(160, 165, 175)),
+            (256, Rgb(85, 90, 100)),
+        ],
+        cap_dark: Rgb(60, 62, 70),
+        cap_h: 4.0,
+        cap_extra: 2.5,
+        pattern: PipePattern::Rings {
+            color: Rgb(105, 110, 120),
+            period: 16.0,
+        },
+    },
+    PipeStyle {
+        name: "candy",
+        stops: &[
+            (0, Rgb(200, 200, 205)),
+            (80, Rgb(245, 245, 245)),
+            (120, WHITE),
+            (256, Rgb(190, 190, 195)),
+        ],
+        cap_dark: Rgb(170, 30, 40),
+        cap_h: PIPE_CAP_H,
+        cap_extra: PIPE_CAP_EXTRA,
+        pattern: PipePattern::Stripes {
+            color: Rgb(215, 40, 50),
+            period: 10.0,
+        },
+    },
+    PipeStyle {
+        name: "bamboo",
+        stops: &[
+            (0, Rgb(120, 140, 40)),
+            (70, Rgb(170, 190, 70)),
+            (110, Rgb(205, 215, 110)),
+            (180, Rgb(165, 185, 65)),
+            (256, Rgb(115, 135, 35)),
+        ],
+        cap_dark: Rgb(95, 110, 30),
+        cap_h: 2.5,
+        cap_extra: 1.5,
+        pattern: PipePattern::Rings {
+            color: Rgb(110, 125, 35),
+            period: 18.0,
+        },
+    },
+];
+
+pub(crate) fn pipe_style(name: &str) -> Option<&'static PipeStyle> {
+    PIPE_STYLES.iter().find(|style| style.name == name)
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    skin: BirdSkin,
+    pipes: &'static PipeStyle,
+    // None leaves the game's configured sky.
+    sky: Option<Vec<Rgb>>,
+    hills: HillShape,
+    tuning: Tuning,
+    energy: EnergyRules,
+    gaps: GapRules,
+    sound: SoundPalette,
+    // The event in effect, if any, and its score multiplier.
+    event: Option<String>,
+    points: u32,
+}
+
+// The ENERGY mutator: each flap costs `cost` of a full bar, and the bar
+// refills by `refill` bars per second.
+#[derive(Clone, Copy)]
+pub(crate) struct EnergyRules {
+    pub(crate) cost: f64,
+    pub(crate) refill: f64,
+}
+
+impl Default for EnergyRules {
+    fn default() -> Self {
+        EnergyRules {
+            cost: 0.25,
+            refill: 0.45,
+        }
+    }
+}
+
+// How pipe gap heights follow one another. `Spread` retries a few times to
+// land at least `min_step` from the last gap so heights don't repeat, and
+// `Bounded` stays within `max_step` of it so no climb is out of reach. Both
+// steps are shares of the heights available.
+#[derive(Clone, Copy, PartialEq)]
+enum GapDistribution {
+    Uniform,
+    Spread,
+    Bounded,
+}
+
+const SPREAD_TRIES: usize = 4;
+
+#[derive(Clone, Copy)]
+pub(crate) struct GapRules {
+    distribution: GapDistribution,
+    min_step: f64,
+    max_step: f64,
+}
+
+impl Default for GapRules {
+    fn default() -> Self {
+        GapRules {
+            distribution: GapDistribution::Uniform,
+            min_step: 0.2,
+            max_step: 0.35,
+        }
+    }
+}
+
+impl GapRules {
+    // A gap center in `lo..lo + range`, given the previous pipe's.
+    pub(crate) fn pick(&self, rng: &mut impl Rng, lo: f64, range: f64, prev: Option<f64>) -> f64 {
+        let Some(prev) = prev else {
+            return lo + rng.next_f64() * range;
+        };
+        match self.distribution {
+            GapDistribution::Uniform => lo + rng.next_f64() * range,
+            GapDistribution::Spread => {
+                let mut center = lo + rng.next_f64() * range;
+                for _ in 1..SPREAD_TRIES {
+                    if (center - prev).abs() >= self.min_step * range {
+                        break;
+                    }
+                    center = lo + rng.next_f64() * range;
+                }
+                center
+            }
+            GapDistribution::Bounded => {
+                let step = self.max_step * range;
+                let from = (prev - step).max(lo);
+                let to = (prev + step).min(lo + range);
+                from + rng.next_f64() * (to - from).max(0.0)
+            }
+        }
+    }
+}
+
+// Scales for gravity, flap strength and scroll speed; 1.0 is stock. `coyote`
+// is the grace period, in frames, for clipping a pipe on the way out.
+#[derive(Clone, Copy)]
+pub(crate) struct Tuning {
+    pub(crate) gravity: f64,
+    pub(crate) flap: f64,
+    pub(crate) speed: f64,
+    pub(crate) coyote: f64,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            gravity: 1.0,
+            flap: 1.0,
+            speed: 1.0,
+            coyote: COYOTE_FRAMES,
+        }
+    }
+}
+
+// The playground's sliders, one per Tuning field in order: label, range and
+// the step a key press moves. The ranges are the ones theme files allow.
+pub(crate) const PLAYGROUND_SLIDERS: [(&str, f64, f64, f64); 4] = [
+    ("GRAV", 0.25, 4.0, 0.05),
+    ("FLAP", 0.25, 4.0, 0.05),
+    ("SPEED", 0.25, 4.0, 0.05),
+    ("GRACE", 0.0, COYOTE_MAX, 0.5),
+];
+
+// How far ahead the playground's flap arc is predicted.
+pub(crate) const ARC_FRAMES: u32 = 45;
+
+impl Tuning {
+    pub(crate) fn slider(&mut self, i: usize) -> &mut f64 {
+        match i {
+            0 => &mut self.gravity,
+            1 => &mut self.flap,
+            2 => &mut self.speed,
+            _ => &mut self.coyote,
+        }
+    }
+}
+
+// Scales for the hill noise; 1.0 is the stock look.
+#[derive(Clone, Copy)]
+pub(crate) struct HillShape {
+    pub(crate) amplitude: f64,
+    pub(crate) frequency: f64,
+}
+
+impl Default for HillShape {
+    fn default() -> Self {
+        HillShape {
+            amplitude: 1.0,
+            frequency: 1.0,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            skin: DEFAULT_SKIN,
+            pipes: &PIPE_STYLES[0],
+            sky: None,
+            hills: HillShape::default(),
+            tuning: Tuning::default(),
+            energy: EnergyRules::default(),
+            gaps: GapRules::default(),
+            sound: SoundPalette::default(),
+            event: None,
+            points: 1,
+        }
+    }
+}
+
+impl Theme {
+    // Fragments are INI-like: `[bird]` takes `part = #rrggbb` lines,
+    // `[pipes]` takes `style = classic|metal|candy|bamboo`, and `[sky]` takes
+    // `gradient = #top, ..., #bottom` with two or more evenly spaced stops,
+    // `[hills]` takes `amplitude` and `frequency` multipliers, `[physics]`
+    // takes `gravity`, `flap` and `speed` multipliers and the `coyote` frames
+    // a just-cleared pipe can still be clipped, `[energy]` takes the flap
+    // `cost` and `refill` rate, `[gaps]` takes
+    // `distribution = uniform|spread|bounded` with `min_step` and `max_step`,
+    // and `[sound]` takes `wave = sine|triangle|square|saw`,
+    // `noise = white|pink|brown`, and `pitch` and `volume` multipliers.
+    fn apply_fragment(&mut self, text: &str) {
+        let mut section = "";
+        for line in text.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match section {
+                "bird" => {
+                    let part = BIRD_PARTS.iter().find(|p| p.name() == key);
+                    if let (Some(part), Some(c)) = (part, parse_hex_color(value)) {
+                        self.skin.set(*part, c);
+                    }
+                }
+                "pipes" if key == "style" => {
+                    if let Some(style) = pipe_style(value) {
+                        self.pipes = style;
+                    }
+                }
+                "sky" if key == "gradient" => {
+                    let stops: Option<Vec<Rgb>> = value
+                        .split(',')
+                        .map(|c| parse_hex_color(c.trim()))
+                        .collect();
+                    if let Some(stops) = stops.filter(|s| s.len() >= 2) {
+                        self.sky = Some(stops);
+                    }
+                }
+                "hills" => {
+                    let Some(v) = value.parse::<f64>().ok().filter(|v| *v > 0.0) else {
+                        continue;
+                    };
+                    match key {
+                        "amplitude" => self.hills.amplitude = v.min(3.0),
+                        "frequency" => self.hills.frequency = v.min(5.0),
+                        _ => {}
+                    }
+                }
+                "physics" => {
+                    let Ok(v) = value.parse::<f64>() else {
+                        continue;
+                    };
+                    match key {
+                        "gravity" => self.tuning.gravity = v.clamp(0.25, 4.0),
+                        "flap" => self.tuning.flap = v.clamp(0.25, 4.0),
+                        "speed" => self.tuning.speed = v.clamp(0.25, 4.0),
+                        "coyote" => self.tuning.coyote = v.clamp(0.0, COYOTE_MAX),
+                        _ => {}
+                    }
+                }
+                "energy" => {
+                    let Some(v) = value.parse::<f64>().ok().map(|v| v.clamp(0.05, 5.0)) else {
+                        continue;
+                    };
+                    match key {
+                        "cost" => self.energy.cost = v.min(1.0),
+                        "refill" => self.energy.refill = v,
+                        _ => {}
+                    }
+                }
+                "gaps" => match key {
+                    "distribution" => {
+                        self.gaps.distribution = match value {
+                            "uniform" => GapDistribution::Uniform,
+                            "spread" => GapDistribution::Spread,
+                            "bounded" => GapDistribution::Bounded,
+                            _ => continue,
+                        }
+                    }
+                    "min_step" | "max_step" => {
+                        let Some(v) = value.parse::<f64>().ok().map(|v| v.clamp(0.05, 1.0)) else {
+                            continue;
+                        };
+                        if key == "min_step" {
+                            self.gaps.min_step = v;
+                        } else {
+                            self.gaps.max_step = v;
+                        }
+                    }
+                    _ => {}
+                },
+                "sound" => match key {
+                    "wave" => {
+                        self.sound.wave = Some(match value {
+                            "sine" => Wave::Sine,
+                            "triangle" => Wave::Triangle,
+                            "square" => Wave::Square,
+                            "saw" => Wave::Saw,
+                            _ => continue,
+                        })
+                    }
+                    "noise" => {
+                        self.sound.noise = match value {
+                            "white" => Noise::White,
+                            "pink" => Noise::Pink,
+                            "brown" => Noise::Brown,
+                            _ => continue,
+                        }
+                    }
+                    "pitch" | "volume" => {
+                        let Ok(v) = value.parse::<f32>() else {
+                            continue;
+                        };
+                        if key == "pitch" {
+                            self.sound.pitch = v.clamp(0.5, 2.0);
+                        } else {
+                            self.sound.volume = v.clamp(0.0, 1.0);
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+fn parse_hex_color(s: &str) -> Option<Rgb> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let v = u32::from_str_radix(hex, 16).ok()?;
+    Some(Rgb((v >> 16) as u8, (v >> 8) as u8, v as u8))
+}
+
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|dir| dir.join("flappy-tui"))
+}
+
+// Where runs in progress are kept, which is state rather than settings.
+pub(crate) fn state_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+        .map(|dir| dir.join("flappy-tui"))
+}
+
+fn skin_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("bird.theme"))
+}
+
+// Files in the config directory with the given extension, in name order.
+fn config_files(ext: &str) -> Vec<PathBuf> {
+    let Some(entries) = config_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == ext))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// Kid mode's look and pace, applied over every other theme.
+const KID_THEME: &str = "[pipes]
+style = candy
+
+[sky]
+gradient = #1e90ff, #7fd4ff, #e6f8ff
+
+[bird]
+body = #ffd21f
+wing = #ff6a13
+
+[physics]
+gravity = 0.8
+speed = 0.6
+";
+
+// The theme files, then the first event running today, if `events` allows,
+// then kid mode's KID_THEME over the lot when `kid` is set.
+pub fn load_theme(events: bool, kid: bool) -> Theme {
+    let mut theme = Theme::default();
+    for path in config_files("theme") {
+        if let Ok(text) = fs::read_to_string(path) {
+            theme.apply_fragment(&text);
+        }
+    }
+    if events {
+        let today = today();
+        if let Some(event) = load_events().into_iter().find(|e| e.is_on(today)) {
+            theme.apply_fragment(&event.fragment);
+            theme.event = Some(event.name);
+            theme.points = event.points;
+        }
+    }
+    if kid {
+        theme.apply_fragment(KID_THEME);
+    }
+    theme
+}
+
+// Every look the scene viewer flips between, by name: the stock one, each
+// theme file on its own, each event's, and kid mode's.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn theme_presets() -> Vec<(String, Theme)> {
+    let mut presets = vec![("STOCK".to_string(), Theme::default())];
+    for path in config_files("theme") {
+        let (Ok(text), Some(name)) = (fs::read_to_string(&path), path.file_stem()) else {
+            continue;
+        };
+        let mut theme = Theme::default();
+        theme.apply_fragment(&text);
+        presets.push((name.to_string_lossy().to_uppercase(), theme));
+    }
+    for event in load_events() {
+        let mut theme = Theme::default();
+        theme.apply_fragment(&event.fragment);
+        theme.event = Some(event.name.clone());
+        theme.points = event.points;
+        presets.push((event.name, theme));
+    }
+    let mut kid = Theme::default();
+    kid.apply_fragment(KID_THEME);
+    presets.push(("KID MODE".to_string(), kid));
+    presets
+}
+
+// Signals on `tx` whenever a theme file in the config directory changes, for
+// as long as the returned watcher is kept.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn watch_themes(
+    tx: tokio::sync::mpsc::UnboundedSender<()>,
+) -> Option<notify::RecommendedWatcher> {
+    use notify::Watcher;
+    let dir = config_dir()?;
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let is_theme = |p: &PathBuf| {
+            p.extension()
+                .is_some_and(|ext| ext == "theme" || ext == "event")
+        };
+        if event.is_ok_and(|e| e.paths.iter().any(is_theme)) {
+            let _ = tx.send(());
+        }
+    })
+    .ok()?;
+    watcher
+        .watch(&dir, notify::RecursiveMode::NonRecursive)
+        .ok()?;
+    Some(watcher)
+}
+
+pub(crate) fn save_skin(skin: BirdSkin) -> Result<(), FlappyError> {
+    let path = skin_path().ok_or_else(|| FlappyError::no_config_dir("the bird"))?;
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, skin.to_theme_fragment()))
+        .map_err(FlappyError::config("the bird", path))
+}
+
+// ── Events ──────────────────────────────────────────────────────────────────
+
+// Bundled calendar, in the same format as `*.event` files: an `[event]`
+// section with the name, the dates and a score multiplier, and any theme
+// sections to apply while it runs.
+const BUNDLED_EVENTS: [&str; 3] = [
+    "[event]
+name = Double points weekend
+days = sat, sun
+points = 2
+",
+    "[event]
+name = Spooky season
+from = 10-24
+to = 10-31
+
+[pipes]
+style = candy
+
+[sky]
+gradient = #1d1030, #4a2a5e, #c8587a
+
+[bird]
+body = #f08a24
+wing = #3a2a4a
+",
+    "[event]
+name = Winter holidays
+from = 12-20
+to = 01-02
+
+[pipes]
+style = metal
+
+[sky]
+gradient = #5a7fa8, #b9d3e8, #eef4f8
+",
+];
+
+// A date range (month and day, inclusive, wrapping over new year) and/or a
+// set of weekdays, as a bitmask with Sunday at bit 0.
+struct ScheduledEvent {
+    name: String,
+    dates: Option<((u32, u32), (u32, u32))>,
+    days: u8,
+    points: u32,
+    fragment: String,
+}
+
+impl ScheduledEvent {
+    fn parse(text: &str) -> Option<ScheduledEvent> {
+        let mut event = ScheduledEvent {
+            name: String::new(),
+            dates: None,
+            days: 0,
+            points: 1,
+            fragment: text.to_string(),
+        };
+        let (mut from, mut to) = (None, None);
+        let mut section = "";
+        for line in text.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if section != "event" {
+                continue;
+            }
+            let value = value.trim();
+            let month_day = |v: &str| {
+                let (m, d) = v.split_once('-')?;
+                let (m, d) = (m.parse().ok()?, d.parse().ok()?);
+                ((1..=12).contains(&m) && (1..=31).contains(&d)).then_some((m, d))
+            };
+            match key.trim() {
+                "name" => event.name = value.to_uppercase(),
+                "from" => from = month_day(value),
+                "to" => to = month_day(value),
+                "points" => event.points = value.parse().unwrap_or(1).clamp(1, 10),
+                "days" => {
+                    for day in value.split(',').map(str::trim) {
+                        let names = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+                        if let Some(i) = names.iter().position(|n| day.eq_ignore_ascii_case(n)) {
+                            event.days |= 1 << i;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        event.dates = from.zip(to);
+        let dated = event.dates.is_some() || event.days != 0;
+        (!event.name.is_empty() && dated).then_some(event)
+    }
+
+    fn is_on(&self, (month, day, weekday): (u32, u32, u32)) -> bool {
+        let in_dates = self.dates.is_none_or(|(from, to)| {
+            let today = (month, day);
+            if from <= to {
+                from <= today && today <= to
+            } else {
+                today >= from || today <= to
+            }
+        });
+        let on_day = self.days == 0 || self.days & (1 << weekday) != 0;
+        in_dates && on_day
+    }
+}
+
+// `*.event` files from the config directory, ahead of the bundled ones.
+fn load_events() -> Vec<ScheduledEvent> {
+    let user = config_files("event")
+        .into_iter()
+        .filter_map(|path| fs::read_to_string(path).ok());
+    let bundled = BUNDLED_EVENTS.iter().map(|text| text.to_string());
+    user.chain(bundled)
+        .filter_map(|text| ScheduledEvent::parse(&text))
+        .collect()
+}
+
+// Today's month, day and weekday (Sunday is 0), in local time.
+#[cfg(unix)]
+fn today() -> (u32, u32, u32) {
+    // SAFETY: a null pointer asks `time` for the result only, and
+    // `localtime_r` fills the zeroed `tm` it is given.
+    let tm = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        tm
+    };
+    (tm.tm_mon as u32 + 1, tm.tm_mday as u32, tm.tm_wday as u32)
+}
+
+// Without a time zone database, today in UTC.
+#[cfg(not(unix))]
+fn today() -> (u32, u32, u32) {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400) as i64;
+    // Civil date from days since 1970-01-01, a Thursday.
+    let z = days + 719_468;
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (month, day, (days + 4).rem_euclid(7) as u32)
+}
+
+impl Game {
+    // Reloaded themes leave a bird being edited alone.
+    pub fn apply_theme(&mut self, theme: Theme) {
+        if self.state != State::Editor {
+            self.skin = theme.skin;
+        }
+        self.pipe_style = theme.pipes;
+        self.sky = theme.sky.unwrap_or_else(|| self.config.sky.clone());
+        self.hills = theme.hills;
+        self.tuning = theme.tuning;
+        self.apply_tuning();
+        self.energy_rules = theme.energy;
+        self.gap_rules = theme.gaps;
+        self.sound_palette = theme.sound;
+        self.event = theme.event;
+        self.points = theme.points;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors_need_a_hash_and_six_digits() {
+        assert!(parse_hex_color("#ff8000") == Some(Rgb(255, 128, 0)));
+        assert!(parse_hex_color("ff8000").is_none());
+        assert!(parse_hex_color("#fff").is_none());
+        assert!(parse_hex_color("#gg0000").is_none());
+    }
+
+    #[test]
+    fn fragments_clamp_to_the_allowed_ranges() {
+        let mut theme = Theme::default();
+        theme.apply_fragment(
+            "[physics]\ngravity = 10\nflap = 0.01\nspeed = fast\n\
+             [hills]\namplitude = -1\nfrequency = 9\n\
+             [energy]\ncost = 3\n\
+             [pipes]\nstyle = candy\n\
+             [sky]\ngradient = #000000, #ffffff, #ff0000",
+        );
+        assert_eq!(theme.tuning.gravity, 4.0);
+        assert_eq!(theme.tuning.flap, 0.25);
+        assert_eq!(theme.tuning.speed, 1.0);
+        assert_eq!(theme.hills.amplitude, 1.0);
+        assert_eq!(theme.hills.frequency, 5.0);
+        assert_eq!(theme.energy.cost, 1.0);
+        assert_eq!(theme.pipes.name, "candy");
+        assert_eq!(theme.sky.map(|sky| sky.len()), Some(3));
+    }
+
+    #[test]
+    fn sound_sections_set_the_palette() {
+        let mut theme = Theme::default();
+        theme.apply_fragment("[sound]\nwave = square\nnoise = beige\npitch = 9\nvolume = 0.4");
+        assert!(theme.sound.wave == Some(Wave::Square));
+        assert!(theme.sound.noise == Noise::White);
+        assert_eq!(theme.sound.pitch, 2.0);
+        assert_eq!(theme.sound.volume, 0.4);
+    }
+
+    #[test]
+    fn bad_sky_gradients_are_ignored() {
+        let mut theme = Theme::default();
+        theme.apply_fragment("[sky]\ngradient = #000000\n[sky]\ngradient = #000000, blue");
+        assert!(theme.sky.is_none());
+    }
+
+    #[test]
+    fn events_need_a_name_and_a_date() {
+        assert!(ScheduledEvent::parse("[event]\nname = x").is_none());
+        assert!(ScheduledEvent::parse("[event]\nfrom = 1-1\nto = 1-2").is_none());
+        let weekend = ScheduledEvent::parse("[event]\nname = weekend\ndays = sat, Sun").unwrap();
+        assert_eq!(weekend.name, "WEEKEND");
+        assert!(weekend.is_on((5, 4, 0)));
+        assert!(!weekend.is_on((5, 5, 1)));
+    }
+
+    #[test]
+    fn event_dates_can_wrap_the_new_year() {
+        let text = "[event]\nname = winter\nfrom = 12-20\nto = 1-6\npoints = 50";
+        let winter = ScheduledEvent::parse(text).unwrap();
+        assert_eq!(winter.points, 10);
+        assert!(winter.is_on((12, 25, 3)));
+        assert!(winter.is_on((1, 2, 3)));
+        assert!(!winter.is_on((6, 1, 3)));
+    }
+}
@@ -0,0 +1,1127 @@
+//! Screens drawn over the world: the title, menus, HUD, stats and game-over
+//! panels.
+
+use std::io;
+
+use crate::draw::draw_bird_sprite;
+use crate::game::{
+    BEST_FLASH_FRAMES, BOSS_BANNER_FRAMES, CEILING_WARN_FRAMES, Category, GAME_OVER_DELAY, Game,
+    MILESTONE_FRAMES, MILESTONES, MUTATORS, Medal, RUSH_WARN_FRAMES, RunRecord, SIM_FPS, SKY_H,
+    State, VU, ZONE_BANNER_FRAMES, encode_seed,
+};
+use crate::pixels::{PixelBuf, Rgb, Sprite, SwapChain, draw_text_4x6, text_width_4x6};
+use crate::render::{
+    BIRD_BEAK_HI, BIRD_PUPIL, BIRD_Y, DIRT, FLAPPY_LOGO, MEDAL_ART, PIPE_M, RollingNumber, SHADOW,
+    SKY_TOP, WHITE, draw_flappy_logo, draw_number, flappy_logo_width,
+};
+use crate::theme::{BIRD_PARTS, PLAYGROUND_SLIDERS, SKIN_PALETTE};
+
+// ── Transitions ─────────────────────────────────────────────────────────────
+
+const TRANSITION_FRAMES: f64 = 12.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Screen {
+    Title,
+    Play,
+    GameOver,
+    Menu,
+    TooSmall,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TransitionKind {
+    // Pixels switch over in a scattered order.
+    Dissolve,
+    // The new screen is revealed from the top down.
+    Wipe,
+}
+
+impl TransitionKind {
+    fn between(from: Screen, to: Screen) -> Option<TransitionKind> {
+        match (from, to) {
+            (Screen::TooSmall, _) | (_, Screen::TooSmall) => None,
+            (Screen::Title, Screen::Play) | (Screen::GameOver, Screen::Title) => {
+                Some(TransitionKind::Wipe)
+            }
+            _ => Some(TransitionKind::Dissolve),
+        }
+    }
+}
+
+// Watches for screen changes and blends the last frame of the old screen into
+// the first TRANSITION_FRAMES of the new one.
+pub struct Transitions {
+    screen: Screen,
+    last_px: Vec<Rgb>,
+    last_dither: Vec<bool>,
+    // Progress in simulation steps, which may be fractional between steps.
+    active: Option<(TransitionKind, f64)>,
+}
+
+impl Transitions {
+    pub fn new(screen: Screen) -> Self {
+        Self {
+            screen,
+            last_px: Vec::new(),
+            last_dither: Vec::new(),
+            active: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.active.is_some()
+    }
+
+    // Draws a frame `steps` simulation steps after the previous one.
+    pub fn draw(&mut self, game: &Game, buf: &mut PixelBuf, steps: f64) {
+        game.draw(buf);
+
+        let screen = game.screen();
+        if screen != self.screen {
+            self.active = TransitionKind::between(self.screen, screen)
+                .filter(|_| self.last_px.len() == buf.px.len())
+                .map(|kind| (kind, 0.0));
+            self.screen = screen;
+        }
+        if let Some((kind, frame)) = &mut self.active {
+            *frame += steps;
+            let t = (*frame * 256.0 / TRANSITION_FRAMES) as u32;
+            for y in 0..buf.h {
+                for x in 0..buf.w {
+                    let keep_old = match kind {
+                        TransitionKind::Dissolve => dissolve_rank(x, y) >= t,
+                        TransitionKind::Wipe => (y as u32 * 256) >= t * buf.h as u32,
+                    };
+                    if keep_old {
+                        let i = y * buf.w + x;
+                        buf.px[i] = self.last_px[i];
+                        buf.dither[i] = self.last_dither[i];
+                    }
+                }
+            }
+            if *frame >= TRANSITION_FRAMES {
+                self.active = None;
+            }
+        }
+
+        self.last_px.clone_from(&buf.px);
+        self.last_dither.clone_from(&buf.dither);
+    }
+}
+
+// Pseudo-random 0..256 per pixel, fixed so a dissolve doesn't shimmer.
+fn dissolve_rank(x: usize, y: usize) -> u32 {
+    let h = (x as u32).wrapping_mul(0x9e37_79b1) ^ (y as u32).wrapping_mul(0x85eb_ca6b);
+    (h ^ (h >> 15)).wrapping_mul(0x2c1b_3c6d) >> 24
+}
+
+// ── Screens ─────────────────────────────────────────────────────────────────
+
+// Pixels on screen a menu item or button covers: x, y, width and height.
+type UiRect = (i32, i32, i32, i32);
+
+// How far a dozing title is dimmed toward black, out of 256.
+const DOZE_DIM: u16 = 170;
+const TITLE_PROMPT: &str = "SPACE TO FLAP";
+const TITLE_PROMPT_TOUCH: &str = "TAP TO FLAP";
+
+const MUTED_ICON_FRAMES: u64 = 60;
+#[rustfmt::skip]
+const MUTED_ART: [&str; 7] = [
+    "...W......",
+    "..WW.X...X",
+    "WWWW..X.X.",
+    "WWWW...X..",
+    "WWWW..X.X.",
+    "..WW.X...X",
+    "...W......",
+];
+
+impl Game {
+    pub(crate) fn open_mutators(&mut self) {
+        self.open(State::Mutators);
+    }
+
+    pub(crate) fn close_mutators(&mut self) {
+        self.close();
+    }
+
+    pub(crate) fn open_stats(&mut self) {
+        let categories = self.stats_categories();
+        let current = self.category();
+        self.stats_category = categories.iter().position(|c| *c == current).unwrap_or(0);
+        self.open(State::Stats);
+    }
+
+    // Categories with runs in the history, most recently played first.
+    fn stats_categories(&self) -> Vec<Category> {
+        let mut categories = Vec::new();
+        for run in self.runs.iter().rev() {
+            if !categories.contains(&run.category) {
+                categories.push(run.category);
+            }
+        }
+        categories
+    }
+
+    pub(crate) fn stats_select(&mut self, delta: i32) {
+        let n = self.stats_categories().len().max(1) as i32;
+        self.stats_category = (self.stats_category as i32 + delta).rem_euclid(n) as usize;
+    }
+
+    pub(crate) fn close_stats(&mut self) {
+        self.close();
+    }
+
+    pub(crate) fn open_playground(&mut self) {
+        self.open(State::Playground);
+    }
+
+    pub(crate) fn close_playground(&mut self) {
+        self.close();
+    }
+
+    pub(crate) fn playground_select(&mut self, delta: i32) {
+        let n = PLAYGROUND_SLIDERS.len() as i32;
+        self.playground_cursor = (self.playground_cursor as i32 + delta).rem_euclid(n) as usize;
+    }
+
+    // Slider values feed the tuning every run starts from, so they last
+    // until the theme is reloaded.
+    pub(crate) fn playground_adjust(&mut self, i: usize, steps: f64) {
+        let (_, lo, hi, step) = PLAYGROUND_SLIDERS[i];
+        let v = self.tuning.slider(i);
+        *v = ((*v / step).round() + steps).clamp(lo / step, hi / step) * step;
+        self.apply_tuning();
+    }
+
+    // Sets slider `i` to `t` of the way along its range, 0.0 to 1.0.
+    pub(crate) fn playground_set(&mut self, i: usize, t: f64) {
+        let (_, lo, hi, step) = PLAYGROUND_SLIDERS[i];
+        *self.tuning.slider(i) = lo + ((hi - lo) * t.clamp(0.0, 1.0) / step).round() * step;
+        self.apply_tuning();
+    }
+
+    pub(crate) fn mutators_select(&mut self, delta: i32) {
+        let n = MUTATORS.len() as i32;
+        self.mutator_cursor = (self.mutator_cursor as i32 + delta).rem_euclid(n) as usize;
+    }
+
+    pub(crate) fn mutators_toggle(&mut self) {
+        self.mutators.toggle(MUTATORS[self.mutator_cursor]);
+    }
+
+    // A crossed-out speaker in the top right for a moment after sound stops.
+    pub(crate) fn draw_muted_icon(&self, buf: &mut PixelBuf) {
+        // Restoring a snapshot can put the frame back before `at`.
+        if let Some(at) = self.muted_at
+            && self.frame.saturating_sub(at) < MUTED_ICON_FRAMES
+        {
+            let x = self.pw as i32 - MUTED_ART[0].len() as i32 - 4;
+            let shadow = Sprite::from_art(&MUTED_ART, &[('W', SHADOW), ('X', SHADOW)]);
+            buf.blit(&shadow, x + 1, 5, 1.0, false);
+            let icon = Sprite::from_art(&MUTED_ART, &[('W', WHITE), ('X', Rgb(230, 40, 40))]);
+            buf.blit(&icon, x, 4, 1.0, false);
+        }
+    }
+
+    // Glide stamina as a bar in the top-left corner, red when nearly spent.
+    pub(crate) fn draw_stamina(&self, buf: &mut PixelBuf) {
+        if !matches!(self.state, State::Ready | State::Playing) {
+            return;
+        }
+        let (x, y, w, h) = (3, 3, 16, 2);
+        buf.fill_rect(x - 1, y - 1, w + 2, h + 2, SHADOW);
+        buf.fill_rect(x, y, w, h, Rgb(60, 60, 70));
+        let color = if self.stamina < 0.25 {
+            Rgb(220, 70, 60)
+        } else {
+            Rgb(120, 200, 240)
+        };
+        let fill = (self.stamina * w as f64).round() as i32;
+        buf.fill_rect(x, y, fill, h, color);
+    }
+
+    // A thin bar along the top edge filling toward the best score, so how
+    // close a run is shows without reading digits. It flashes as the run
+    // passes the best and then stays gold. Runs with no best yet have nothing
+    // to fill toward.
+    pub(crate) fn draw_best_progress(&self, buf: &mut PixelBuf) {
+        if !matches!(self.state, State::Playing | State::Paused) || self.best == 0 {
+            return;
+        }
+        let w = self.pw as i32;
+        buf.fill_rect(0, 0, w, 1, SHADOW);
+        let color = match self.best_passed_at {
+            // The replay goes back to frames from before the best was passed.
+            Some(at) if self.frame.saturating_sub(at) < BEST_FLASH_FRAMES => {
+                if self.frame.saturating_sub(at) % 8 < 4 {
+                    WHITE
+                } else {
+                    BIRD_Y
+                }
+            }
+            Some(_) => BIRD_Y,
+            None => WHITE,
+        };
+        let fill = (self.score.min(self.best) as u64 * w as u64 / self.best as u64) as i32;
+        buf.fill_rect(0, 0, fill, 1, color);
+    }
+
+    // The milestone text crosses from the right edge to the left on a ribbon
+    // under the other banners.
+    pub(crate) fn draw_milestone(&self, buf: &mut PixelBuf) {
+        let Some((i, at)) = self.milestone else {
+            return;
+        };
+        let age = self.frame.saturating_sub(at);
+        if age >= MILESTONE_FRAMES {
+            return;
+        }
+        let text = MILESTONES[i].1;
+        let w = text_width_4x6(text, 1);
+        let travel = self.pw as i32 + w;
+        let x = self.pw as i32 - (travel as u64 * age / MILESTONE_FRAMES) as i32;
+        let y = 26;
+        for ry in y - 2..y + 8 {
+            for rx in 0..self.pw as i32 {
+                buf.blend(rx, ry, SHADOW, 140);
+            }
+        }
+        draw_text_4x6(buf, x, y, text, BIRD_Y, 1);
+    }
+
+    fn draw_boss_banner(&self, buf: &mut PixelBuf) {
+        if let Some(enc) = &self.encounter
+            && enc.frame < BOSS_BANNER_FRAMES
+            && (enc.frame / 8) % 2 == 0
+        {
+            self.draw_banner(buf, "CRUSHERS!", BIRD_Y);
+        }
+    }
+
+    // Centered, shadowed text where the zone and warning banners go.
+    fn draw_banner(&self, buf: &mut PixelBuf, text: &str, color: Rgb) {
+        let w = text_width_4x6(text, 1);
+        let x = (self.pw as i32 - w) / 2;
+        draw_text_4x6(buf, x + 1, 17, text, SHADOW, 1);
+        draw_text_4x6(buf, x, 16, text, color, 1);
+    }
+
+    fn draw_rush_banner(&self, buf: &mut PixelBuf) {
+        if let Some(rush) = &self.rush
+            && rush.frame < RUSH_WARN_FRAMES
+            && (rush.frame / 6) % 2 == 0
+        {
+            self.draw_banner(buf, "RUSH HOUR!", BIRD_BEAK_HI);
+        }
+    }
+
+    fn draw_ceiling_banner(&self, buf: &mut PixelBuf) {
+        if let Some(low) = &self.low_ceiling
+            && low.frame < CEILING_WARN_FRAMES
+            && (low.frame / 6) % 2 == 0
+        {
+            self.draw_banner(buf, "LOW CEILING!", WHITE);
+        }
+    }
+
+    fn draw_zone_banner(&self, buf: &mut PixelBuf) {
+        if let Some((medal, at)) = self.zone_banner
+            && self.frame.saturating_sub(at) < ZONE_BANNER_FRAMES
+        {
+            self.draw_banner(buf, medal.zone_name(), medal.color());
+        }
+    }
+
+    pub(crate) fn draw_replay_mark(&self, buf: &mut PixelBuf) {
+        if self.frame % 30 < 20 {
+            buf.fill_rect(4, 5, 4, 4, Rgb(230, 40, 40));
+        }
+        draw_text_4x6(buf, 11, 5, "REPLAY", SHADOW, 1);
+        draw_text_4x6(buf, 10, 4, "REPLAY", WHITE, 1);
+    }
+
+    pub(crate) fn draw_score(&self, buf: &mut PixelBuf) {
+        self.score_roll
+            .draw(buf, self.pw as i32 / 2, 4, self.score, self.frame, WHITE);
+        if self.hint && self.state != State::Ready {
+            let y = self.cam.y(SKY_H) + 3;
+            draw_text_4x6(buf, 3, y + 1, "ASSIST", SHADOW, 1);
+            draw_text_4x6(buf, 2, y, "ASSIST", WHITE, 1);
+        }
+        if let Some(adaptive) = &self.adaptive {
+            self.draw_adaptive_level(buf, adaptive.level);
+        }
+        if self.state == State::Paused {
+            let text = "PAUSED";
+            let w = text_width_4x6(text, 2);
+            let (tx, ty) = ((self.pw as i32 - w) / 2, self.ph as i32 / 3);
+            draw_text_4x6(buf, tx + 1, ty + 1, text, SHADOW, 2);
+            draw_text_4x6(buf, tx, ty, text, WHITE, 2);
+        }
+        self.draw_zone_banner(buf);
+        self.draw_boss_banner(buf);
+        self.draw_rush_banner(buf);
+        self.draw_ceiling_banner(buf);
+        if self.show_hud {
+            self.draw_tuning_hud(buf);
+        }
+    }
+
+    // Five small pips over the ground in the bottom right, one lit for the
+    // level: the left ones for eased off, the middle for stock.
+    fn draw_adaptive_level(&self, buf: &mut PixelBuf, level: f64) {
+        let lit = ((level + 1.0) * 2.0).round() as i32;
+        let y = self.cam.y(SKY_H) + 4;
+        let x = self.pw as i32 - 3 - 5 * 4;
+        for i in 0..5 {
+            let px = x + i * 4;
+            if i == lit {
+                buf.fill_rect(px, y, 3, 3, WHITE);
+            } else {
+                for dy in 0..3 {
+                    for dx in 0..3 {
+                        buf.blend(px + dx, y + dy, SHADOW, 120);
+                    }
+                }
+            }
+        }
+    }
+
+    // Rows of the tuning HUD, for gravity, flap and speed: (x, y, w, h).
+    pub(crate) fn tuning_rows(&self) -> [UiRect; 3] {
+        let y = self.cam.y(SKY_H) - 8;
+        let x = self.pw as i32 - 30;
+        [0, 1, 2].map(|i| (x, y - 7 * i - 1, 28, 7))
+    }
+
+    fn draw_tuning_hud(&self, buf: &mut PixelBuf) {
+        let values = [
+            (self.gravity * 100.0) as u32,
+            (-self.flap_vel * 100.0) as u32,
+            (self.pipe_speed * 100.0) as u32,
+        ];
+        let colors = [Rgb(180, 180, 255), Rgb(255, 180, 180), Rgb(180, 255, 180)];
+        for (i, rect) in self.tuning_rows().into_iter().enumerate() {
+            // The hovered value can be scrolled.
+            if self.hovers(rect) {
+                let (x, y, w, h) = rect;
+                for ry in y..y + h {
+                    for rx in x..x + w {
+                        buf.blend(rx, ry, SHADOW, 120);
+                    }
+                }
+            }
+            draw_number(buf, rect.0 + 6, rect.1 + 1, values[i], colors[i]);
+        }
+    }
+
+    pub(crate) fn hovers(&self, (x, y, w, h): UiRect) -> bool {
+        self.pointer
+            .is_some_and(|(px, py)| (x..x + w).contains(&px) && (y..y + h).contains(&py))
+    }
+
+    fn status_line(&self, fps: u32) -> String {
+        let code = self.mutators.code();
+        let mode = if code.is_empty() { "CLASSIC" } else { &code };
+        let assist = if self.hint { " ASSIST" } else { "" };
+        format!(
+            " SCORE {}  BEST {}  MODE {}{}  FPS {}",
+            self.score, self.best, mode, assist, fps
+        )
+    }
+
+    pub(crate) fn open_editor(&mut self) {
+        self.open(State::Editor);
+    }
+
+    pub(crate) fn close_editor(&mut self, keep: bool) {
+        if !keep {
+            self.skin = self.editor_backup;
+        }
+        self.close();
+    }
+
+    pub(crate) fn editor_select(&mut self, delta: i32) {
+        let n = BIRD_PARTS.len() as i32;
+        self.editor_part = (self.editor_part as i32 + delta).rem_euclid(n) as usize;
+    }
+
+    pub(crate) fn editor_pick(&mut self, swatch: usize) {
+        let part = BIRD_PARTS[self.editor_part];
+        self.skin.set(part, SKIN_PALETTE[swatch].0);
+    }
+
+    pub(crate) fn editor_cycle(&mut self, delta: i32) {
+        let part = BIRD_PARTS[self.editor_part];
+        let current = self.skin.color(part);
+        let n = SKIN_PALETTE.len() as i32;
+        let idx = SKIN_PALETTE
+            .iter()
+            .position(|(c, _)| *c == current)
+            .map_or(0, |i| i as i32 + delta);
+        self.skin
+            .set(part, SKIN_PALETTE[idx.rem_euclid(n) as usize].0);
+    }
+
+    pub(crate) fn draw_editor(&self, buf: &mut PixelBuf) {
+        // The sky stops at the ground, which the editor leaves out.
+        buf.clear(SKY_TOP);
+        self.draw_sky(buf, &self.cam);
+        let cx = self.pw as i32 / 2;
+        let label_color = Rgb(80, 60, 20);
+
+        // Part selector: "<BODY>"
+        let part = BIRD_PARTS[self.editor_part];
+        let label = format!("<{}>", part.name().to_uppercase());
+        let label_w = text_width_4x6(&label, 1);
+        let (x, y, w, h) = self.editor_part_label();
+        let hovered = self.hovers((x, y, w, h));
+        buf.fill_rect(x, y, w, h, if hovered { BIRD_Y } else { WHITE });
+        draw_text_4x6(buf, cx - label_w / 2, 3, &label, label_color, 1);
+
+        // Live preview on a large bird.
+        let s = (self.ph as f64 / 48.0 * 3.0).min(self.pw as f64 / 14.0);
+        let wing_y_off = if self.frame % 16 < 8 { -1 } else { 1 };
+        draw_bird_sprite(
+            buf,
+            cx,
+            (self.ph as i32 / 2 - 2) as f64,
+            s,
+            0,
+            wing_y_off,
+            &self.skin,
+        );
+
+        // Palette swatches, current color outlined, hovered one in gold.
+        let current = self.skin.color(part);
+        for (i, (c, _)) in SKIN_PALETTE.iter().enumerate() {
+            let (x, y, w, h) = self.editor_swatch(i);
+            let outline = if *c == current {
+                WHITE
+            } else if self.hovers((x, y, w, h)) {
+                BIRD_Y
+            } else {
+                SHADOW
+            };
+            buf.fill_rect(x, y, w, h, outline);
+            buf.fill_rect(x + 1, y + 1, w - 2, h - 2, *c);
+        }
+
+        let hint = "ENTER SAVE";
+        let hint_w = text_width_4x6(hint, 1);
+        if hint_w < self.pw as i32 {
+            let color = if self.hovers(self.editor_save_hint()) {
+                WHITE
+            } else {
+                SHADOW
+            };
+            draw_text_4x6(buf, cx - hint_w / 2, self.ph as i32 - 7, hint, color, 1);
+        }
+    }
+
+    // Click targets in the editor, as (x, y, w, h): the part selector, the
+    // palette swatches and the save hint.
+    pub(crate) fn editor_part_label(&self) -> UiRect {
+        let label = format!("<{}>", BIRD_PARTS[self.editor_part].name());
+        let w = text_width_4x6(&label, 1) + 4;
+        (self.pw as i32 / 2 - w / 2, 2, w, 9)
+    }
+
+    pub(crate) fn editor_swatch(&self, i: usize) -> UiRect {
+        let n = SKIN_PALETTE.len() as i32;
+        let sw = ((self.pw as i32 - 4) / n).clamp(3, 6);
+        let row_x = self.pw as i32 / 2 - (sw * n) / 2;
+        let row_y = self.ph as i32 - sw - 10;
+        (row_x + i as i32 * sw, row_y - 1, sw, sw + 1)
+    }
+
+    pub(crate) fn editor_save_hint(&self) -> UiRect {
+        let w = text_width_4x6("ENTER SAVE", 1);
+        (self.pw as i32 / 2 - w / 2, self.ph as i32 - 8, w, 8)
+    }
+
+    // The prompt under the logo and its text scale; touch screens get a
+    // double-size one where it fits.
+    fn title_prompt(&self) -> (&'static str, i32) {
+        if !self.touch {
+            (TITLE_PROMPT, 1)
+        } else if text_width_4x6(TITLE_PROMPT_TOUCH, 2) + 4 <= self.pw as i32 {
+            (TITLE_PROMPT_TOUCH, 2)
+        } else {
+            (TITLE_PROMPT_TOUCH, 1)
+        }
+    }
+
+    // The prompt box under the logo, which a click also starts from.
+    fn title_button(&self) -> UiRect {
+        let (text, scale) = self.title_prompt();
+        let title_h = FLAPPY_LOGO.len() as i32 * 2;
+        let w = text_width_4x6(text, scale) + 4;
+        let h = 6 * scale + 2;
+        (
+            self.pw as i32 / 2 - w / 2,
+            self.ph as i32 / 3 + title_h + 4,
+            w,
+            h,
+        )
+    }
+
+    pub(crate) fn draw_title(&self, buf: &mut PixelBuf) {
+        let cx = self.pw as i32 / 2;
+        let cy = self.ph as i32 / 3;
+        let title_scale = 1;
+        let title_w = flappy_logo_width(title_scale);
+        let title_x = cx - title_w / 2;
+
+        draw_flappy_logo(buf, title_x, cy, title_scale);
+
+        // Subtitle in a white box (gold under the mouse) with dark text.
+        let (box_x, box_y, box_w, box_h) = self.title_button();
+        let fill = if self.hovers((box_x, box_y, box_w, box_h)) {
+            BIRD_Y
+        } else {
+            WHITE
+        };
+        buf.fill_rect(box_x - 1, box_y - 1, box_w + 2, box_h + 1, SHADOW);
+        buf.fill_rect(box_x, box_y, box_w, box_h - 1, fill);
+        let (text, scale) = self.title_prompt();
+        draw_text_4x6(buf, box_x + 2, box_y + 1, text, BIRD_PUPIL, scale);
+
+        // A run to resume, or else the running event, on a full-width ribbon,
+        // scrolling if it's too long.
+        let ribbon = match (&self.resume, &self.event) {
+            (Some((_, snap)), _) => Some(format!("RESUME RUN AT {}? Y/N", snap.score)),
+            (None, Some(name)) if self.points > 1 => Some(format!("{name}  X{}", self.points)),
+            (None, name) => name.clone(),
+        };
+        if let Some(text) = ribbon {
+            let w = text_width_4x6(&text, 1);
+            let y = box_y + box_h + 4;
+            buf.fill_rect(0, y - 1, self.pw as i32, 9, SHADOW);
+            buf.fill_rect(0, y, self.pw as i32, 7, BIRD_Y);
+            let x = if w + 4 <= self.pw as i32 {
+                cx - w / 2
+            } else {
+                let travel = self.pw as i32 + w;
+                self.pw as i32 - (self.frame % travel as u64) as i32
+            };
+            draw_text_4x6(buf, x, y + 1, &text, BIRD_PUPIL, 1);
+        }
+
+        // A newer release, quietly in the bottom corner.
+        if let Some(version) = &self.update {
+            let text = format!("V{version} AVAILABLE");
+            let y = self.ph as i32 - 8;
+            draw_text_4x6(buf, 3, y + 1, &text, SHADOW, 1);
+            draw_text_4x6(buf, 2, y, &text, WHITE, 1);
+        }
+    }
+
+    // A dozing title is dimmed, so the rare frames it still draws are dark.
+    pub(crate) fn draw_doze(&self, buf: &mut PixelBuf) {
+        for y in 0..buf.h as i32 {
+            for x in 0..buf.w as i32 {
+                buf.blend(x, y, Rgb(0, 0, 0), DOZE_DIM);
+            }
+        }
+    }
+
+    pub(crate) fn draw_too_small(&self, buf: &mut PixelBuf) {
+        buf.fill_rect(0, 0, self.pw as i32, self.ph as i32, Rgb(20, 20, 30));
+
+        let mut center_text = |y: i32, msg: &str, color: Rgb| {
+            let width = text_width_4x6(msg, 1);
+            let center = self.pw as i32 / 2;
+            draw_text_4x6(buf, center - width / 2, y, msg, color, 1);
+        };
+
+        let center = self.ph as i32 / 2;
+        center_text(center - 13, "TOO", Rgb(200, 80, 80));
+        center_text(center - 5, "SMALL", Rgb(200, 80, 80));
+        center_text(center + 3, "PLEASE", Rgb(160, 160, 160));
+        center_text(center + 11, "RESIZE", Rgb(160, 160, 160));
+    }
+
+    // The mutators panel, (x, y, w, h), and the rows shown on it by index
+    // into MUTATORS, each with the rect it highlights.
+    pub(crate) fn mutators_layout(&self) -> (UiRect, Vec<(usize, UiRect)>) {
+        let row_h = if self.touch {
+            10
+        } else if self.ph >= 60 {
+            7
+        } else {
+            6
+        };
+        let panel_w = 42.min(self.pw as i32 - 2);
+        // Scroll the list when the screen is too short for every row.
+        let visible = ((self.ph as i32 - 13) / row_h).clamp(1, MUTATORS.len() as i32) as usize;
+        let first = (self.mutator_cursor + 1).saturating_sub(visible);
+        let panel_h = 11 + row_h * visible as i32;
+        let px = self.pw as i32 / 2 - panel_w / 2;
+        let py = (self.ph as i32 - panel_h) / 2;
+        let rows = (first..first + visible)
+            .map(|i| {
+                let y = py + 10 + (i - first) as i32 * row_h;
+                (i, (px + 1, y - 1, panel_w - 2, row_h - 1))
+            })
+            .collect();
+        ((px, py, panel_w, panel_h), rows)
+    }
+
+    pub(crate) fn draw_mutators(&self, buf: &mut PixelBuf) {
+        let cx = self.pw as i32 / 2;
+        let label_color = Rgb(80, 60, 20);
+        let ((px, py, panel_w, panel_h), rows) = self.mutators_layout();
+
+        buf.fill_rect(px - 1, py - 1, panel_w + 2, panel_h + 2, SHADOW);
+        buf.fill_rect(px, py, panel_w, panel_h, DIRT);
+        buf.fill_rect(px + 1, py + 1, panel_w - 2, panel_h - 2, Rgb(220, 195, 120));
+
+        let title = if panel_w >= 42 { "MUTATORS" } else { "MODS" };
+        draw_text_4x6(
+            buf,
+            cx - text_width_4x6(title, 1) / 2,
+            py + 3,
+            title,
+            label_color,
+            1,
+        );
+
+        for (i, (x, y, w, h)) in rows {
+            let m = MUTATORS[i];
+            if i == self.mutator_cursor {
+                buf.fill_rect(x, y, w, h, WHITE);
+            }
+            let y = y + 1;
+            let box_color = if self.mutators.has(m) { PIPE_M } else { SHADOW };
+            buf.fill_rect(px + 3, y + 1, 3, 3, box_color);
+            draw_text_4x6(buf, px + 8, y, m.label(), label_color, 1);
+        }
+    }
+
+    // The playground panel, (x, y, w, h), and each slider's row and track.
+    pub(crate) fn playground_layout(&self) -> (UiRect, [(UiRect, UiRect); 4]) {
+        let row_h = if self.touch { 10 } else { 7 };
+        let label_w = text_width_4x6("SPEED", 1) + 3;
+        let value_w = text_width_4x6("0.00", 1) + 3;
+        let track_w = (self.pw as i32 / 4).clamp(12, 48);
+        let panel_w = label_w + track_w + value_w + 4;
+        let panel_h = row_h * PLAYGROUND_SLIDERS.len() as i32 + if self.ph >= 80 { 12 } else { 4 };
+        let (px, py) = (self.pw as i32 - panel_w - 2, 2);
+        let rows = [0, 1, 2, 3].map(|i| {
+            let y = py + 2 + row_h * i;
+            let track = (px + 2 + label_w, y + row_h / 2 - 1, track_w, 3);
+            ((px + 1, y, panel_w - 2, row_h), track)
+        });
+        ((px, py, panel_w, panel_h), rows)
+    }
+
+    // Sliders over a see-through panel, so the bird and its arc show
+    // underneath, and on taller screens how high and how long a flap goes.
+    pub(crate) fn draw_playground(&self, buf: &mut PixelBuf) {
+        let ((px, py, panel_w, panel_h), rows) = self.playground_layout();
+        for y in py..py + panel_h {
+            for x in px..px + panel_w {
+                buf.blend(x, y, SHADOW, 150);
+            }
+        }
+        let mut tuning = self.tuning;
+        for (i, (row, (tx, ty, tw, th))) in rows.into_iter().enumerate() {
+            let (label, lo, hi, _) = PLAYGROUND_SLIDERS[i];
+            let (rx, ry, _, rh) = row;
+            let color = if i == self.playground_cursor || self.hovers(row) {
+                BIRD_Y
+            } else {
+                WHITE
+            };
+            let v = *tuning.slider(i);
+            draw_text_4x6(buf, rx + 1, ry + (rh - 6) / 2, label, color, 1);
+            buf.fill_rect(tx, ty + 1, tw, th - 2, SHADOW);
+            let knob = tx + ((v - lo) / (hi - lo) * (tw - 2) as f64).round() as i32;
+            buf.fill_rect(tx, ty + 1, knob - tx, th - 2, color);
+            buf.fill_rect(knob, ty - 1, 2, th + 2, color);
+            let value = if hi > 4.0 {
+                format!("{v:.1}")
+            } else {
+                format!("{v:.2}")
+            };
+            draw_text_4x6(buf, tx + tw + 3, ry + (rh - 6) / 2, &value, color, 1);
+        }
+        if self.ph >= 80 {
+            // A flap from rest tops out at v^2 / 2g and lands after 2v / g.
+            let (v, g) = (-self.flap_vel, self.gravity);
+            let jump = (v * v / (2.0 * g) * 100.0 / SKY_H).round();
+            let air = 2.0 * v / g / SIM_FPS as f64;
+            let text = format!("JUMP {jump}%  AIR {air:.1}S");
+            draw_text_4x6(buf, px + 3, py + panel_h - 8, &text, WHITE, 1);
+        }
+    }
+
+    // Scores of the most recent runs in one category that fit, oldest on the
+    // left with new bests in gold, over a histogram of every score in it.
+    pub(crate) fn draw_stats(&self, buf: &mut PixelBuf) {
+        let label_color = Rgb(80, 60, 20);
+        let chart_bg = Rgb(200, 172, 100);
+        let panel_w = 120.min(self.pw as i32 - 2);
+        let panel_h = 72.min(self.ph as i32 - 2);
+        let px = (self.pw as i32 - panel_w) / 2;
+        let py = (self.ph as i32 - panel_h) / 2;
+
+        buf.fill_rect(px - 1, py - 1, panel_w + 2, panel_h + 2, SHADOW);
+        buf.fill_rect(px, py, panel_w, panel_h, DIRT);
+        buf.fill_rect(px + 1, py + 1, panel_w - 2, panel_h - 2, Rgb(220, 195, 120));
+
+        let cx = px + panel_w / 2;
+        let center_text = |buf: &mut PixelBuf, y: i32, text: &str| {
+            draw_text_4x6(
+                buf,
+                cx - text_width_4x6(text, 1) / 2,
+                y,
+                text,
+                label_color,
+                1,
+            );
+        };
+        let categories = self.stats_categories();
+        let Some(&category) = categories.get(self.stats_category) else {
+            center_text(buf, py + 3, "STATS");
+            center_text(buf, py + panel_h / 2, "NO RUNS YET");
+            return;
+        };
+        let label = Some(category.label(false))
+            .filter(|label| text_width_4x6(label, 1) <= panel_w - 16)
+            .unwrap_or_else(|| category.label(true));
+        center_text(buf, py + 3, &label);
+        if categories.len() > 1 {
+            draw_text_4x6(buf, px + 3, py + 3, "<", label_color, 1);
+            let right = px + panel_w - 3 - text_width_4x6(">", 1);
+            draw_text_4x6(buf, right, py + 3, ">", label_color, 1);
+        }
+
+        let runs: Vec<RunRecord> = (self.runs.iter())
+            .filter(|r| r.category == category)
+            .copied()
+            .collect();
+        let scores: Vec<u32> = runs.iter().map(|r| r.score).collect();
+        let max = scores.iter().copied().max().unwrap_or(0);
+        let mean = scores.iter().map(|&s| s as f64).sum::<f64>() / scores.len() as f64;
+        let mut top = py + 10;
+        // Short panels skip the summary line to leave room for the charts.
+        if panel_h >= 60 {
+            let summary = format!("RUNS {} BEST {} AVG {:.1}", scores.len(), max, mean);
+            let summary = if text_width_4x6(&summary, 1) <= panel_w - 4 {
+                summary
+            } else {
+                format!("BEST {max} AVG {mean:.1}")
+            };
+            center_text(buf, top, &summary);
+            top += 8;
+        }
+
+        let (x0, w) = (px + 3, panel_w - 6);
+        let h = ((py + panel_h - 3 - top - 17) / 2).max(2);
+        let bar_height = |v: u32, of: u32| (v as i64 * h as i64 / of.max(1) as i64) as i32;
+
+        // Score over time: one bar per run, up to four pixels a run.
+        let shown = scores.len().min(w as usize);
+        let recent = &scores[scores.len() - shown..];
+        let span = (w as f64 / shown as f64).min(4.0);
+        let mut best = scores[..scores.len() - shown].iter().copied().max();
+        draw_text_4x6(buf, x0, top, &format!("LAST {shown}"), label_color, 1);
+        let top_label = max.to_string();
+        draw_text_4x6(
+            buf,
+            x0 + w - text_width_4x6(&top_label, 1),
+            top,
+            &top_label,
+            label_color,
+            1,
+        );
+        let chart_y = top + 7;
+        buf.fill_rect(x0, chart_y, w, h, chart_bg);
+        for (i, &score) in recent.iter().enumerate() {
+            let color = if best.is_none_or(|b| score > b) {
+                BIRD_Y
+            } else {
+                PIPE_M
+            };
+            best = best.max(Some(score));
+            let bh = bar_height(score, max);
+            let x = x0 + (i as f64 * span) as i32;
+            let next = x0 + ((i + 1) as f64 * span) as i32;
+            // Gaps between bars only once there's room for them.
+            let bar_w = if span >= 2.0 { next - x - 1 } else { next - x };
+            buf.fill_rect(x, chart_y + h - bh, bar_w.max(1), bh, color);
+        }
+
+        // Distribution: up to ten buckets of equal width from zero to the best.
+        let buckets = (max as usize + 1).min(10);
+        let size = (max as usize + 1).div_ceil(buckets);
+        let mut counts = vec![0u32; buckets];
+        for &score in &scores {
+            counts[score as usize / size] += 1;
+        }
+        let most = counts.iter().copied().max().unwrap_or(1);
+        let top = chart_y + h + 3;
+        draw_text_4x6(buf, x0, top, "SCORES", label_color, 1);
+        let range = format!("0-{max}");
+        draw_text_4x6(
+            buf,
+            x0 + w - text_width_4x6(&range, 1),
+            top,
+            &range,
+            label_color,
+            1,
+        );
+        let chart_y = top + 7;
+        buf.fill_rect(x0, chart_y, w, h, chart_bg);
+        let bucket_w = w / buckets as i32;
+        for (i, &count) in counts.iter().enumerate() {
+            let bh = bar_height(count, most);
+            let x = x0 + i as i32 * bucket_w;
+            buf.fill_rect(x, chart_y + h - bh, (bucket_w - 1).max(1), bh, PIPE_M);
+        }
+    }
+
+    // Touch buttons in the top corners: pause on the left, restart on the right.
+    pub(crate) fn pause_button(&self) -> UiRect {
+        let size = (self.ph as i32 / 6).clamp(9, 16);
+        (2, 2, size, size)
+    }
+
+    pub(crate) fn restart_button(&self) -> UiRect {
+        let size = (self.ph as i32 / 6).clamp(9, 16);
+        (self.pw as i32 - 2 - size, 2, size, size)
+    }
+
+    pub(crate) fn draw_touch_buttons(&self, buf: &mut PixelBuf) {
+        if !matches!(self.state, State::Playing | State::Paused | State::Dying) {
+            return;
+        }
+        for rect in [self.pause_button(), self.restart_button()] {
+            let (x, y, w, h) = rect;
+            for ry in y..y + h {
+                for rx in x..x + w {
+                    buf.blend(rx, ry, SHADOW, 150);
+                }
+            }
+        }
+        let icon = |rect| if self.hovers(rect) { BIRD_Y } else { WHITE };
+
+        // Two bars to pause, a triangle to resume.
+        let (x, y, size, _) = self.pause_button();
+        let inset = size / 4;
+        let color = icon(self.pause_button());
+        if self.state == State::Paused {
+            let h = size - inset * 2;
+            for r in 0..h {
+                let len = h / 2 - (r - h / 2).abs() + 1;
+                buf.fill_rect(x + inset + 1, y + inset + r, len, 1, color);
+            }
+        } else {
+            let bar = ((size - inset * 2) / 3).max(1);
+            for bx in [x + inset, x + size - inset - bar] {
+                buf.fill_rect(bx, y + inset, bar, size - inset * 2, color);
+            }
+        }
+
+        // A clockwise arrow: a ring open at the top right, its head at the top.
+        let (x, y, size, _) = self.restart_button();
+        let color = icon(self.restart_button());
+        let c = size as f64 / 2.0;
+        let r = c - size as f64 / 4.0;
+        for py in 0..size {
+            for px in 0..size {
+                let (dx, dy) = (px as f64 + 0.5 - c, c - (py as f64 + 0.5));
+                let angle = dy.atan2(dx).to_degrees();
+                if ((dx * dx + dy * dy).sqrt() - r).abs() < 0.8 && !(15.0..90.0).contains(&angle) {
+                    buf.set(x + px, y + py, color);
+                }
+            }
+        }
+        let (head_x, head_y) = (x + c as i32 - 1, y + (c - r) as i32);
+        for k in 0..3 {
+            buf.fill_rect(head_x + k, head_y - 2 + k, 1, 5 - k * 2, color);
+        }
+    }
+
+    pub(crate) fn game_over_panel(&self) -> UiRect {
+        let w = (30.0 * VU * self.cam.scale).clamp(30.0, 50.0) as i32;
+        let h = 34;
+        (self.pw as i32 / 2 - w / 2, self.ph as i32 / 2 - h / 2, w, h)
+    }
+
+    pub(crate) fn draw_game_over(&self, buf: &mut PixelBuf) {
+        if self.screen() != Screen::GameOver {
+            return;
+        }
+        let cx = self.pw as i32 / 2;
+        let (px, py, panel_w, panel_h) = self.game_over_panel();
+
+        // Dark overlay
+        for y in 0..self.ph {
+            for x in 0..self.pw {
+                let c = buf.get(x, y);
+                buf.set(x as i32, y as i32, Rgb(c.0 / 2, c.1 / 2, c.2 / 2));
+            }
+        }
+
+        // Panel background, outlined in gold under the mouse as a click on it
+        // restarts.
+        let border = if self.hovers((px, py, panel_w, panel_h)) {
+            BIRD_Y
+        } else {
+            SHADOW
+        };
+        buf.fill_rect(px - 1, py - 1, panel_w + 2, panel_h + 2, border);
+        buf.fill_rect(px, py, panel_w, panel_h, DIRT);
+        buf.fill_rect(px + 1, py + 1, panel_w - 2, panel_h - 2, Rgb(220, 195, 120));
+
+        // "SCORE" label + value
+        let label_color = Rgb(80, 60, 20);
+        let score_label = "SCORE";
+        let score_label_w = text_width_4x6(score_label, 1);
+        draw_text_4x6(
+            buf,
+            cx - score_label_w / 2,
+            py + 3,
+            score_label,
+            label_color,
+            1,
+        );
+        // The score rolls up from zero as the panel opens, and the best on
+        // from the last one when the run beat it.
+        let opened = GAME_OVER_DELAY as u64 + 1;
+        let now = self.dead_timer as u64;
+        RollingNumber::new(0, self.score, opened).draw(buf, cx, py + 10, self.score, now, WHITE);
+        if let Some(medal) = Medal::for_score(self.score) {
+            let (mx, my) = (px + 4, py + 10);
+            let palette = [
+                ('M', medal.color()),
+                ('h', Rgb::lerp(medal.color(), WHITE, 128)),
+            ];
+            buf.blit(&Sprite::from_art(&MEDAL_ART, &palette), mx, my, 1.0, false);
+        }
+
+        // Divider line
+        buf.fill_rect(px + 3, py + panel_h / 2, panel_w - 6, 1, label_color);
+
+        // "BEST" label + value
+        let best_label = "BEST";
+        let best_label_w = text_width_4x6(best_label, 1);
+        draw_text_4x6(
+            buf,
+            cx - best_label_w / 2,
+            py + panel_h / 2 + 2,
+            best_label,
+            label_color,
+            1,
+        );
+        RollingNumber::new(self.best_before, self.best, opened).draw(
+            buf,
+            cx,
+            py + panel_h / 2 + 9,
+            self.best,
+            now,
+            BIRD_Y,
+        );
+
+        // The run's seed code, for replaying the same pipes with FLAPPY_SEED.
+        let seed = encode_seed(self.run_seed).to_uppercase();
+        let seed_w = text_width_4x6(&seed, 1);
+        draw_text_4x6(buf, cx - seed_w / 2, py - 9, &seed, WHITE, 1);
+
+        // Mutator set, so scores from different rule sets aren't confused.
+        let code = self.mutators.code();
+        if !code.is_empty() {
+            let code_w = text_width_4x6(&code, 1);
+            draw_text_4x6(buf, cx - code_w / 2, py + panel_h + 3, &code, WHITE, 1);
+        }
+    }
+}
+// Presents the frame in the back buffer, filling in the status bar if it's on.
+pub fn present(game: &Game, chain: &mut SwapChain) -> io::Result<()> {
+    let fps = chain.fps;
+    if let Some(status) = &mut chain.back.status {
+        *status = game.status_line(fps);
+    }
+    chain.present()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dissolve_ranks_cover_the_whole_range() {
+        let ranks: Vec<u32> = (0..64)
+            .flat_map(|y| (0..64).map(move |x| dissolve_rank(x, y)))
+            .collect();
+        assert!(ranks.iter().all(|r| *r < 256));
+        let mean = ranks.iter().sum::<u32>() as f64 / ranks.len() as f64;
+        assert!((mean - 127.5).abs() < 8.0);
+    }
+
+    #[test]
+    fn too_small_screens_cut_straight_over() {
+        assert!(TransitionKind::between(Screen::Play, Screen::TooSmall).is_none());
+        assert!(TransitionKind::between(Screen::TooSmall, Screen::Title).is_none());
+        assert!(TransitionKind::between(Screen::Title, Screen::Play) == Some(TransitionKind::Wipe));
+    }
+
+    #[test]
+    fn transitions_run_for_a_fixed_number_of_steps() {
+        let mut game = Game::headless(1);
+        let mut buf = PixelBuf::new(game.pw, game.ph);
+        let mut transitions = Transitions::new(game.screen());
+        transitions.draw(&game, &mut buf, 1.0);
+        assert!(!transitions.is_running());
+
+        let before = game.screen();
+        game.step(true);
+        assert!(game.screen() != before);
+        transitions.draw(&game, &mut buf, 1.0);
+        assert!(transitions.is_running());
+        transitions.draw(&game, &mut buf, TRANSITION_FRAMES);
+        assert!(!transitions.is_running());
+    }
+
+    #[test]
+    fn the_replay_draws_the_best_bar_from_before_it_was_passed() {
+        let mut game = Game::headless(42);
+        game.progress_bar = true;
+        game.step(true);
+        game.best = 1;
+        while !game.is_over() && game.score() < 2 {
+            let (x, y) = game.bird();
+            let next = game.obstacles().into_iter().find(|o| o.x + o.w > x);
+            let gap = next.map_or(45.0, |o| (o.top + o.bottom) / 2.0);
+            game.step(game.state == State::Ready || y > gap + 8.0);
+        }
+        assert!(game.best_passed_at.is_some());
+        let mut buf = PixelBuf::new(game.pw, game.ph);
+        for _ in 0..600 {
+            game.step(false);
+            game.draw(&mut buf);
+        }
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn muting_then_restoring_an_earlier_frame_still_draws() {
+        let mut game = Game::headless(3);
+        game.step(true);
+        let earlier = game.snapshot();
+        for _ in 0..10 {
+            game.step(false);
+        }
+        game.set_muted(true);
+        game.restore(&earlier);
+        let mut buf = PixelBuf::new(game.pw, game.ph);
+        game.draw_muted_icon(&mut buf);
+        assert!(game.frame < game.muted_at.unwrap());
+    }
+}
@@ -0,0 +1,143 @@
+//! The opt-in check for a newer release on crates.io.
+
+use std::fs;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+use crate::theme::config_dir;
+
+// ── Update check ────────────────────────────────────────────────────────────
+
+// crates.io is asked at most this often, in seconds; in between, the answer
+// it last gave is reused.
+const UPDATE_CHECK_EVERY: u64 = 24 * 60 * 60;
+
+// Sends the newest version on crates.io if it's newer than this build, from
+// a thread of its own so startup never waits on the network. Only builds
+// with the `update-check` feature and FLAPPY_UPDATE_CHECK=1 ever look.
+pub fn check_for_update(tx: mpsc::UnboundedSender<String>) {
+    if !cfg!(feature = "update-check") || std::env::var("FLAPPY_UPDATE_CHECK").as_deref() != Ok("1")
+    {
+        return;
+    }
+    std::thread::spawn(move || {
+        let Some(latest) = latest_version() else {
+            return;
+        };
+        if is_newer(&latest, env!("CARGO_PKG_VERSION")) {
+            tracing::info!(version = %latest, "update available");
+            let _ = tx.send(latest);
+        }
+    });
+}
+
+// When crates.io was last asked, as `<unix seconds> [version]` with the last
+// version it gave. Failed checks are noted too, so they also wait a day.
+fn update_cache_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("update-check"))
+}
+
+fn latest_version() -> Option<String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = update_cache_path();
+    let cached = path.as_ref().and_then(|path| fs::read_to_string(path).ok());
+    let mut fields = cached.as_deref().unwrap_or("").split_whitespace();
+    let checked: Option<u64> = fields.next().and_then(|s| s.parse().ok());
+    let version = fields.next().map(str::to_string);
+    if checked.is_some_and(|t| now.saturating_sub(t) < UPDATE_CHECK_EVERY) {
+        return version;
+    }
+
+    let fetched = fetch_crate_json().and_then(|json| max_stable_version(&json));
+    let version = fetched.or(version);
+    if let Some(path) = path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let line = match &version {
+            Some(v) => format!("{now} {v}\n"),
+            None => format!("{now}\n"),
+        };
+        let _ = fs::write(path, line);
+    }
+    version
+}
+
+// Asks crates.io with curl, which saves the crate a TLS stack for one request
+// a day. crates.io wants a user agent that says who's asking.
+#[cfg(feature = "update-check")]
+fn fetch_crate_json() -> Option<String> {
+    let agent = format!(
+        "flappy-tui/{} ({})",
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_REPOSITORY")
+    );
+    let output = std::process::Command::new("curl")
+        .args(["-fsS", "--max-time", "10", "-A", &agent])
+        .arg("https://crates.io/api/v1/crates/flappy-tui")
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(output) => {
+            tracing::warn!(status = %output.status, "update check failed");
+            None
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "update check failed");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "update-check"))]
+fn fetch_crate_json() -> Option<String> {
+    None
+}
+
+// Picks `max_stable_version` out of the crate's JSON, which is all we need of it.
+fn max_stable_version(json: &str) -> Option<String> {
+    let key = "\"max_stable_version\":";
+    let rest = json[json.find(key)? + key.len()..].trim_start();
+    let version = rest.strip_prefix('"')?.split('"').next()?;
+    let valid = !version.is_empty()
+        && version.split('.').count() == 3
+        && version.split('.').all(|n| n.parse::<u64>().is_ok());
+    valid.then(|| version.to_string())
+}
+
+// Compares `major.minor.patch` numerically; anything else is never newer.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Option<Vec<u64>> { v.split('.').map(|n| n.parse().ok()).collect() };
+    match (parse(latest), parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("1.0.0", "0.2.1"));
+        assert!(!is_newer("0.2.1", "0.2.1"));
+        assert!(!is_newer("0.2.0", "0.2.1"));
+        assert!(!is_newer("0.3.0-beta", "0.2.1"));
+    }
+
+    #[test]
+    fn reads_the_stable_version_from_crate_json() {
+        let json = r#"{"crate":{"id":"flappy-tui","max_version":"0.4.0-rc.1","max_stable_version": "0.3.2","newest_version":"0.4.0-rc.1"}}"#;
+        assert_eq!(max_stable_version(json).as_deref(), Some("0.3.2"));
+        assert_eq!(max_stable_version(r#"{"max_stable_version":null}"#), None);
+        assert_eq!(max_stable_version(r#"{"errors":[]}"#), None);
+    }
+}
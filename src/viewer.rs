@@ -0,0 +1,296 @@
+//! `flappy-tui scenes`: stages every screen, from the title through a crash
+//! to the menus, and flips between them, the themes and zoom levels with a
+//! key, so UI and theme changes can be checked without playing to each one.
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, terminal,
+};
+use std::io::{self, Write, stdout};
+
+use crate::error::FlappyError;
+use crate::game::{GAME_OVER_DELAY, Game, GameConfig, MIN_COLS, State, load_history};
+use crate::pixels::{CellLayout, ColorMode, PixelBuf, PostFx, render_cells};
+use crate::theme::theme_presets;
+
+// ── Scenes ──────────────────────────────────────────────────────────────────
+
+// Every scene is staged from this seed, so it looks the same each time.
+const SCENES_SEED: u64 = 42;
+const ZOOM_STEP: f64 = 0.25;
+// No staged run flies longer than this, should the autopilot crash early.
+const STAGE_MAX_FRAMES: u32 = 3000;
+
+// A screen to look at, and how a fresh game gets there.
+pub(crate) struct Staged {
+    pub(crate) name: &'static str,
+    pub(crate) stage: fn(&mut Game),
+}
+
+pub(crate) const SCENES: [Staged; 13] = [
+    Staged {
+        name: "TITLE",
+        stage: |_| {},
+    },
+    Staged {
+        name: "PLAYING",
+        stage: |game| fly_to(game, 3),
+    },
+    Staged {
+        name: "BOSS",
+        stage: |game| {
+            game.next_boss = 0;
+            fly_until(game, |game| {
+                let ahead = game.world.pistons.iter().next();
+                ahead.is_some_and(|p| p.x < game.world_w * 0.7)
+            });
+        },
+    },
+    Staged {
+        name: "RUSH HOUR",
+        stage: |game| {
+            game.next_rush = 0;
+            fly_for(game, 30);
+        },
+    },
+    Staged {
+        name: "LOW CEILING",
+        stage: |game| {
+            game.next_ceiling = 0;
+            fly_until(game, |game| {
+                let mut hanging = game.world.hazards.iter().filter(|h| h.kind.hangs());
+                hanging.any(|h| h.x < game.world_w * 0.7)
+            });
+        },
+    },
+    Staged {
+        name: "DYING",
+        stage: |game| {
+            fly_to(game, 3);
+            crash(game);
+            fly_for(game, 4);
+        },
+    },
+    Staged {
+        name: "REPLAY",
+        stage: |game| {
+            fly_to(game, 3);
+            crash(game);
+            fly_until_dead(game);
+            fly_for(game, 40);
+        },
+    },
+    Staged {
+        name: "GAME OVER",
+        stage: |game| {
+            fly_to(game, 3);
+            crash(game);
+            fly_until_dead(game);
+            game.replay = None;
+            fly_for(game, GAME_OVER_DELAY * 4);
+        },
+    },
+    Staged {
+        name: "PAUSED",
+        stage: |game| {
+            fly_to(game, 3);
+            game.open(State::Paused);
+        },
+    },
+    Staged {
+        name: "MUTATORS",
+        stage: Game::open_mutators,
+    },
+    Staged {
+        name: "STATS",
+        stage: Game::open_stats,
+    },
+    Staged {
+        name: "PLAYGROUND",
+        stage: Game::open_playground,
+    },
+    Staged {
+        name: "EDITOR",
+        stage: Game::open_editor,
+    },
+];
+
+fn fly_to(game: &mut Game, score: u32) {
+    fly_until(game, |game| game.score >= score);
+}
+
+// A fresh game to stage a scene in, the same every time.
+pub(crate) fn scene_game(pw: usize, ph: usize) -> Game {
+    let mut game = Game::new(pw, ph, GameConfig::default());
+    game.forced_seed = Some(SCENES_SEED);
+    game.hill_seed = SCENES_SEED;
+    game
+}
+
+// Lets the autopilot fly until `done` says the scene is set.
+fn fly_until(game: &mut Game, done: impl Fn(&Game) -> bool) {
+    for _ in 0..STAGE_MAX_FRAMES {
+        if done(game) || game.state != State::Playing && game.state != State::Ready {
+            return;
+        }
+        game.autopilot();
+        game.update();
+    }
+}
+
+// Steps `frames` times, with the autopilot flying while the run is on.
+fn fly_for(game: &mut Game, frames: u32) {
+    for _ in 0..frames {
+        if matches!(game.state, State::Ready | State::Playing) {
+            game.autopilot();
+        }
+        game.update();
+    }
+}
+
+// Keeps climbing until the bird hits a pipe.
+fn crash(game: &mut Game) {
+    for _ in 0..STAGE_MAX_FRAMES {
+        if game.state != State::Playing {
+            return;
+        }
+        if game.bird_vy >= 0.0 {
+            game.flap();
+        }
+        game.update();
+    }
+}
+
+fn fly_until_dead(game: &mut Game) {
+    for _ in 0..STAGE_MAX_FRAMES {
+        if game.state == State::Dead {
+            return;
+        }
+        game.update();
+    }
+}
+
+// ── Viewer ──────────────────────────────────────────────────────────────────
+
+const SCENES_KEYS: &str = "←→ scene  t theme  +- zoom  q quit";
+
+pub fn view_scenes() -> Result<(), FlappyError> {
+    show_scenes().map_err(FlappyError::terminal("show the scenes"))
+}
+
+fn show_scenes() -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    let mut out = stdout();
+    execute!(
+        out,
+        terminal::EnterAlternateScreen,
+        cursor::Hide,
+        terminal::DisableLineWrap,
+    )?;
+    let result = view(&mut out);
+    execute!(
+        out,
+        terminal::LeaveAlternateScreen,
+        cursor::Show,
+        terminal::EnableLineWrap,
+    )?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn view(out: &mut impl Write) -> io::Result<()> {
+    let themes = theme_presets();
+    let runs = load_history();
+    let layout = CellLayout::from_env();
+    let (color_mode, _) = ColorMode::detect();
+    let (mut scene, mut theme, mut zoom) = (0, 0, 1.0);
+    loop {
+        let (cols, rows) = terminal::size()?;
+        // A row is kept under the pixels for the caption.
+        if cols < MIN_COLS || rows < layout.min_rows() + 1 {
+            execute!(
+                out,
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0),
+            )?;
+            write!(
+                out,
+                "Make the terminal at least {MIN_COLS}x{}.",
+                layout.min_rows() + 1
+            )?;
+        } else {
+            let (pw, ph) = layout.pixel_size(cols, rows - 1);
+            let mut game = scene_game(pw, ph);
+            game.runs = runs.clone();
+            let (theme_name, look) = &themes[theme];
+            game.apply_theme(look.clone());
+            game.set_zoom(zoom);
+            zoom = game.zoom;
+            (SCENES[scene].stage)(&mut game);
+
+            let mut buf = PixelBuf::new(pw, ph);
+            buf.layout = layout;
+            buf.color_mode = color_mode;
+            buf.effects = PostFx::from_env();
+            game.draw(&mut buf);
+            buf.apply_effects();
+            buf.status = Some(format!(
+                " {}/{} {}  ·  {theme_name}  ·  {zoom:.2}x  ·  {SCENES_KEYS}",
+                scene + 1,
+                SCENES.len(),
+                SCENES[scene].name,
+            ));
+            render_cells(&buf, out)?;
+        }
+        out.flush()?;
+
+        let n = SCENES.len();
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                KeyCode::Right | KeyCode::Char(' ' | 'l') => scene = (scene + 1) % n,
+                KeyCode::Left | KeyCode::Char('h') => scene = (scene + n - 1) % n,
+                KeyCode::Char('t') => theme = (theme + 1) % themes.len(),
+                KeyCode::Char('T') => theme = (theme + themes.len() - 1) % themes.len(),
+                KeyCode::Char('+' | '=') => zoom += ZOOM_STEP,
+                KeyCode::Char('-') => zoom -= ZOOM_STEP,
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            },
+            Event::Resize(..) => execute!(out, terminal::Clear(terminal::ClearType::All))?,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_scene_is_staged_in_its_state() {
+        use State::*;
+        let states = [
+            Ready, Playing, Playing, Playing, Playing, Dying, Dead, Dead, Paused, Mutators, Stats,
+            Playground, Editor,
+        ];
+        for (staged, state) in SCENES.iter().zip(states) {
+            let mut game = scene_game(320, 200);
+            (staged.stage)(&mut game);
+            assert!(
+                game.state == state,
+                "{} staged as {:?}",
+                staged.name,
+                game.state
+            );
+            match staged.name {
+                "BOSS" => assert!(game.encounter.is_some()),
+                "RUSH HOUR" => assert!(game.rush.is_some()),
+                "LOW CEILING" => assert!(game.low_ceiling.is_some()),
+                "REPLAY" => assert!(game.replay.is_some()),
+                "GAME OVER" => assert!(game.replay.is_none()),
+                _ => {}
+            }
+        }
+    }
+}
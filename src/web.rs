@@ -0,0 +1,201 @@
+//! The browser backend, behind the `web` feature: `WebGame` runs the game in
+//! a page, built for wasm32-unknown-unknown. Frames come out as the same
+//! escape sequences the terminal gets, for xterm.js to show, and sounds play
+//! through Web Audio.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{AudioBufferSourceNode, AudioContext, AudioScheduledSourceNode};
+
+use crate::audio::{SAMPLE_RATE, Sound, SoundPalette, generate_rush_samples};
+use crate::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use crate::game::{Game, GameConfig, GameEvent, State};
+use crate::input::{FlapInput, KeyLayout, handle_input};
+use crate::pixels::{CellLayout, PixelBuf, render_cells};
+use crate::ui::Transitions;
+
+// ── Game ────────────────────────────────────────────────────────────────────
+
+// A game in a page. The page feeds it keys and animation frames and writes
+// what `frame` returns to the terminal; nothing is saved between visits.
+#[wasm_bindgen]
+pub struct WebGame {
+    game: Game,
+    buf: PixelBuf,
+    transitions: Transitions,
+    flap_input: FlapInput,
+    audio: WebAudio,
+    out: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl WebGame {
+    // A game filling a `cols` by `rows` terminal. The page picks the seed, as
+    // there's no clock to take one from before the first frame.
+    #[wasm_bindgen(constructor)]
+    pub fn new(cols: u16, rows: u16, seed: u64) -> WebGame {
+        let layout = CellLayout::HalfBlock;
+        let (pw, ph) = layout.pixel_size(cols, rows);
+        let mut buf = PixelBuf::new(pw, ph);
+        buf.layout = layout;
+        let mut game = Game::new(pw, ph, GameConfig::default());
+        game.hill_seed = seed;
+        let transitions = Transitions::new(game.screen());
+        WebGame {
+            game,
+            buf,
+            transitions,
+            // Browsers say which key events are repeats, and report releases.
+            flap_input: FlapInput::new(None, true),
+            audio: WebAudio::default(),
+            out: Vec::new(),
+        }
+    }
+
+    // A `keydown` or `keyup`, by its `KeyboardEvent.key`. Returns false once
+    // the player quits, for the page to stop calling.
+    pub fn key(&mut self, key: &str, down: bool, repeat: bool) -> bool {
+        // Browsers only let a page start audio from a key or click.
+        self.audio.wake();
+        let code = match key {
+            " " => KeyCode::Char(' '),
+            "Enter" => KeyCode::Enter,
+            "Escape" => KeyCode::Esc,
+            "Backspace" => KeyCode::Backspace,
+            "Tab" => KeyCode::Tab,
+            "ArrowUp" => KeyCode::Up,
+            "ArrowDown" => KeyCode::Down,
+            "ArrowLeft" => KeyCode::Left,
+            "ArrowRight" => KeyCode::Right,
+            key => match key.chars().collect::<Vec<_>>()[..] {
+                [c] => KeyCode::Char(c.to_ascii_lowercase()),
+                _ => return true,
+            },
+        };
+        let kind = match (down, repeat) {
+            (false, _) => KeyEventKind::Release,
+            (true, true) => KeyEventKind::Repeat,
+            (true, false) => KeyEventKind::Press,
+        };
+        let event = Event::Key(KeyEvent {
+            kind,
+            ..KeyEvent::from(code)
+        });
+        self.handle(event)
+    }
+
+    // The terminal was resized to `cols` by `rows`.
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.handle(Event::Resize(cols, rows));
+    }
+
+    // Steps the game by the `dt` seconds since the last frame, and returns
+    // the next frame to write to the terminal.
+    pub fn frame(&mut self, dt: f64) -> String {
+        self.audio.palette = self.game.sound_palette;
+        for event in self.game.update_dt(dt) {
+            self.audio.play(Sound::Game(event));
+        }
+        let steps = dt / self.game.config().frame_dur.as_secs_f64();
+        self.transitions.draw(&self.game, &mut self.buf, steps);
+        self.buf.apply_effects();
+        self.out.clear();
+        let _ = render_cells(&self.buf, &mut self.out);
+        String::from_utf8_lossy(&self.out).into_owned()
+    }
+}
+
+impl WebGame {
+    fn handle(&mut self, event: Event) -> bool {
+        let mut sounds = Vec::new();
+        let layout = self.buf.layout;
+        let running = handle_input(
+            event,
+            &mut self.game,
+            &mut self.buf,
+            layout,
+            KeyLayout::Standard,
+            &mut self.flap_input,
+            &mut sounds,
+        );
+        self.audio.palette = self.game.sound_palette;
+        for sound in sounds {
+            self.audio.play(sound);
+        }
+        running
+    }
+}
+
+// ── Audio ───────────────────────────────────────────────────────────────────
+
+// Plays the same sounds as `Audio`, each as a buffer of its samples. Without
+// a context, before the first key or where Web Audio is missing, it's silent.
+#[derive(Default)]
+struct WebAudio {
+    ctx: Option<AudioContext>,
+    // The rush hour layer, looping while the rush is on and the run playing.
+    rush: Option<AudioBufferSourceNode>,
+    rush_on: bool,
+    palette: SoundPalette,
+}
+
+impl WebAudio {
+    fn wake(&mut self) {
+        if self.ctx.is_none() {
+            self.ctx = AudioContext::new().ok();
+        }
+    }
+
+    fn play(&mut self, sound: Sound) {
+        match sound {
+            Sound::Game(GameEvent::RushHour(on)) => {
+                self.rush_on = on;
+                self.follow_rush(on);
+            }
+            // The layer stops while paused, and picks up again after.
+            Sound::Game(GameEvent::StateChanged { to, .. }) => {
+                self.rush_on &= matches!(to, State::Playing | State::Paused);
+                self.follow_rush(self.rush_on && to == State::Playing);
+            }
+            sound => {
+                if let (Some(ctx), Some(samples)) = (&self.ctx, sound.samples(&self.palette)) {
+                    start_samples(ctx, &samples, false);
+                }
+            }
+        }
+    }
+
+    fn follow_rush(&mut self, playing: bool) {
+        match (&self.ctx, self.rush.take()) {
+            (Some(ctx), None) if playing => {
+                self.rush = start_samples(
+                    ctx,
+                    &generate_rush_samples(SAMPLE_RATE, &self.palette),
+                    true,
+                );
+            }
+            (_, Some(node)) if playing => self.rush = Some(node),
+            (_, Some(node)) => {
+                let node: &AudioScheduledSourceNode = &node;
+                let _ = node.stop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn start_samples(
+    ctx: &AudioContext,
+    samples: &[f32],
+    looping: bool,
+) -> Option<AudioBufferSourceNode> {
+    let buffer = ctx
+        .create_buffer(1, samples.len() as u32, SAMPLE_RATE as f32)
+        .ok()?;
+    buffer.copy_to_channel(samples, 0).ok()?;
+    let node = ctx.create_buffer_source().ok()?;
+    node.set_buffer(Some(&buffer));
+    node.set_loop(looping);
+    node.connect_with_audio_node(&ctx.destination()).ok()?;
+    node.start().ok()?;
+    Some(node)
+}
@@ -0,0 +1,66 @@
+//! Drives the game through the public headless API only, as a bot or an
+//! outside test would.
+
+use flappy_tui::{Game, State};
+
+// Flaps whenever the bird sinks below the middle of the next gap.
+fn autopilot(game: &Game) -> bool {
+    let (x, y) = game.bird();
+    let next = game.obstacles().into_iter().find(|o| o.x + o.w > x);
+    let gap = next.map_or(45.0, |o| (o.top + o.bottom) / 2.0);
+    game.state == State::Ready || y > gap + 8.0
+}
+
+#[test]
+fn a_bot_scores_and_the_run_ends() {
+    let mut game = Game::headless(42);
+    let mut steps = 0;
+    while !game.is_over() && steps < 20_000 {
+        game.step(autopilot(&game));
+        steps += 1;
+    }
+    assert!(game.score() > 0);
+    while !game.is_over() {
+        game.step(false);
+    }
+    assert!(game.is_over());
+}
+
+#[test]
+fn a_seed_plays_out_the_same() {
+    let play = |seed| {
+        let mut game = Game::headless(seed);
+        for _ in 0..2000 {
+            if game.is_over() {
+                break;
+            }
+            game.step(autopilot(&game));
+        }
+        (game.score(), game.bird())
+    };
+    assert_eq!(play(7), play(7));
+}
+
+#[test]
+fn obstacles_leave_a_gap() {
+    let mut game = Game::headless(1);
+    let mut seen = 0;
+    while !game.is_over() && game.score() < 3 {
+        for o in game.obstacles() {
+            assert!(o.w > 0.0 && o.top < o.bottom);
+            seen += 1;
+        }
+        game.step(autopilot(&game));
+    }
+    assert!(seen > 0);
+}
+
+#[test]
+fn never_flapping_scores_nothing() {
+    let mut game = Game::headless(1);
+    game.step(true);
+    while !game.is_over() {
+        game.step(false);
+    }
+    assert_eq!(game.score(), 0);
+}